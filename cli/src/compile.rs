@@ -1,31 +1,226 @@
-mod source;
+pub mod diagnostic;
+pub mod source;
 
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::{Instant, SystemTime};
 use anyhow::{Context, Error, Result};
-use worldtree_compiler::Model;
+use clap::ValueEnum;
+use log::{debug, LevelFilter};
+use serde_derive::Serialize;
+use worldtree_compiler::{Attribution, Catalog, Level, Mark, Message, Model, ModelParsingResult, Playthrough, Problem, Source, UriReference};
+use crate::compile::diagnostic::DiagnosticRenderer;
+use crate::render::Renderer;
 
-pub fn compile(context: &std::path::PathBuf) -> Result<Model> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+#[derive(Serialize)]
+struct JsonProblem<'a> {
+    level: &'static str,
+    message: &'static str,
+    #[serde(flatten)]
+    attribution: JsonAttribution<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<JsonContext<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonContext<'a> {
+    message: &'static str,
+    #[serde(flatten)]
+    attribution: JsonAttribution<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonAttribution<'a> {
+    source: &'a str,
+    path: &'a str,
+    start: JsonMark,
+    end: JsonMark,
+}
+
+#[derive(Serialize)]
+struct JsonMark {
+    line: u64,
+    column: u64,
+}
+
+impl<'a> From<&'a Attribution> for JsonAttribution<'a> {
+    fn from(attribution: &'a Attribution) -> Self {
+        Self {
+            source: &attribution.source,
+            path: &attribution.path,
+            start: attribution.start_mark.into(),
+            end: attribution.end_mark.into(),
+        }
+    }
+}
+
+impl From<Mark> for JsonMark {
+    fn from(mark: Mark) -> Self {
+        Self {
+            line: mark.line,
+            column: mark.column,
+        }
+    }
+}
+
+pub fn compile(context: &std::path::PathBuf, format: MessageFormat) -> Result<Model> {
+    compile_with_uris(context, format).map(|(model, _)| model)
+}
+
+pub fn compile_with_uris(context: &std::path::PathBuf, format: MessageFormat) -> Result<(Model, Vec<UriReference>)> {
+    let sources = source::gather_sources(context).with_context(|| "Failed to gather sources")?;
+    let start = Instant::now();
+    let (model, problems, uris) = match worldtree_compiler::compile(&sources) {
+        Ok(result) => (result.model, result.problems, result.uris),
+        Err(e) => return Err(Error::msg(format!("Compilation failed: {}", e))),
+    };
+    debug!("Compiled {} source{} in {:.2?} with {} problem{}", sources.len(), if sources.len() == 1 { "" } else { "s" }, start.elapsed(), problems.len(), if problems.len() == 1 { "" } else { "s" });
+
+    report_problems(problems, format);
+    Ok((model, uris))
+}
+
+// Compiles a world with its text replaced by translations from a catalog
+pub fn compile_localized(context: &std::path::PathBuf, catalog: &Catalog, format: MessageFormat) -> Result<(Model, Vec<UriReference>)> {
+    let sources = source::gather_sources(context).with_context(|| "Failed to gather sources")?;
+    let start = Instant::now();
+    let (model, problems, uris) = match worldtree_compiler::compile_localized(&sources, catalog) {
+        Ok(result) => (result.model, result.problems, result.uris),
+        Err(e) => return Err(Error::msg(format!("Compilation failed: {}", e))),
+    };
+    debug!("Compiled {} source{} with {} in {:.2?} with {} problem{}", sources.len(), if sources.len() == 1 { "" } else { "s" }, catalog.path, start.elapsed(), problems.len(), if problems.len() == 1 { "" } else { "s" });
+
+    report_problems(problems, format);
+    Ok((model, uris))
+}
+
+pub fn compile_messages(context: &std::path::PathBuf, format: MessageFormat) -> Result<Vec<Message>> {
     let sources = source::gather_sources(context).with_context(|| "Failed to gather sources")?;
-    let (model, problems) = match worldtree_compiler::compile(&sources) {
-        Ok(result) => (result.model, result.problems),
+    let (messages, problems) = match worldtree_compiler::compile(&sources) {
+        Ok(result) => (result.messages, result.problems),
         Err(e) => return Err(Error::msg(format!("Compilation failed: {}", e))),
     };
 
-    if !problems.is_empty() {
-        for problem in problems {
-            eprintln!("{}", problem.message);
-            eprintln!("    in {}", problem.attribution.source);
-            eprintln!("    at {}", problem.attribution.path);
-            eprintln!("        line {}, column {}", problem.attribution.start_mark.line + 1, problem.attribution.start_mark.column + 1);
-            if let Some(context) = problem.context {
-                eprintln!("{}", context.message);
-                eprintln!("    in {}", context.attribution.source);
-                eprintln!("    at {}", context.attribution.path);
-                eprintln!("        line {}, column {}", context.attribution.start_mark.line + 1, context.attribution.start_mark.column + 1);
+    report_problems(problems, format);
+    Ok(messages)
+}
+
+// Prints problems, exiting if any of them are fatal
+pub fn report_problems(problems: Vec<Problem>, format: MessageFormat) {
+    let fatal = problems.iter().any(|problem| problem.level == Level::Fatal);
+    print_problems(problems, format);
+    if fatal {
+        exit(1);
+    }
+}
+
+pub struct IncrementalCompiler {
+    context: PathBuf,
+    sources: HashMap<PathBuf, (Option<SystemTime>, Source)>,
+}
+
+impl IncrementalCompiler {
+    pub fn new(context: &Path) -> Self {
+        Self {
+            context: context.to_path_buf(),
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn compile(&mut self) -> Result<ModelParsingResult> {
+        let paths = source::gather_sources(&self.context).with_context(|| "Failed to gather sources")?;
+        let start = Instant::now();
+        let mut sources = Vec::new();
+        let mut reused = 0;
+        for path in &paths {
+            let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            match self.sources.remove(path) {
+                Some((cached, source)) if cached.is_some() && cached == modified => {
+                    reused += 1;
+                    sources.push((modified, source));
+                },
+                _ => {
+                    let source = Source::from_path(path).map_err(|e| Error::msg(format!("Compilation failed: {}", e)))?;
+                    sources.push((modified, source));
+                },
             }
         }
-        exit(1);
-    } else {
-        Ok(model)
+
+        let (modified, sources): (Vec<Option<SystemTime>>, Vec<Source>) = sources.into_iter().unzip();
+        let result = worldtree_compiler::compile_sources(&sources);
+        debug!("Compiled {} sources ({} unchanged) in {:.2?}", sources.len(), reused, start.elapsed());
+        self.sources = paths.into_iter().zip(modified.into_iter().zip(sources)).collect();
+        Ok(result)
+    }
+}
+
+pub fn render_problems(problems: &[Problem]) -> Vec<String> {
+    let mut diagnostics = DiagnosticRenderer::new(Renderer::new(false));
+    problems.iter().map(|problem| diagnostics.render(problem)).collect()
+}
+
+pub fn compile_playthroughs(context: &std::path::PathBuf, format: MessageFormat) -> Result<Vec<Playthrough>> {
+    let sources = source::gather_playthroughs(context).with_context(|| "Failed to gather playthroughs")?;
+    let (playthroughs, problems) = match worldtree_compiler::compile_playthroughs(&sources) {
+        Ok(result) => (result.playthroughs, result.problems),
+        Err(e) => return Err(Error::msg(format!("Compilation failed: {}", e))),
+    };
+
+    report_problems(problems, format);
+    Ok(playthroughs)
+}
+
+// Where to write output meant for people, such as play and reports, and whether it's a terminal. With JSON messages
+// that's standard error, so that standard output only has problems, one per line
+pub fn human_output(format: MessageFormat) -> (Box<dyn Write>, bool) {
+    match format {
+        MessageFormat::Human => (Box::new(std::io::stdout().lock()), std::io::stdout().is_terminal()),
+        MessageFormat::Json => (Box::new(std::io::stderr().lock()), std::io::stderr().is_terminal()),
+    }
+}
+
+pub fn print_problems(problems: Vec<Problem>, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => print_human_problems(problems),
+        MessageFormat::Json => print_json_problems(problems),
+    }
+}
+
+fn print_json_problems(problems: Vec<Problem>) {
+    for problem in &problems {
+        let json = JsonProblem {
+            level: match problem.level {
+                Level::Warning => "warning",
+                Level::Fatal => "fatal",
+            },
+            message: problem.message,
+            attribution: (&problem.attribution).into(),
+            help: problem.help.as_deref(),
+            context: problem.context.as_ref().map(|context| JsonContext {
+                message: context.message,
+                attribution: (&context.attribution).into(),
+            }),
+        };
+        println!("{}", serde_json::to_string(&json).unwrap());
+    }
+}
+
+fn print_human_problems(problems: Vec<Problem>) {
+    let renderer = Renderer::new(std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none());
+    let mut diagnostics = DiagnosticRenderer::new(renderer);
+    // --quiet leaves only fatal problems
+    let warnings = log::max_level() >= LevelFilter::Warn;
+    for problem in problems.iter().filter(|problem| warnings || problem.level == Level::Fatal) {
+        eprintln!("{}", diagnostics.render(problem));
     }
 }
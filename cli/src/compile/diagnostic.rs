@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use worldtree_compiler::{Attribution, Level, Problem};
+use crate::render::Renderer;
+
+const MAX_SNIPPET_LINES: usize = 6;
+
+pub struct DiagnosticRenderer {
+    renderer: Renderer,
+    sources: HashMap<String, Option<Vec<String>>>,
+}
+
+impl DiagnosticRenderer {
+    pub fn new(renderer: Renderer) -> Self {
+        Self {
+            renderer,
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn render(&mut self, problem: &Problem) -> String {
+        let mut result = String::new();
+        let level = match problem.level {
+            Level::Warning => self.renderer.yellow("warning"),
+            Level::Fatal => self.renderer.red("error"),
+        };
+        result.push_str(&format!("{}: {}\n", level, self.renderer.bold(problem.message)));
+
+        let gutter = self.gutter_width(&problem.attribution)
+            .max(problem.context.as_ref().map_or(0, |context| self.gutter_width(&context.attribution)));
+        self.render_span(&mut result, &problem.attribution, '^', None, gutter, problem.level);
+
+        if let Some(context) = &problem.context {
+            self.render_span(&mut result, &context.attribution, '-', Some(context.message), gutter, problem.level);
+        }
+
+        let padding = " ".repeat(gutter);
+        if !problem.attribution.path.is_empty() {
+            result.push_str(&format!("{} {} at {}\n", padding, self.renderer.blue("="), problem.attribution.path));
+        }
+        if let Some(help) = &problem.help {
+            result.push_str(&format!("{} {} {}: {}\n", padding, self.renderer.blue("="), self.renderer.bold("help"), help));
+        }
+        result
+    }
+
+    fn render_span(&mut self, result: &mut String, attribution: &Attribution, underline: char, label: Option<&str>, gutter: usize, level: Level) {
+        let renderer = self.renderer;
+        let padding = " ".repeat(gutter);
+        let bar = renderer.blue("|");
+        let lines = self.lines(&attribution.source);
+        let (start, end) = span(attribution, lines);
+        let column = lines.and_then(|lines| lines.get(start.0))
+            .map_or(start.1, |line| start.1 + line.chars().skip(start.1).take_while(|c| c.is_whitespace()).count());
+        result.push_str(&format!("{}{} {}:{}:{}\n", padding, renderer.blue("-->"), attribution.source, start.0 + 1, column + 1));
+
+        let Some(lines) = lines else {
+            if let Some(label) = label {
+                result.push_str(&format!("{} {} {}\n", padding, renderer.blue("="), label));
+            }
+            return;
+        };
+
+        result.push_str(&format!("{} {}\n", padding, bar));
+        let last = end.0.min(start.0 + MAX_SNIPPET_LINES - 1);
+        for line_index in start.0..=last {
+            let Some(line) = lines.get(line_index) else {
+                break;
+            };
+            let length = line.chars().count();
+            let from = if line_index == start.0 { start.1.min(length) } else { 0 };
+            let to = if line_index == end.0 { end.1.min(length) } else { line.trim_end().chars().count() };
+            let from = from + line.chars().skip(from).take(to.saturating_sub(from)).take_while(|c| c.is_whitespace()).count();
+            let width = to.saturating_sub(from).max(1);
+
+            let marker = underline.to_string().repeat(width);
+            let marker = match (underline, level) {
+                ('-', _) => renderer.blue(&marker),
+                (_, Level::Warning) => renderer.yellow(&marker),
+                (_, Level::Fatal) => renderer.red(&marker),
+            };
+            let label = if line_index == last { label.map(|label| format!(" {}", label)).unwrap_or_default() } else { String::new() };
+
+            result.push_str(&format!("{} {} {}\n", renderer.blue(&format!("{:>width$}", line_index + 1, width = gutter)), bar, line));
+            result.push_str(&format!("{} {} {}{}{}\n", padding, bar, " ".repeat(from), marker, label));
+        }
+        if end.0 > last {
+            result.push_str(&format!("{} {}\n", renderer.blue(&format!("{:>width$}", "...", width = gutter)), bar));
+        }
+    }
+
+    fn gutter_width(&self, attribution: &Attribution) -> usize {
+        let (start, end) = span(attribution, None);
+        let last = end.0.min(start.0 + MAX_SNIPPET_LINES - 1);
+        (last + 1).to_string().len().max(3)
+    }
+
+    fn lines(&mut self, source: &str) -> Option<&Vec<String>> {
+        self.sources.entry(source.to_string())
+            .or_insert_with(|| std::fs::read_to_string(source).ok().map(|contents| contents.lines().map(String::from).collect()))
+            .as_ref()
+    }
+}
+
+// Nodes can end in the indentation of the following line, which shouldn't be underlined
+pub fn span(attribution: &Attribution, lines: Option<&Vec<String>>) -> ((usize, usize), (usize, usize)) {
+    let start = (attribution.start_mark.line as usize, attribution.start_mark.column as usize);
+    let mut end = (attribution.end_mark.line as usize, attribution.end_mark.column as usize);
+    while end.0 > start.0 && lines.and_then(|lines| lines.get(end.0)).map_or(end.1 == 0, |line| line.chars().take(end.1).all(char::is_whitespace)) {
+        end = (end.0 - 1, lines.and_then(|lines| lines.get(end.0 - 1)).map_or(usize::MAX, |line| line.trim_end().chars().count()));
+    }
+    (start, end)
+}
@@ -1,13 +1,27 @@
 use std::collections::VecDeque;
 use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
+use log::{info, warn};
 
 pub fn gather_sources(context: &PathBuf) -> Result<Vec<PathBuf>> {
+    info!("Gathering sources...");
+    gather(context, |path| !is_playthrough(path))
+}
+
+pub fn gather_playthroughs(context: &PathBuf) -> Result<Vec<PathBuf>> {
+    info!("Gathering playthroughs...");
+    gather(context, is_playthrough)
+}
+
+pub fn is_playthrough(path: &Path) -> bool {
+    path.file_stem().and_then(|stem| Path::new(stem).extension()).is_some_and(|extension| extension.eq_ignore_ascii_case("test"))
+}
+
+fn gather(context: &PathBuf, filter: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
     let mut paths: Vec<PathBuf> = Vec::new();
     let mut entries: VecDeque<std::io::Result<DirEntry>> = VecDeque::new();
     entries.extend(std::fs::read_dir(context).with_context(|| format!("FATAL Failed to read context {:?}", context))?);
-    eprintln!("Gathering sources...");
     while let Some(entry) = entries.pop_front() {
         match entry {
             Err(_e) => continue,
@@ -17,14 +31,14 @@ pub fn gather_sources(context: &PathBuf) -> Result<Vec<PathBuf>> {
                     if let Ok(dir_entries) = std::fs::read_dir(&path) {
                         entries.extend(dir_entries);
                     } else {
-                        eprintln!("    WARNING Failed to read subdirectory in context {:?}", &path);
+                        warn!("Failed to read subdirectory in context {:?}", &path);
                     }
                 }
 
                 if path.is_file() {
                     if let Some(extension) = path.extension() {
-                        if extension.eq_ignore_ascii_case("yaml") || extension.eq_ignore_ascii_case("yml") {
-                            eprintln!("    {}", (&path).strip_prefix(&context)?.to_str().unwrap_or(""));
+                        if (extension.eq_ignore_ascii_case("yaml") || extension.eq_ignore_ascii_case("yml")) && filter(&path) {
+                            info!("    {}", (&path).strip_prefix(&context)?.to_str().unwrap_or(""));
                             paths.push(path);
                         }
                     }
@@ -32,6 +46,6 @@ pub fn gather_sources(context: &PathBuf) -> Result<Vec<PathBuf>> {
             }
         }
     }
-    eprintln!();
+    info!("");
     Ok(paths)
 }
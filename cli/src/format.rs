@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use log::{error, info};
+use worldtree_compiler::{Source, SourceFormatter};
+use crate::compile::source::{gather_playthroughs, gather_sources, is_playthrough};
+
+// Fails when a file can't be formatted or, when checking, would change
+pub fn format(context: &PathBuf, check: bool) -> Result<bool> {
+    let mut paths = gather_sources(context)?;
+    // Files that don't parse can't contribute names, and are reported when it's their turn to be formatted
+    let sources: Vec<Source> = paths.iter().filter_map(|path| Source::from_path(path).ok()).collect();
+    let formatter = SourceFormatter::new(&sources);
+    paths.extend(gather_playthroughs(context)?);
+
+    let mut changed = 0;
+    let mut failed = 0;
+    for path in &paths {
+        let name = path.strip_prefix(context).unwrap_or(path).display();
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let formatted = if is_playthrough(path) { formatter.format_playthrough(&text) } else { formatter.format_source(&text) };
+        match formatted {
+            Ok(formatted) if formatted != text => {
+                if check {
+                    info!("Would reformat {}", name);
+                } else {
+                    std::fs::write(path, &formatted).with_context(|| format!("Failed to write {:?}", path))?;
+                    info!("Reformatted {}", name);
+                }
+                changed += 1;
+            },
+            Ok(_) => {},
+            Err(e) => {
+                match (e.problem(), e.mark()) {
+                    (Some(problem), Some(mark)) => error!("Failed to format {}:{}:{}: {}", name, mark.line + 1, mark.column + 1, problem),
+                    _ => error!("Failed to format {}: {}", name, e),
+                }
+                failed += 1;
+            },
+        }
+    }
+
+    info!("");
+    if check {
+        info!("{} of {} files would be reformatted", changed, paths.len());
+    } else {
+        info!("{} of {} files reformatted", changed, paths.len());
+    }
+    Ok(failed == 0 && (changed == 0 || !check))
+}
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Error, Result};
+use log::info;
+use worldtree_compiler::{Catalog, CatalogEntry, Language};
+use crate::compile::{self, MessageFormat};
+
+pub const TEMPLATE_FILE_NAME: &str = "messages.pot";
+
+// Writes every translatable text in a world to a catalog. Translations already in the catalog are kept, and marked
+// fuzzy when the text they translate has changed, as msgmerge does
+pub fn extract(context: &PathBuf, output: &Path, format: MessageFormat) -> Result<()> {
+    let messages = compile::compile_messages(context, format).with_context(|| "Failed to compile world")?;
+    let existing = if output.is_file() { Some(read_catalog(output, format)?) } else { None };
+    let language = match &existing {
+        Some(existing) => existing.language.clone(),
+        None => language_from_path(output).map(|language| language.tag().to_string()),
+    };
+
+    let mut catalog = Catalog::new(&output.to_string_lossy(), language);
+    for message in messages {
+        let source = Path::new(message.attribution.source.as_str());
+        let reference = format!("{}:{}", source.strip_prefix(context).unwrap_or(source).display(), message.attribution.start_mark.line + 1).replace('\\', "/");
+        if let Some(entry) = catalog.entries.iter_mut().find(|entry| entry.id == message.id) {
+            entry.references.push(reference);
+            continue;
+        }
+
+        let (translation, fuzzy) = match existing.as_ref().and_then(|existing| existing.get(&message.id)) {
+            Some(previous) => (previous.translation.clone(), previous.fuzzy || (previous.source != message.source && !previous.translation.is_empty())),
+            None => (String::new(), false),
+        };
+        catalog.entries.push(CatalogEntry {
+            id: message.id,
+            source: message.source,
+            translation,
+            fuzzy,
+            references: vec!(reference),
+            attribution: message.attribution,
+        });
+    }
+
+    if let Some(existing) = &existing {
+        let removed = existing.entries.iter().filter(|entry| catalog.get(&entry.id).is_none()).count();
+        if removed > 0 {
+            info!("Removed {} translation{} that no longer match{} any text", removed, if removed == 1 { "" } else { "s" }, if removed == 1 { "es" } else { "" });
+        }
+    }
+
+    if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Could not create directory {:?}", parent))?;
+    }
+    std::fs::write(output, catalog.to_po()).with_context(|| format!("Failed to write catalog {:?}", output))?;
+    info!("{} {} message{}", output.display(), catalog.entries.len(), if catalog.entries.len() == 1 { "" } else { "s" });
+    Ok(())
+}
+
+// Catalogs to build, one per locale, in a stable order
+pub fn gather_catalogs(locales: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(locales).with_context(|| format!("Failed to read locales directory {:?}", locales))? {
+        let path = entry.with_context(|| format!("Failed to read locales directory {:?}", locales))?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("po")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(Error::msg(format!("No catalogs (*.po) found in {:?}. Create one from `worldtree i18n extract`", locales)));
+    }
+    Ok(paths)
+}
+
+pub fn read_catalog(path: &Path, format: MessageFormat) -> Result<Catalog> {
+    let input = std::fs::read_to_string(path).with_context(|| format!("Failed to read catalog {:?}", path))?;
+    let (catalog, problems) = Catalog::parse(&path.to_string_lossy(), &input);
+    compile::report_problems(problems, format);
+    Ok(catalog)
+}
+
+// The catalog's Language header, or else its file name, as with fr.po or pt-BR.po
+pub fn catalog_language(catalog: &Catalog, path: &Path) -> Result<Language> {
+    let tag = match &catalog.language {
+        Some(language) => language.replace('_', "-"),
+        None => path.file_stem().map(|stem| stem.to_string_lossy().replace('_', "-")).unwrap_or_default(),
+    };
+    Language::parse(&tag).map_err(|reason| Error::msg(format!("Catalog {:?} has no valid language: {:?} is not a BCP 47 language tag, because {}", path, tag, reason)))
+}
+
+fn language_from_path(path: &Path) -> Option<Language> {
+    path.extension().filter(|extension| extension.eq_ignore_ascii_case("po"))?;
+    Language::parse(&path.file_stem()?.to_string_lossy().replace('_', "-")).ok()
+}
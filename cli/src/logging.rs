@@ -0,0 +1,40 @@
+use std::time::Instant;
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+lazy_static! {
+    static ref START: Instant = Instant::now();
+}
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+pub fn init(level: LevelFilter) {
+    lazy_static::initialize(&START);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies log through the same facade, but only our own output is interesting
+        metadata.level() <= log::max_level() && metadata.target().starts_with("worldtree")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            Level::Error => eprintln!("error: {}", record.args()),
+            Level::Warn => eprintln!("warning: {}", record.args()),
+            Level::Info => eprintln!("{}", record.args()),
+            Level::Debug | Level::Trace => eprintln!("[{:>8.3}s {}] {}", START.elapsed().as_secs_f32(), record.target(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,534 @@
+mod hover;
+mod protocol;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Error, Result};
+use lazy_static::lazy_static;
+use log::error;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use url::Url;
+use worldtree_compiler::{compile_sources, index_sources, Attribution, Field, FieldKind, Level, Mark, Model, Problem, Source, SourceError, SymbolIndex, SymbolKind};
+use crate::compile::diagnostic::span;
+use crate::compile::source::{gather_sources, is_playthrough};
+use crate::lsp::protocol::{read_message, write_message, CompletionItem, Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, InitializeParams, InlayHintParams, Location, Position, Range, ReferenceParams, RelatedInformation, TextDocumentPositionParams, INTERNAL_ERROR, METHOD_NOT_FOUND};
+
+lazy_static! {
+    // Keys whose values name or refer to symbols, for completing values that don't parse yet
+    static ref SYMBOL_KEY_REGEX: Regex = Regex::new(r"^\s*(?:-\s+)?(?:when|if|unless|go|push|shift|set|unset|increase|decrease|increment|decrement|to|by|limit|shuffle)\s*:").unwrap();
+}
+
+struct CachedSource {
+    text: String,
+    lines: Vec<String>,
+    source: Source,
+}
+
+struct Server {
+    root: Option<PathBuf>,
+    documents: HashMap<PathBuf, String>,
+    sources: HashMap<PathBuf, CachedSource>,
+    index: SymbolIndex,
+    model: Option<Model>,
+    published: HashSet<PathBuf>,
+}
+
+pub fn serve(context: Option<PathBuf>) -> Result<()> {
+    let root = context.map(|context| std::path::absolute(&context).with_context(|| format!("Failed to resolve context {:?}", context))).transpose()?;
+    let mut server = Server {
+        root,
+        documents: HashMap::new(),
+        sources: HashMap::new(),
+        index: SymbolIndex::default(),
+        model: None,
+        published: HashSet::new(),
+    };
+
+    let mut reader = BufReader::new(std::io::stdin().lock());
+    let mut writer = std::io::stdout().lock();
+    while let Some(message) = read_message(&mut reader)? {
+        // Responses to requests from the server aren't needed
+        let Some(method) = message.method else {
+            continue;
+        };
+
+        if method == "exit" {
+            break;
+        }
+
+        if let Some(id) = message.id {
+            let response = match server.request(&method, message.params) {
+                Some(Ok(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Some(Err(e)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": INTERNAL_ERROR, "message": format!("{:#}", e) } }),
+                None => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method {}", method) } }),
+            };
+            write_message(&mut writer, &response)?;
+        } else {
+            match server.notify(&method, message.params) {
+                Ok(notifications) => for notification in notifications {
+                    write_message(&mut writer, &notification)?;
+                },
+                Err(e) => error!("Failed to handle {}: {:#}", method, e),
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: Value) -> Option<Result<Value>> {
+        Some(match method {
+            "initialize" => parse(params).map(|params| self.initialize(params)),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/completion" => parse(params).map(|params| self.completion(params)),
+            "textDocument/definition" => parse(params).map(|params| self.definition(params)),
+            "textDocument/references" => parse(params).map(|params| self.references(params)),
+            "textDocument/hover" => parse(params).map(|params| self.hover(params)),
+            "textDocument/inlayHint" => parse(params).map(|params| self.inlay_hints(params)),
+            _ => return None,
+        })
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<Vec<Value>> {
+        match method {
+            "textDocument/didOpen" => {
+                let params: DidOpenTextDocumentParams = parse(params)?;
+                if let Some(path) = to_path(&params.text_document.uri) {
+                    self.documents.insert(path, params.text_document.text);
+                }
+            },
+            "textDocument/didChange" => {
+                let params: DidChangeTextDocumentParams = parse(params)?;
+                if let (Some(path), Some(change)) = (to_path(&params.text_document.uri), params.content_changes.into_iter().last()) {
+                    self.documents.insert(path, change.text);
+                }
+            },
+            "textDocument/didClose" => {
+                let params: DidCloseTextDocumentParams = parse(params)?;
+                if let Some(path) = to_path(&params.text_document.uri) {
+                    self.documents.remove(&path);
+                }
+            },
+            "initialized" | "textDocument/didSave" | "workspace/didChangeWatchedFiles" => {},
+            _ => return Ok(Vec::new()),
+        }
+
+        Ok(self.rebuild())
+    }
+
+    fn initialize(&mut self, params: InitializeParams) -> Value {
+        if self.root.is_none() {
+            let uri = params.workspace_folders.and_then(|folders| folders.into_iter().next()).map(|folder| folder.uri).or(params.root_uri);
+            self.root = uri.and_then(|uri| to_path(&uri));
+        }
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                "completionProvider": { "triggerCharacters": ["{"] },
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "hoverProvider": true,
+                "inlayHintProvider": true,
+            },
+            "serverInfo": { "name": "worldtree", "version": clap::crate_version!() },
+        })
+    }
+
+    fn rebuild(&mut self) -> Vec<Value> {
+        let mut diagnostics: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+        let mut cached = std::mem::take(&mut self.sources);
+        let mut entries = Vec::new();
+        for path in self.paths() {
+            let Some(text) = self.text(&path) else {
+                continue;
+            };
+
+            match cached.remove(&path) {
+                Some(cached_source) if cached_source.text == text => entries.push((path, cached_source)),
+                previous => match Source::from_string(&path.to_string_lossy(), &text) {
+                    Ok(source) => {
+                        let lines = text.lines().map(String::from).collect();
+                        entries.push((path, CachedSource { text, lines, source }));
+                    },
+                    Err(e) => {
+                        diagnostics.entry(path.clone()).or_default().push(syntax_diagnostic(&text, &e));
+                        // The last version that parsed keeps the rest of the world resolving
+                        if let Some(previous) = previous {
+                            entries.push((path, previous));
+                        }
+                    },
+                },
+            }
+        }
+
+        let mut paths = Vec::new();
+        let mut sources = Vec::new();
+        for (path, CachedSource { text, lines, source }) in entries {
+            paths.push((path, text, lines));
+            sources.push(source);
+        }
+        let result = compile_sources(&sources);
+        self.index = index_sources(&sources);
+        self.sources = paths.into_iter().zip(sources)
+            .map(|((path, text, lines), source)| (path, CachedSource { text, lines, source }))
+            .collect();
+
+        for problem in &result.problems {
+            let diagnostic = self.diagnostic(problem);
+            diagnostics.entry(PathBuf::from(problem.attribution.source.as_str())).or_default().push(diagnostic);
+        }
+        self.model = Some(result.model);
+
+        let mut notifications = Vec::new();
+        for path in self.published.iter().chain(diagnostics.keys()).collect::<HashSet<&PathBuf>>() {
+            if let Some(uri) = to_uri(path) {
+                notifications.push(json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": diagnostics.get(path).map_or(&Vec::new(), |diagnostics| diagnostics) },
+                }));
+            }
+        }
+        self.published = diagnostics.into_keys().collect();
+        notifications
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        if let Some(root) = &self.root {
+            gather_sources(root).unwrap_or_default()
+        } else {
+            let mut paths: Vec<PathBuf> = self.documents.keys()
+                .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("yaml") || extension.eq_ignore_ascii_case("yml")) && !is_playthrough(path))
+                .cloned()
+                .collect();
+            paths.sort();
+            paths
+        }
+    }
+
+    fn text(&self, path: &Path) -> Option<String> {
+        self.documents.get(path).cloned().or_else(|| std::fs::read_to_string(path).ok())
+    }
+
+    fn diagnostic(&self, problem: &Problem) -> Diagnostic {
+        let message = if let Some(help) = &problem.help {
+            format!("{}\nhelp: {}", problem.message, help)
+        } else {
+            problem.message.to_string()
+        };
+
+        Diagnostic {
+            range: self.range(&problem.attribution),
+            severity: match problem.level {
+                Level::Fatal => 1,
+                Level::Warning => 2,
+            },
+            source: "worldtree",
+            message,
+            related_information: problem.context.iter().filter_map(|context| Some(RelatedInformation {
+                location: self.location(&context.attribution)?,
+                message: context.message.to_string(),
+            })).collect(),
+        }
+    }
+
+    fn completion(&self, params: TextDocumentPositionParams) -> Value {
+        let Some(path) = to_path(&params.text_document.uri) else {
+            return json!([]);
+        };
+        let text = self.text(&path).unwrap_or_default();
+        let mark = to_mark(&text, params.position);
+        if !self.completes_symbol(&path, &text, mark) {
+            return json!([]);
+        }
+
+        let mut symbols = HashSet::new();
+        let items: Vec<CompletionItem> = self.index.definitions.iter()
+            .filter(|definition| symbols.insert(definition.symbol.as_str()))
+            .map(|definition| CompletionItem {
+                label: definition.name.clone(),
+                kind: match definition.kind {
+                    SymbolKind::Quality => 6,
+                    SymbolKind::Value => 20,
+                    SymbolKind::Location => 9,
+                    SymbolKind::Storylet => 3,
+                },
+                detail: match definition.kind {
+                    SymbolKind::Quality => "quality",
+                    SymbolKind::Value => "quality value",
+                    SymbolKind::Location => "location",
+                    SymbolKind::Storylet => "storylet",
+                },
+            })
+            .collect();
+        json!(items)
+    }
+
+    fn completes_symbol(&self, path: &Path, text: &str, mark: Mark) -> bool {
+        let field = self.index.fields.iter().find(|field| contains(&field.attribution, path, mark));
+        match field.map(|field| (field.kind, &field.attribution)) {
+            Some((FieldKind::Expression | FieldKind::Name, _)) => true,
+            Some((FieldKind::Template, attribution)) => in_tag(&slice(text, attribution.start_mark, mark)),
+            None => {
+                let line = slice(text, Mark { line: mark.line, column: 0 }, mark);
+                SYMBOL_KEY_REGEX.is_match(&line) || in_tag(&line)
+            },
+        }
+    }
+
+    fn definition(&self, params: TextDocumentPositionParams) -> Value {
+        let Some((symbol, _)) = self.symbol_at(&params.text_document.uri, params.position) else {
+            return Value::Null;
+        };
+
+        let locations: Vec<Location> = self.index.definitions_of(&symbol)
+            .filter_map(|definition| self.location(&definition.attribution))
+            .collect();
+        json!(locations)
+    }
+
+    fn references(&self, params: ReferenceParams) -> Value {
+        let Some((symbol, _)) = self.symbol_at(&params.text_document.uri, params.position) else {
+            return Value::Null;
+        };
+
+        let declarations = self.index.definitions_of(&symbol)
+            .filter(|_| params.context.include_declaration)
+            .map(|definition| &definition.attribution);
+        let locations: Vec<Location> = declarations.chain(self.index.references_to(&symbol).map(|reference| &reference.attribution))
+            .filter_map(|attribution| self.location(attribution))
+            .collect();
+        json!(locations)
+    }
+
+    fn hover(&self, params: TextDocumentPositionParams) -> Value {
+        let (Some((symbol, attribution)), Some(model)) = (self.symbol_at(&params.text_document.uri, params.position), &self.model) else {
+            return Value::Null;
+        };
+
+        let contents: Vec<String> = self.index.definitions_of(&symbol).map(|definition| hover::hover(model, definition)).collect();
+        if contents.is_empty() {
+            return Value::Null;
+        }
+        json!({
+            "contents": { "kind": "markdown", "value": contents.join("\n\n---\n\n") },
+            "range": self.range(attribution),
+        })
+    }
+
+    fn inlay_hints(&self, params: InlayHintParams) -> Value {
+        let Some(path) = to_path(&params.text_document.uri) else {
+            return Value::Null;
+        };
+
+        // Only references whose extent isn't obvious from the text: multi-word names, or names followed by stray words
+        let hints: Vec<Value> = self.index.references.iter()
+            .filter(|reference| Path::new(reference.attribution.source.as_str()) == path && reference.field != FieldKind::Name)
+            .filter(|reference| reference.trailing_words || reference.symbol.contains(' '))
+            .filter_map(|reference| {
+                let range = self.range(&reference.attribution);
+                if range.end < params.range.start || range.start > params.range.end {
+                    return None;
+                }
+
+                let definition = self.index.definitions_of(&reference.symbol).next()?;
+                let mut tooltip = format!("Resolved to the {} `{}`", hover::kind_label(definition.kind), definition.name);
+                if reference.trailing_words {
+                    tooltip.push_str("; the words that follow aren't part of any name");
+                }
+                Some(json!({
+                    "position": range.end,
+                    "label": format!("{}: {}", hover::kind_label(definition.kind), definition.name),
+                    "kind": 1,
+                    "paddingLeft": true,
+                    "tooltip": { "kind": "markdown", "value": tooltip },
+                }))
+            })
+            .collect();
+        json!(hints)
+    }
+
+    fn symbol_at(&self, uri: &str, position: Position) -> Option<(String, &Attribution)> {
+        let path = to_path(uri)?;
+        let mark = to_mark(&self.text(&path)?, position);
+        self.index.references.iter()
+            .filter(|reference| Path::new(reference.attribution.source.as_str()) == path)
+            .find(|reference| contains(&self.in_document(&reference.attribution), &path, mark)).map(|reference| (&reference.symbol, &reference.attribution))
+            .or_else(|| self.index.definitions.iter().find(|definition| contains(&definition.attribution, &path, mark)).map(|definition| (&definition.symbol, &definition.attribution)))
+            .map(|(symbol, attribution)| (symbol.clone(), attribution))
+    }
+
+    fn location(&self, attribution: &Attribution) -> Option<Location> {
+        Some(Location {
+            uri: to_uri(Path::new(attribution.source.as_str()))?,
+            range: self.range(attribution),
+        })
+    }
+
+    fn range(&self, attribution: &Attribution) -> Range {
+        let lines = self.sources.get(Path::new(attribution.source.as_str())).map(|cached_source| &cached_source.lines);
+        let (start, end) = span(&self.in_document(attribution), lines);
+        let line = |index: usize| lines.and_then(|lines| lines.get(index)).map_or("", |line| line.as_str());
+        let start_column = start.1 + line(start.0).chars().skip(start.1).take_while(|c| c.is_whitespace()).count();
+        let start = to_position(line(start.0), start.0, start_column);
+        let end = to_position(line(end.0), end.0, end.1);
+        Range { start, end: end.max(start) }
+    }
+
+    // Attributions within a field count from the start of its value, which YAML has stripped of quotes, indicators and
+    // indentation, and may have folded onto fewer lines
+    fn in_document(&self, attribution: &Attribution) -> Attribution {
+        let path = Path::new(attribution.source.as_str());
+        let Some(lines) = self.sources.get(path).map(|cached_source| &cached_source.lines) else {
+            return attribution.clone();
+        };
+        let Some(field) = self.index.fields.iter().find(|field| contains(&field.attribution, path, attribution.start_mark) && attribution.end_mark <= value_end(field)) else {
+            return attribution.clone();
+        };
+
+        let marks = document_marks(lines, field);
+        let start = value_offset(field, attribution.start_mark);
+        let end = value_offset(field, attribution.end_mark);
+        match (marks.get(start), end.checked_sub(1).and_then(|end| marks.get(end))) {
+            (Some(start_mark), Some(end_mark)) => Attribution {
+                start_mark: *start_mark,
+                end_mark: Mark { line: end_mark.line, column: end_mark.column + 1 },
+                ..attribution.clone()
+            },
+            _ => attribution.clone(),
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T> {
+    serde_json::from_value(params).map_err(|e| Error::msg(format!("Invalid parameters: {}", e)))
+}
+
+fn to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+fn to_uri(path: &Path) -> Option<String> {
+    Url::from_file_path(path).ok().map(String::from)
+}
+
+// Marks count characters, while positions count UTF-16 code units
+fn to_position(line: &str, line_index: usize, column: usize) -> Position {
+    Position {
+        line: line_index as u64,
+        character: line.chars().take(column).map(char::len_utf16).sum::<usize>() as u64,
+    }
+}
+
+fn to_mark(text: &str, position: Position) -> Mark {
+    let line = text.split('\n').nth(position.line as usize).unwrap_or("");
+    let mut units = 0;
+    let column = line.chars().take_while(|c| {
+        units += c.len_utf16() as u64;
+        units <= position.character
+    }).count();
+    Mark { line: position.line, column: column as u64 }
+}
+
+// Where each character of a field's value is in the document, found by matching them in order from the start of the
+// field. Line breaks that were folded into spaces match the spaces, and anything YAML stripped is skipped
+fn document_marks(lines: &[String], field: &Field) -> Vec<Mark> {
+    let start = field.attribution.start_mark;
+    let mut document = lines.iter().enumerate().skip(start.line as usize)
+        .flat_map(|(line, text)| text.chars().chain(std::iter::once('\n')).enumerate().map(move |(column, c)| (Mark { line: line as u64, column: column as u64 }, c)))
+        .skip(start.column as usize);
+    let mut marks = Vec::new();
+    for c in field.source.chars() {
+        match document.find(|(_, d)| *d == c || (c.is_whitespace() && *d == '\n')) {
+            Some((mark, _)) => marks.push(mark),
+            None => break,
+        }
+    }
+    marks
+}
+
+// Marks within a field are relative to its start on its first line, and to the start of the line after that
+fn value_offset(field: &Field, mark: Mark) -> usize {
+    let start = field.attribution.start_mark;
+    let line = mark.line.saturating_sub(start.line) as usize;
+    let column = if line == 0 { mark.column.saturating_sub(start.column) } else { mark.column } as usize;
+    field.source.split('\n').take(line).map(|text| text.chars().count() + 1).sum::<usize>() + column
+}
+
+fn value_end(field: &Field) -> Mark {
+    let last = field.source.split('\n').enumerate().last().map_or((0, 0), |(line, text)| (line, text.chars().count()));
+    field.attribution.at_mark(Mark { line: last.0 as u64, column: last.1 as u64 }).start_mark
+}
+
+fn contains(attribution: &Attribution, path: &Path, mark: Mark) -> bool {
+    Path::new(attribution.source.as_str()) == path && attribution.start_mark <= mark && mark <= attribution.end_mark
+}
+
+fn slice(text: &str, start: Mark, end: Mark) -> String {
+    text.split('\n').enumerate()
+        .skip(start.line as usize)
+        .take((end.line.saturating_sub(start.line) + 1) as usize)
+        .map(|(index, line)| {
+            let from = if index as u64 == start.line { start.column as usize } else { 0 };
+            let to = if index as u64 == end.line { end.column as usize } else { usize::MAX };
+            line.chars().skip(from).take(to.saturating_sub(from)).collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn in_tag(text: &str) -> bool {
+    text.rfind('{').is_some_and(|open| text.rfind('}').is_none_or(|close| close < open))
+}
+
+fn syntax_diagnostic(text: &str, error: &SourceError) -> Diagnostic {
+    let mark = error.mark().unwrap_or_default();
+    let line = text.split('\n').nth(mark.line as usize).unwrap_or("");
+    let start = to_position(line, mark.line as usize, mark.column as usize);
+    let end = to_position(line, mark.line as usize, mark.column as usize + 1);
+    Diagnostic {
+        range: Range { start, end },
+        severity: 1,
+        source: "worldtree",
+        message: error.problem().map_or_else(|| error.to_string(), |problem| format!("Invalid YAML: {}", problem)),
+        related_information: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use worldtree_compiler::{Attribution, Field, FieldKind, Mark};
+    use crate::lsp::{document_marks, value_offset};
+
+    // Where the reference to `coins` in a body is found, given where YAML starts the body's scalar
+    fn find_coins(text: &str, start_mark: Mark) -> Mark {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let source = match lines[0].chars().nth(start_mark.column as usize) {
+            Some('|') => "You see\n{coins} here.\n",
+            _ => "You see {coins} here.",
+        };
+        let field = Field {
+            kind: FieldKind::Template,
+            attribution: Attribution::new("world.yaml", start_mark, Mark { line: 3, column: 0 }),
+            source: source.to_string(),
+        };
+        let (line, column) = if source.contains('\n') { (1, 1) } else { (0, 9) };
+        let mark = field.attribution.at_mark(Mark { line, column }).start_mark;
+        document_marks(&lines, &field)[value_offset(&field, mark)]
+    }
+
+    #[test]
+    fn test_finds_marks_in_multiline_scalars() {
+        let coins = Mark { line: 2, column: 3 };
+        assert_eq!(find_coins("body: |\n  You see\n  {coins} here.\n", Mark { line: 0, column: 6 }), coins);
+        assert_eq!(find_coins("body: >\n  You see\n  {coins} here.\n", Mark { line: 0, column: 6 }), coins);
+        assert_eq!(find_coins("body: You see\n  {coins} here.\n", Mark { line: 0, column: 6 }), Mark { line: 1, column: 3 });
+        assert_eq!(find_coins("body: \"You see\n  {coins} here.\"\n", Mark { line: 0, column: 6 }), Mark { line: 1, column: 3 });
+    }
+}
@@ -0,0 +1,95 @@
+use worldtree_compiler::{normalize, normalize_expression, Model, QualityStyle, SymbolDefinition, SymbolKind, TemplateParse, TemplateParseNode};
+
+pub fn hover(model: &Model, definition: &SymbolDefinition) -> String {
+    let language = model.meta.language();
+    let mut lines = Vec::new();
+    match definition.kind {
+        SymbolKind::Quality => {
+            lines.push(format!("**{}** (quality)", definition.name));
+            if let Some(quality) = model.qualities.iter().find(|quality| normalize(&quality.name, &language) == definition.symbol) {
+                push_template(&mut lines, "Label", &quality.label);
+                push_template(&mut lines, "Singular label", &quality.singular_label);
+                push_template(&mut lines, "Plural label", &quality.plural_label);
+                push_template(&mut lines, "Description", &quality.description);
+                if let Some(style) = &quality.style {
+                    lines.push(format!("Style: {}", style_tags(style).join(", ")));
+                }
+                if quality.hidden {
+                    lines.push("Hidden".to_string());
+                }
+                if let Some(values) = &quality.values {
+                    let values: Vec<String> = values.iter().map(|value| match &value.label {
+                        Some(label) => format!("`{}` ({})", value.name, template(label)),
+                        None => format!("`{}`", value.name),
+                    }).collect();
+                    lines.push(format!("{}: {}", if quality.exclusive { "Exclusive values" } else { "Values" }, values.join(", ")));
+                }
+            }
+        },
+        SymbolKind::Value => {
+            let quality = model.qualities.iter().find(|quality| quality.values.iter().flatten().any(|value| normalize(&value.name, &language) == definition.symbol));
+            if let Some(quality) = quality {
+                lines.push(format!("**{}** (value of `{}`)", definition.name, quality.name));
+                if let Some(value) = quality.values.iter().flatten().find(|value| normalize(&value.name, &language) == definition.symbol) {
+                    push_template(&mut lines, "Label", &value.label);
+                    push_template(&mut lines, "Description", &value.description);
+                }
+            } else {
+                lines.push(format!("**{}** (quality value)", definition.name));
+            }
+        },
+        SymbolKind::Location => {
+            lines.push(format!("**{}** (location)", definition.name));
+            if let Some(location) = model.locations.iter().find(|location| normalize(&location.name, &language) == definition.symbol) {
+                lines.push(format!("Label: {}", template(&location.label)));
+                push_template(&mut lines, "Description", &location.description);
+            }
+        },
+        SymbolKind::Storylet => {
+            lines.push(format!("**{}** (storylet)", definition.name));
+            if let Some(storylet) = model.storylets.iter().find(|storylet| normalize(&storylet.name, &language) == definition.symbol) {
+                push_template(&mut lines, "Label", &storylet.label);
+                if let Some(condition) = &storylet.condition {
+                    lines.push(format!("Condition: `{}`", normalize_expression(condition)));
+                }
+            }
+        },
+    }
+    lines.join("\n\n")
+}
+
+pub fn kind_label(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Quality => "quality",
+        SymbolKind::Value => "quality value",
+        SymbolKind::Location => "location",
+        SymbolKind::Storylet => "storylet",
+    }
+}
+
+fn push_template(lines: &mut Vec<String>, name: &str, parse: &Option<TemplateParse>) {
+    if let Some(parse) = parse {
+        lines.push(format!("{}: {}", name, template(parse)));
+    }
+}
+
+fn style_tags(style: &QualityStyle) -> Vec<&'static str> {
+    [(style.currency, "currency"), (style.personal, "personal"), (style.plural, "plural"), (style.possessive, "possessive"), (style.uncounted, "uncounted")]
+        .into_iter()
+        .filter_map(|(set, tag)| set.then_some(tag))
+        .collect()
+}
+
+fn template(parse: &TemplateParse) -> String {
+    parse.iter().map(|node| match node {
+        TemplateParseNode::Text(text) => text.clone(),
+        TemplateParseNode::Paragraph => " ".to_string(),
+        TemplateParseNode::Italic(inner) => format!("_{}_", template(inner)),
+        TemplateParseNode::Bold(inner) => format!("**{}**", template(inner)),
+        TemplateParseNode::Anchor(href, inner) => format!("[{}]({})", template(inner), href),
+        TemplateParseNode::Branch(condition, then, otherwise) => match otherwise {
+            Some(otherwise) => format!("{{ {} }}{}{{ else }}{}{{ end }}", condition, template(then), template(otherwise)),
+            None => format!("{{ {} }}{}{{ end }}", condition, template(then)),
+        },
+    }).collect::<String>().trim().to_string()
+}
@@ -1,14 +1,29 @@
 mod compile;
 mod package;
 mod error;
+mod format;
+mod i18n;
+mod logging;
+mod lsp;
+mod play;
+mod playthrough;
+mod render;
+mod scaffold;
+mod serve;
+mod simulate;
 
+use std::collections::BTreeMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::process::exit;
+use std::path::{Path, PathBuf};
 use clap::{Parser, Subcommand, crate_version};
 use anyhow::{Context, Error, Result};
+use log::{debug, info, LevelFilter};
 use serde_derive::Deserialize;
-use worldtree_compiler::{Model, Text, TextNode};
-use crate::package::{add_game_icons_credits};
+use worldtree_compiler::{obfuscate_names, obfuscate_names_as, plain, Model, Problem, Simulation};
+use crate::compile::MessageFormat;
+use crate::scaffold::Template;
+use crate::package::{absolute_url, add_game_icons_credits, archive_name, bundle_game_icons, copy_assets, default_game_icons_dir, embed_font, encode_content, icon_type, parse_template, web_manifest, write_archive, write_manifest, write_name_map, ContentEncoding, ModelFormat, MANIFEST_FILE_NAME};
 
 #[derive(Debug, Parser)]
 #[command(name = "worldtree")]
@@ -17,10 +32,39 @@ use crate::package::{add_game_icons_credits};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[arg(long, value_enum, global = true, default_value_t = MessageFormat::Human)]
+    #[arg(help = "Format for reporting problems. `json` prints one object per line to stdout, with zero-based lines and columns, and everything else to stderr")]
+    message_format: MessageFormat,
+    #[arg(short, long, global = true, action = clap::ArgAction::SetTrue, conflicts_with = "verbose")]
+    #[arg(help = "Suppress output other than fatal errors. Conflicts with --verbose")]
+    quiet: bool,
+    #[arg(short, long, global = true, action = clap::ArgAction::SetTrue, conflicts_with = "quiet")]
+    #[arg(help = "Show debug level output, including timings. Conflicts with --quiet")]
+    verbose: bool,
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
+    #[command(about = "Create a new world in a new directory")]
+    New {
+        path: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = Template::Blank)]
+        #[arg(help = "World to start from")]
+        template: Template,
+        #[arg(long)]
+        #[arg(help = "Title of the world. Defaults to the directory name")]
+        title: Option<String>,
+    },
+    #[command(about = "Create a new world in an existing directory, the current directory by default")]
+    Init {
+        path: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t = Template::Blank)]
+        #[arg(help = "World to start from")]
+        template: Template,
+        #[arg(long)]
+        #[arg(help = "Title of the world. Defaults to the directory name")]
+        title: Option<String>,
+    },
     #[command(about = "Compile and package a world")]
     Build {
         context: Option<PathBuf>,
@@ -30,17 +74,83 @@ enum Commands {
         #[arg(short, long)]
         config_file: Option<PathBuf>,
         #[arg(short = 'D', long, visible_alias = "dev", action = clap::ArgAction::SetTrue)]
-        #[arg(help = "Create the package in development mode")]
+        #[arg(help = "Create the package in development mode, rebuilding on changes and serving it with live reload")]
         development: bool,
-        #[arg(short, long, action = clap::ArgAction::SetTrue)]
-        #[arg(help = "Collect output files into a ZIP archive")]
+        #[arg(short, long, default_value_t = 8080)]
+        #[arg(help = "Port to serve the package on in development mode")]
+        port: u16,
+        #[arg(short, long, action = clap::ArgAction::SetTrue, conflicts_with = "development")]
+        #[arg(help = "Collect output files into a ZIP archive, named after the world's title unless `archive_name` is configured")]
         zip: bool,
-        #[arg(short, long, action = clap::ArgAction::SetTrue, conflicts_with = "verbose")]
-        #[arg(help = "Suppress output other than fatal errors. Conflicts with --verbose")]
-        quiet: bool,
-        #[arg(short, long, action = clap::ArgAction::SetTrue, conflicts_with = "quiet")]
-        #[arg(help = "Show debug level output. Conflicts with --quiet")]
-        verbose: bool,
+        #[arg(short, long, conflicts_with = "development")]
+        #[arg(help = "Directory of translated catalogs (*.po). Each one is built into a directory named after its language, alongside the untranslated world")]
+        locales: Option<PathBuf>,
+        #[arg(long, value_enum, conflicts_with = "development")]
+        #[arg(help = "Format to embed the compiled model in, `json` unless obfuscating. `bytecode` is smaller and loads faster, but can't be read by custom templates that expect JSON")]
+        model_format: Option<ModelFormat>,
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "development")]
+        #[arg(help = "Hide spoilers in the embedded model by replacing names with short IDs and obfuscating its text. The original names are written to names.map.json, which isn't included in the ZIP archive")]
+        obfuscate: bool,
+    },
+    #[command(about = "Serve a world locally, rebuilding and reloading it whenever it changes")]
+    Serve {
+        context: Option<PathBuf>,
+        #[arg(short, long)]
+        config_file: Option<PathBuf>,
+        #[arg(short, long, default_value_t = 8080)]
+        #[arg(help = "Port to serve the world on")]
+        port: u16,
+    },
+    #[command(about = "Run a language server for editing a world, communicating over standard input and output")]
+    Lsp {
+        context: Option<PathBuf>,
+    },
+    #[command(about = "Compile a world and play it in the terminal")]
+    Play {
+        context: Option<PathBuf>,
+        #[arg(short, long)]
+        #[arg(help = "Seed for the random number generator, to make a playthrough repeatable")]
+        seed: Option<u64>,
+    },
+    #[command(about = "Compile a world and run its scripted playthroughs (*.test.yaml)")]
+    Test {
+        context: Option<PathBuf>,
+    },
+    #[command(about = "Rewrite a world's content files in canonical style")]
+    Fmt {
+        context: Option<PathBuf>,
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        #[arg(help = "List files that aren't formatted instead of rewriting them, failing if there are any")]
+        check: bool,
+    },
+    #[command(about = "Compile a world and report statistics from randomized playthroughs")]
+    Simulate {
+        context: Option<PathBuf>,
+        #[arg(short, long, default_value_t = 1000)]
+        #[arg(help = "Number of playthroughs to simulate")]
+        runs: usize,
+        #[arg(short = 'n', long, default_value_t = 200)]
+        #[arg(help = "Maximum number of choices to make in each playthrough")]
+        steps: usize,
+        #[arg(short, long, default_value_t = 0)]
+        #[arg(help = "Seed for the random number generator")]
+        seed: u64,
+    },
+    #[command(about = "Manage translations of a world")]
+    I18n {
+        #[command(subcommand)]
+        command: I18nCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum I18nCommands {
+    #[command(about = "Write a gettext catalog of a world's text for translators, keeping any translations already in it")]
+    Extract {
+        context: Option<PathBuf>,
+        #[arg(short, long)]
+        #[arg(help = "Catalog to write. Defaults to locales/messages.pot in the context")]
+        output: Option<PathBuf>,
     },
 }
 
@@ -54,42 +164,44 @@ struct PackageConfig {
     highlight_foreground_color: Option<String>,
     omit_bundled_stylesheet: Option<bool>,
     stylesheet: Option<PathBuf>,
+    template: Option<PathBuf>,
     body_font_family: Option<String>,
     label_font_family: Option<String>,
+    archive_name: Option<String>,
+    game_icons_dir: Option<PathBuf>,
+    canonical_url: Option<String>,
+    twitter_site: Option<String>,
 }
 
-fn to_plain(text: &Text) -> String {
-    let mut result = String::new();
-    for node in text {
-        match node {
-            TextNode::Plain(s) => result.push_str(s),
-            TextNode::Paragraph(t) => {
-                result.push_str(&to_plain(t));
-                result.push(' ');
-            },
-            TextNode::Italic(t) | TextNode::Bold(t) | TextNode::Anchor(_, t) =>
-                result.push_str(&to_plain(t)),
-        }
-    }
-
-    if result.ends_with(' ') {
-        result.truncate(result.len() - 1);
-    }
-
-    result
-}
+// Problems with a user template are added to the problems rather than failing, leaving no HTML
+fn template(content: &Model, config: PackageConfig, google_fonts_params: String, font_faces: String, game_icons: &BTreeMap<String, String>, encoding: ContentEncoding, problems: &mut Vec<Problem>) -> Result<Option<String>> {
+    let user_template = match &config.template {
+        Some(path) => {
+            let (template, template_problems) = parse_template(path)?;
+            problems.extend(template_problems);
+            match template {
+                Some(template) => Some(template),
+                None => return Ok(None),
+            }
+        },
+        None => None,
+    };
 
-fn template(content: &Model, config: PackageConfig, google_fonts_params: String) -> Result<String> {
-    return {
-        let template = liquid::ParserBuilder::with_stdlib().build()?.parse(include_str!("../resources/index.html.liquid"))?;
-        let title = if let Some(title) = &content.meta.title { Some(to_plain(title)) } else { None };
+    let model = {
+        let title = if let Some(title) = &content.meta.title { Some(plain(title)) } else { None };
         let description: String = if let Some(description) = &content.meta.description {
-            to_plain(description)
+            plain(description)
         } else {
             "This world does not have a description.".to_string()
         };
-        let lang = "en-us";
+        let lang = content.meta.lang.as_ref().map(|lang| lang.tag().to_string()).unwrap_or("en".to_string());
         let generator = format!("Worldtree {}", crate_version!());
+        let author = content.meta.author.as_ref().map(plain).unwrap_or_default();
+        let url = config.canonical_url.or(content.meta.url.clone()).unwrap_or_default();
+        let base = if url.is_empty() { None } else { Some(url.as_str()) };
+        let image = content.meta.image.as_deref().map(|image| absolute_url(image, base)).unwrap_or_default();
+        let favicon = content.meta.favicon.clone().unwrap_or_default();
+        let favicon_type = icon_type(&favicon).unwrap_or("");
 
         let user_stylesheet = if let Some(stylesheet) = config.stylesheet {
             std::fs::read_to_string(&stylesheet).with_context(|| format!("Failed to load user stylesheet {:?}", &stylesheet))?
@@ -97,7 +209,7 @@ fn template(content: &Model, config: PackageConfig, google_fonts_params: String)
             String::new()
         };
 
-        let model = liquid::object!({
+        liquid::object!({
             "config": liquid::object!({
                 "stateKey": config.state_key.unwrap_or(String::new()),
                 "backgroundColor": config.background_color.unwrap_or(String::new()),
@@ -110,34 +222,189 @@ fn template(content: &Model, config: PackageConfig, google_fonts_params: String)
                 "bodyFontFamily": config.body_font_family.unwrap_or(String::new()),
                 "labelFontFamily": config.label_font_family.unwrap_or(String::new()),
                 "googleFontsParams": google_fonts_params,
+                "fontFaces": font_faces,
             }),
             "meta": liquid::object!({
                 "title": title,
                 "description": description,
                 "lang": lang,
                 "generator": generator,
+                "author": author,
+                "keywords": content.meta.keywords.join(", "),
+                "url": url,
+                "image": image,
+                "favicon": favicon,
+                "faviconType": favicon_type,
+                "twitterSite": config.twitter_site.unwrap_or_default(),
+                "manifest": MANIFEST_FILE_NAME,
             }),
-            "content": serde_json::to_string(&content)?,
+            "content": encode_content(content, encoding)?,
+            "contentFormat": encoding.format.name(),
+            "gameIcons": serde_json::to_string(game_icons)?,
             "bundle": liquid::object!({
                 "script": include_str!("../../engine/standalone/browser/dist/bundle.js"),
                 "stylesheet": include_str!("../../engine/standalone/browser/dist/bundle.css"),
             }),
-        });
-        template.render(&model)
-    }.with_context(|| "Failed to generate HTML")
+        })
+    };
+
+    match &user_template {
+        Some(template) => Ok(template.render(&model, problems)),
+        None => {
+            let template = liquid::ParserBuilder::with_stdlib().build()?.parse(include_str!("../resources/index.html.liquid"))?;
+            template.render(&model).map(Some).with_context(|| "Failed to generate HTML")
+        },
+    }
+}
+
+fn load_config(config_file: &Option<PathBuf>) -> Result<PackageConfig> {
+    let mut config: PackageConfig = if let Some(config_file) = config_file {
+        let config_string = std::fs::read_to_string(config_file).with_context(|| format!("Failed to read config file {:?}", config_file))?;
+        toml::from_str(config_string.as_str()).with_context(|| format!("Failed to parse config file {:?}", config_file))?
+    } else {
+        PackageConfig {
+            background_color: None,
+            foreground_color: None,
+            important_foreground_color: None,
+            highlight_background_color: None,
+            highlight_foreground_color: None,
+            omit_bundled_stylesheet: None,
+            state_key: None,
+            stylesheet: None,
+            template: None,
+            body_font_family: None,
+            label_font_family: None,
+            archive_name: None,
+            game_icons_dir: None,
+            canonical_url: None,
+            twitter_site: None,
+        }
+    };
+
+    if let Some(stylesheet) = &config.stylesheet {
+        config.stylesheet = Some(config_file.as_ref().unwrap().parent().unwrap().join(stylesheet));
+    }
+
+    if let Some(template) = &config.template {
+        config.template = Some(config_file.as_ref().unwrap().parent().unwrap().join(template));
+    }
+
+    if let Some(body_font_family) = config.body_font_family.as_deref().and_then(|family| family.strip_prefix("local:")) {
+        config.body_font_family = Some(format!("local:{}", config_file.as_ref().unwrap().parent().unwrap().join(body_font_family).display()));
+    }
+
+    if let Some(label_font_family) = config.label_font_family.as_deref().and_then(|family| family.strip_prefix("local:")) {
+        config.label_font_family = Some(format!("local:{}", config_file.as_ref().unwrap().parent().unwrap().join(label_font_family).display()));
+    }
+
+    if let Some(game_icons_dir) = &config.game_icons_dir {
+        config.game_icons_dir = Some(config_file.as_ref().unwrap().parent().unwrap().join(game_icons_dir));
+    }
+
+    if let Some(config_file) = config_file {
+        debug!("Loaded config from {:?}", config_file);
+    }
+
+    Ok(config)
+}
+
+fn package(compiled: &mut Model, mut config: PackageConfig, game_icons: &BTreeMap<String, String>, encoding: ContentEncoding, problems: &mut Vec<Problem>) -> Result<Option<(String, String)>> {
+    add_game_icons_credits(compiled);
+
+    let manifest = web_manifest(
+        compiled.meta.title.as_ref().map(plain).as_deref(),
+        compiled.meta.description.as_ref().map(plain).as_deref(),
+        compiled.meta.favicon.as_deref(),
+        config.background_color.as_deref(),
+    )?;
+
+    let mut google_fonts_params = String::new();
+    let mut font_faces = String::new();
+    let mut local_fonts = BTreeMap::new();
+
+    if let Some(body_font_family) = &config.body_font_family {
+        if body_font_family.starts_with("google-fonts:") {
+            let font_name = &body_font_family[13..];
+            google_fonts_params.push_str(&format!("family={}:ital,wght@0,400;0,700;1,400;1,700", font_name.replace(" ", "+")));
+            config.body_font_family = Some(format!("'{}', serif", font_name.replace("+", " ")));
+        } else if let Some(path) = body_font_family.strip_prefix("local:") {
+            let family = embed_local_font(compiled, path, &mut font_faces, &mut local_fonts)?;
+            config.body_font_family = Some(format!("'{}', serif", family));
+        }
+    }
+
+    if let Some(label_font_family) = &config.label_font_family {
+        if label_font_family.starts_with("google-fonts:") {
+            if !google_fonts_params.is_empty() {
+                google_fonts_params.push('&');
+            }
+            let font_name = &label_font_family[13..];
+            google_fonts_params.push_str(&format!("family={}:ital,wght@0,400;0,700;1,400;1,700", font_name.replace(" ", "+")));
+            config.label_font_family = Some(format!("'{}', sans-serif", font_name.replace("+", " ")));
+        } else if let Some(path) = label_font_family.strip_prefix("local:") {
+            let family = embed_local_font(compiled, path, &mut font_faces, &mut local_fonts)?;
+            config.label_font_family = Some(format!("'{}', sans-serif", family));
+        }
+    }
+
+    let html = template(compiled, config, google_fonts_params, font_faces, game_icons, encoding, problems).with_context(|| "Failed to generate index.html")?;
+    Ok(html.map(|html| (html, manifest)))
+}
+
+// Embeds each local font once, even when it's used for both body and labels, and credits each family once
+fn embed_local_font(compiled: &mut Model, path: &str, font_faces: &mut String, local_fonts: &mut BTreeMap<String, String>) -> Result<String> {
+    if let Some(family) = local_fonts.get(path) {
+        return Ok(family.clone());
+    }
+
+    let font = embed_font(Path::new(path)).with_context(|| format!("Failed to embed font {:?}", path))?;
+    font_faces.push_str(&font.css);
+    if let Some(credit) = font.credit.filter(|_| !local_fonts.values().any(|family| *family == font.family)) {
+        compiled.meta.credits.push(credit);
+    }
+    local_fonts.insert(path.to_string(), font.family.clone());
+    Ok(font.family)
+}
+
+fn write_html(out_dir: &Path, html_string: &str) -> Result<PathBuf> {
+    let html_file_path = out_dir.join("index.html");
+    if html_file_path.exists() {
+        std::fs::remove_file(&html_file_path).with_context(|| format!("Failed to delete existing index.html {:?}", &html_file_path))?;
+    }
+    let html_bytes = html_string.as_bytes();
+    std::fs::File::create(&html_file_path)
+        .with_context(|| format!("Failed to create index.html {:?}", &html_file_path))?
+        .write_all(html_bytes)
+        .with_context(|| "Failed to write index.html")?;
+    info!("{} {:.1}kb", html_file_path.display(), html_bytes.len() as f32 / 1024.0);
+    Ok(html_file_path)
 }
 
 fn main() -> Result<()> {
     let args = Cli::parse();
+    logging::init(if args.quiet { LevelFilter::Error } else if args.verbose { LevelFilter::Debug } else { LevelFilter::Info });
     match args.command {
-        Commands::Build { context, out_dir, config_file, development: _, zip: _, quiet: _, verbose: _} => {
+        Commands::New { path, template, title } => {
+            if path.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+                return Err(Error::msg(format!("Directory {:?} already exists and is not empty. Use `worldtree init` to add a world to it", path)));
+            }
+
+            scaffold::create(&path, template, title)?;
+        },
+        Commands::Init { path, template, title } => {
+            let resolved_path = match path {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Path not provided, and current directory not accessible")
+            }?;
+
+            scaffold::create(&resolved_path, template, title)?;
+        },
+        Commands::Build { context, out_dir, config_file, development, port, zip, locales, model_format, obfuscate } => {
             let resolved_context = match context {
                 Some(path) => Ok(path),
                 None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
             }?;
 
-            let mut compiled = compile::compile(&resolved_context).with_context(|| "Failed to compile world")?;
-
             let resolved_out_dir = match out_dir {
                 Some(path) => Ok(path),
                 None => std::env::current_dir().with_context(|| "Our dir not provided, and current directory not accessible").and_then(|current| Ok(current.join("dist"))),
@@ -146,65 +413,129 @@ fn main() -> Result<()> {
                 return Err(Error::msg("Out dir is not a directory"));
             }
 
-            std::fs::create_dir_all(&resolved_out_dir).with_context(|| format!("Could not create out dir {:?}", &resolved_out_dir))?;
+            let encoding = ContentEncoding {
+                format: model_format.unwrap_or(if obfuscate { ModelFormat::Bytecode } else { ModelFormat::Json }),
+                obfuscate,
+            };
+            if obfuscate && encoding.format == ModelFormat::Json {
+                return Err(Error::msg("Obfuscation requires `--model-format bytecode`, as the JSON format can't hide the world's text"));
+            }
 
-            // package_game_icons(&mut compiled, &resolved_out_dir).with_context(|| "Failed to download Game Icons")?;
-            add_game_icons_credits(&mut compiled);
+            std::fs::create_dir_all(&resolved_out_dir).with_context(|| format!("Could not create out dir {:?}", &resolved_out_dir))?;
 
-            let mut config: PackageConfig = if let Some(config_file) = &config_file {
-                let config_string = std::fs::read_to_string(config_file).with_context(|| format!("Failed to read config file {:?}", config_file))?;
-                toml::from_str(config_string.as_str()).with_context(|| format!("Failed to parse config file {:?}", config_file))?
+            if development {
+                serve::serve(&resolved_context, config_file, Some(resolved_out_dir), port, args.message_format)?;
             } else {
-                PackageConfig {
-                    background_color: None,
-                    foreground_color: None,
-                    important_foreground_color: None,
-                    highlight_background_color: None,
-                    highlight_foreground_color: None,
-                    omit_bundled_stylesheet: None,
-                    state_key: None,
-                    stylesheet: None,
-                    body_font_family: None,
-                    label_font_family: None,
-                }
-            };
+                let (mut compiled, uris) = compile::compile_with_uris(&resolved_context, args.message_format).with_context(|| "Failed to compile world")?;
+                let config = load_config(&config_file)?;
 
-            let mut google_fonts_params = String::new();
+                let game_icons_dir = config.game_icons_dir.clone().or_else(default_game_icons_dir);
+                let (game_icons, problems) = bundle_game_icons(&compiled, &uris, game_icons_dir.as_deref()).with_context(|| "Failed to bundle game icons")?;
+                compile::report_problems(problems, args.message_format);
+                let archive_name = archive_name(config.archive_name.as_deref(), compiled.meta.title.as_ref().map(plain).as_deref());
+                // Translations share the default build's IDs, so the one name map covers every locale
+                let names = if obfuscate {
+                    let names = obfuscate_names(&mut compiled);
+                    write_name_map(&resolved_out_dir, &names)?;
+                    Some((names, compiled.meta.language()))
+                } else {
+                    None
+                };
+                let mut problems = Vec::new();
+                let packaged = package(&mut compiled, config, &game_icons, encoding, &mut problems)?;
+                compile::report_problems(problems, args.message_format);
+                let (html_string, manifest_string) = packaged.ok_or_else(|| Error::msg("Failed to generate index.html"))?;
+                let mut outputs = vec!(write_html(&resolved_out_dir, &html_string)?, write_manifest(&resolved_out_dir, &manifest_string)?);
+                outputs.extend(copy_assets(&resolved_context, &compiled, &resolved_out_dir)?);
 
-            if let Some(body_font_family) = &config.body_font_family {
-                if body_font_family.starts_with("google-fonts:") {
-                    let font_name = &body_font_family[13..];
-                    google_fonts_params.push_str(&format!("family={}:ital,wght@0,400;0,700;1,400;1,700", font_name.replace(" ", "+")));
-                    config.body_font_family = Some(format!("'{}', serif", font_name.replace("+", " ")));
-                }
-            }
+                if let Some(locales) = locales {
+                    for catalog_path in i18n::gather_catalogs(&locales)? {
+                        let catalog = i18n::read_catalog(&catalog_path, args.message_format)?;
+                        let language = i18n::catalog_language(&catalog, &catalog_path)?;
+                        let (mut localized, _) = compile::compile_localized(&resolved_context, &catalog, args.message_format)
+                            .with_context(|| format!("Failed to compile world with {:?}", catalog_path))?;
+                        localized.meta.lang = Some(language.clone());
+                        if let Some((names, names_language)) = &names {
+                            obfuscate_names_as(&mut localized, names, names_language);
+                        }
 
-            if let Some(label_font_family) = &config.label_font_family {
-                if label_font_family.starts_with("google-fonts:") {
-                    if !google_fonts_params.is_empty() {
-                        google_fonts_params.push('&');
+                        let locale_out_dir = resolved_out_dir.join(language.tag());
+                        std::fs::create_dir_all(&locale_out_dir).with_context(|| format!("Could not create out dir {:?}", &locale_out_dir))?;
+                        let mut problems = Vec::new();
+                        let packaged = package(&mut localized, load_config(&config_file)?, &game_icons, encoding, &mut problems)?;
+                        compile::report_problems(problems, args.message_format);
+                        let (html_string, manifest_string) = packaged.ok_or_else(|| Error::msg("Failed to generate index.html"))?;
+                        outputs.push(write_html(&locale_out_dir, &html_string)?);
+                        outputs.push(write_manifest(&locale_out_dir, &manifest_string)?);
+                        outputs.extend(copy_assets(&resolved_context, &localized, &locale_out_dir)?);
                     }
-                    let font_name = &label_font_family[13..];
-                    google_fonts_params.push_str(&format!("family={}:ital,wght@0,400;0,700;1,400;1,700", font_name.replace(" ", "+")));
-                    config.label_font_family = Some(format!("'{}', sans-serif", font_name.replace("+", " ")));
+                }
+
+                if zip {
+                    write_archive(&resolved_out_dir, &archive_name, &outputs)?;
                 }
             }
+        },
+        Commands::Serve { context, config_file, port } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
+
+            serve::serve(&resolved_context, config_file, None, port, args.message_format)?;
+        },
+        Commands::Play { context, seed } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
+
+            let compiled = compile::compile(&resolved_context, args.message_format).with_context(|| "Failed to compile world")?;
+            play::play(&compiled, seed, args.message_format)?;
+        },
+        Commands::Lsp { context } => {
+            lsp::serve(context)?;
+        },
+        Commands::Test { context } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
 
-            if let Some(stylesheet) = &config.stylesheet {
-                config.stylesheet = Some(config_file.unwrap().parent().unwrap().join(stylesheet));
+            let compiled = compile::compile(&resolved_context, args.message_format).with_context(|| "Failed to compile world")?;
+            let playthroughs = compile::compile_playthroughs(&resolved_context, args.message_format).with_context(|| "Failed to compile playthroughs")?;
+            if !playthrough::run_playthroughs(&compiled, &playthroughs) {
+                exit(1);
             }
+        },
+        Commands::Fmt { context, check } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
 
-            let html_file_path = resolved_out_dir.join("index.html");
-            if html_file_path.exists() {
-                std::fs::remove_file(&html_file_path).with_context(|| format!("Failed to delete existing index.html {:?}", &html_file_path))?;
+            if !format::format(&resolved_context, check)? {
+                exit(1);
             }
-            let html_string = template(&compiled, config, google_fonts_params).with_context(|| "Failed to generate index.html")?;
-            let html_bytes = html_string.as_bytes();
-            std::fs::File::create(&html_file_path)
-                .with_context(|| format!("Failed to create index.html {:?}", &html_file_path))?
-                .write_all(html_bytes)
-                .with_context(|| "Failed to write index.html")?;
-            eprintln!("{} {:.1}kb", html_file_path.display(), html_bytes.len() as f32 / 1024.0);
+        },
+        Commands::Simulate { context, runs, steps, seed } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
+
+            let compiled = compile::compile(&resolved_context, args.message_format).with_context(|| "Failed to compile world")?;
+            let report = Simulation::new(&compiled).with_runs(runs).with_steps(steps).with_seed(seed).run();
+            simulate::print_report(&mut compile::human_output(args.message_format).0, &compiled, &report)?;
+        },
+        Commands::I18n { command: I18nCommands::Extract { context, output } } => {
+            let resolved_context = match context {
+                Some(path) => Ok(path),
+                None => std::env::current_dir().with_context(|| "Context not provided, and current directory not accessible")
+            }?;
+
+            let output = output.unwrap_or_else(|| resolved_context.join("locales").join(i18n::TEMPLATE_FILE_NAME));
+            i18n::extract(&resolved_context, &output, args.message_format)?;
         },
     }
     Ok(())
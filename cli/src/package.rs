@@ -1,3 +1,15 @@
+mod archive;
+mod assets;
+mod fonts;
 mod game_icons;
+mod manifest;
+mod model;
+mod template;
 
+pub use crate::package::archive::*;
+pub use crate::package::assets::*;
+pub use crate::package::fonts::*;
 pub use crate::package::game_icons::*;
+pub use crate::package::manifest::*;
+pub use crate::package::model::*;
+pub use crate::package::template::*;
@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use zip::{CompressionMethod, DateTime, ZipWriter};
+use zip::write::SimpleFileOptions;
+
+pub fn archive_name(configured: Option<&str>, title: Option<&str>) -> String {
+    let name = match configured {
+        Some(configured) => configured.to_string(),
+        None => title.map(slug).filter(|slug| !slug.is_empty()).unwrap_or_else(|| "world".to_string()),
+    };
+
+    if name.to_lowercase().ends_with(".zip") {
+        name
+    } else {
+        format!("{}.zip", name)
+    }
+}
+
+pub fn slug(title: &str) -> String {
+    let mut result = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            result.extend(c.to_lowercase());
+        } else if !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+    }
+
+    result.trim_end_matches('-').to_string()
+}
+
+pub fn write_archive(out_dir: &Path, name: &str, files: &[PathBuf]) -> Result<PathBuf> {
+    let archive_path = out_dir.join(name);
+    let mut entries = Vec::new();
+    for file in files {
+        let entry_name = file.strip_prefix(out_dir).unwrap_or(file).components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        entries.push((entry_name, file));
+    }
+    entries.sort();
+    entries.dedup_by(|a, b| a.0 == b.0);
+
+    // A fixed timestamp and mode keep repeated builds byte-identical
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+
+    let archive_file = std::fs::File::create(&archive_path).with_context(|| format!("Failed to create archive {:?}", &archive_path))?;
+    let mut archive = ZipWriter::new(archive_file);
+    for (entry_name, file) in entries {
+        let contents = std::fs::read(file).with_context(|| format!("Failed to read {:?}", file))?;
+        debug!("Adding {} ({} bytes) to archive", entry_name, contents.len());
+        archive.start_file(entry_name.as_str(), options).with_context(|| format!("Failed to add {} to archive", entry_name))?;
+        archive.write_all(&contents).with_context(|| format!("Failed to add {} to archive", entry_name))?;
+    }
+    archive.finish().with_context(|| format!("Failed to write archive {:?}", &archive_path))?;
+
+    let size = std::fs::metadata(&archive_path).map_or(0, |metadata| metadata.len());
+    info!("{} {:.1}kb", archive_path.display(), size as f32 / 1024.0);
+    Ok(archive_path)
+}
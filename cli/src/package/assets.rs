@@ -0,0 +1,35 @@
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Context, Result};
+use log::info;
+use url::Url;
+use worldtree_compiler::Model;
+
+// Images the meta names by a path in the world rather than by URL. They're copied to the same path beside index.html, so
+// the favicon, the manifest's icon and the cover image resolve wherever the package is published
+pub fn local_assets(model: &Model) -> Vec<&str> {
+    let mut assets: Vec<&str> = model.meta.favicon.iter().chain(&model.meta.image)
+        .map(String::as_str)
+        .filter(|path| is_local(path))
+        .collect();
+    assets.dedup();
+    assets
+}
+
+fn is_local(path: &str) -> bool {
+    Url::parse(path).is_err() && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+pub fn copy_assets(context: &Path, model: &Model, out_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut outputs = Vec::new();
+    for asset in local_assets(model) {
+        let source = context.join(asset);
+        let destination = out_dir.join(asset);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Could not create out dir {:?}", parent))?;
+        }
+        let size = std::fs::copy(&source, &destination).with_context(|| format!("Failed to copy {:?} to {:?}", &source, &destination))?;
+        info!("{} {:.1}kb", destination.display(), size as f32 / 1024.0);
+        outputs.push(destination);
+    }
+    Ok(outputs)
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Error, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use log::{debug, warn};
+use worldtree_compiler::{Text, TextNode};
+
+pub struct EmbeddedFont {
+    pub family: String,
+    pub css: String,
+    pub credit: Option<Text>,
+}
+
+// Extensions in order of preference, when a face is available in more than one format
+const FORMATS: [(&str, &str, &str); 4] = [
+    ("woff2", "font/woff2", "woff2"),
+    ("woff", "font/woff", "woff"),
+    ("ttf", "font/ttf", "truetype"),
+    ("otf", "font/otf", "opentype"),
+];
+
+// Weights other than the regular and bold ones that Google Fonts families are requested with
+const OTHER_WEIGHTS: [&str; 11] = ["thin", "hairline", "extralight", "ultralight", "light", "medium", "semibold", "demibold", "extrabold", "ultrabold", "black"];
+
+const LICENSE_NAMES: [&str; 4] = ["ofl", "license", "licence", "copying"];
+
+// Embeds the regular, bold, italic and bold italic faces of a font, given either one of its files or a directory containing them
+pub fn embed_font(path: &Path) -> Result<EmbeddedFont> {
+    let (family, files, license_dir) = if path.is_dir() {
+        let family = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path).with_context(|| format!("Failed to read font directory {:?}", path))? {
+            let entry = entry.with_context(|| format!("Failed to read font directory {:?}", path))?;
+            files.push(entry.path());
+        }
+        files.sort();
+        (family, files, path.to_path_buf())
+    } else {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let family = stem.split(['-', '[']).next().unwrap_or(&stem).to_string();
+        (family, vec!(path.to_path_buf()), path.parent().map(Path::to_path_buf).unwrap_or_default())
+    };
+
+    let mut faces: BTreeMap<(String, bool), (usize, PathBuf)> = BTreeMap::new();
+    for file in files {
+        let extension = file.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let Some(rank) = FORMATS.iter().position(|(candidate, _, _)| *candidate == extension) else {
+            continue;
+        };
+        // The family name is left out, so that names like Highlight aren't mistaken for a weight
+        let stem = file.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default()
+            .replacen(&family.to_lowercase().replace(' ', ""), "", 1);
+        let Some(face) = face(&stem) else {
+            debug!("Skipping font file {:?}, which is not a regular or bold face", file);
+            continue;
+        };
+        if faces.get(&face).is_none_or(|(existing, _)| rank < *existing) {
+            faces.insert(face, (rank, file));
+        }
+    }
+
+    if faces.is_empty() {
+        return Err(Error::msg(format!("No font files (woff2, woff, ttf or otf) found at {:?}", path)));
+    }
+
+    let mut css = String::new();
+    for ((weight, italic), (rank, file)) in &faces {
+        let (_, mime, format) = FORMATS[*rank];
+        let bytes = std::fs::read(file).with_context(|| format!("Failed to read font file {:?}", file))?;
+        css.push_str(&format!(
+            "@font-face{{font-family:'{}';font-style:{};font-weight:{};font-display:swap;src:url(data:{};base64,{}) format('{}');}}\n",
+            family.replace('\'', "\\'"),
+            if *italic { "italic" } else { "normal" },
+            weight,
+            mime,
+            BASE64_STANDARD.encode(&bytes),
+            format,
+        ));
+        debug!("Embedded font file {:?}", file);
+    }
+
+    let credit = license_credit(&family, &license_dir)?;
+    if credit.is_none() {
+        warn!("No license file found for font {:?}. Add its OFL.txt or LICENSE file alongside it to credit it", family);
+    }
+
+    Ok(EmbeddedFont { family, css, credit })
+}
+
+// Returns the CSS weight and whether the face is italic, for the faces Google Fonts families are requested with
+fn face(stem: &str) -> Option<(String, bool)> {
+    let italic = stem.contains("italic");
+    if stem.contains('[') {
+        return Some(("400 700".to_string(), italic));
+    }
+
+    if OTHER_WEIGHTS.iter().any(|weight| stem.contains(weight)) {
+        None
+    } else if stem.contains("bold") {
+        Some(("700".to_string(), italic))
+    } else {
+        Some(("400".to_string(), italic))
+    }
+}
+
+fn license_credit(family: &str, dir: &Path) -> Result<Option<Text>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(None);
+    };
+
+    let mut licenses: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+        path.is_file() && LICENSE_NAMES.contains(&stem.as_str())
+    }).collect();
+    licenses.sort();
+
+    let Some(license) = licenses.first() else {
+        return Ok(None);
+    };
+
+    let contents = std::fs::read_to_string(license).with_context(|| format!("Failed to read font license {:?}", license))?;
+    let mut credit: Text = vec!(TextNode::Plain(format!("{} font", family)));
+    if let Some(copyright) = contents.lines().map(str::trim).find(|line| line.to_lowercase().starts_with("copyright")) {
+        credit.push(TextNode::Plain(format!(", {}", copyright.trim_end_matches(['.', ',']))));
+    }
+
+    let license_name = license.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if license_name == "ofl" || contents.contains("SIL Open Font License") {
+        credit.push(TextNode::Plain(", licensed under the ".to_string()));
+        credit.push(TextNode::Anchor("https://openfontlicense.org".to_string(), vec!(TextNode::Plain("SIL Open Font License".to_string()))));
+    } else if contents.contains("Apache License") {
+        credit.push(TextNode::Plain(", licensed under the ".to_string()));
+        credit.push(TextNode::Anchor("https://www.apache.org/licenses/LICENSE-2.0".to_string(), vec!(TextNode::Plain("Apache License 2.0".to_string()))));
+    }
+
+    Ok(Some(credit))
+}
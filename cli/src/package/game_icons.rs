@@ -1,7 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
 use lazy_static::lazy_static;
+use log::debug;
 use url::Url;
-use worldtree_compiler::{Conditional, Model, Text, TextNode};
+use worldtree_compiler::{Conditional, Model, Problem, Text, TextNode, UriReference};
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash)]
 struct ArtistInfo {
@@ -97,9 +101,17 @@ fn gather_icons(icon: &Conditional<String>) -> Vec<String> {
     }
 }
 
-fn gather_game_icons(model: &Model) -> Vec<String> {
+fn game_icon_path(uri: &str) -> Option<String> {
     let base_url = Url::parse("https://www.example.com/").unwrap();
-    let url_options = Url::options().base_url(Some(&base_url));
+    let url = Url::options().base_url(Some(&base_url)).parse(uri).ok()?;
+    if url.scheme() == "game-icons" {
+        Some(url.path().to_owned())
+    } else {
+        None
+    }
+}
+
+fn gather_game_icons(model: &Model) -> Vec<String> {
     let mut game_icons_set = HashSet::new();
 
     let mut urls = Vec::new();
@@ -120,17 +132,83 @@ fn gather_game_icons(model: &Model) -> Vec<String> {
         }
     }
 
-    for url in urls {
-        if let Ok(url) = url_options.parse(&url) {
-            if url.scheme() == "game-icons" {
-                game_icons_set.insert(url.path().to_owned());
+    for quality in &model.qualities {
+        if let Some(icon) = &quality.icon {
+            urls.extend(gather_icons(icon));
+        }
+
+        for value in quality.values.iter().flatten() {
+            if let Some(icon) = &value.icon {
+                urls.extend(gather_icons(icon));
             }
         }
     }
 
+    for url in urls {
+        if let Some(icon) = game_icon_path(&url) {
+            game_icons_set.insert(icon);
+        }
+    }
+
     game_icons_set.iter().map(|icon| String::from(icon)).collect()
 }
 
+// Looks for icons laid out as in https://github.com/game-icons/icons, i.e. `<artist>/<name>.svg`
+pub fn default_game_icons_dir() -> Option<PathBuf> {
+    let cache_dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(cache_dir) => PathBuf::from(cache_dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache_dir.join("worldtree").join("game-icons"))
+}
+
+pub fn bundle_game_icons(model: &Model, uris: &[UriReference], icons_dir: Option<&Path>) -> Result<(BTreeMap<String, String>, Vec<Problem>)> {
+    let mut icons = BTreeMap::new();
+    let mut problems = Vec::new();
+    let mut names = gather_game_icons(model);
+    if names.is_empty() {
+        return Ok((icons, problems));
+    }
+    names.sort();
+
+    // The engine loads icons that aren't bundled from the web, so a world still builds without them
+    let icons_dir = match icons_dir.filter(|icons_dir| icons_dir.is_dir()) {
+        Some(icons_dir) => icons_dir,
+        None => {
+            let help = match icons_dir {
+                Some(icons_dir) => format!("Game icons will be loaded from the web. To bundle them for offline play, clone https://github.com/game-icons/icons to {:?}, or set `game_icons_dir` in the config file", icons_dir),
+                None => "Game icons will be loaded from the web. To bundle them for offline play, set `game_icons_dir` in the config file to a clone of https://github.com/game-icons/icons".to_string(),
+            };
+            if let Some(uri) = uris.iter().find(|uri| game_icon_path(&uri.uri).is_some()) {
+                problems.push(Problem::warning("Game icons aren't bundled", &uri.attribution).with_help(help));
+            }
+            return Ok((icons, problems));
+        },
+    };
+
+    let mut unknown = HashSet::new();
+    for name in names {
+        let path = icons_dir.join(format!("{}.svg", name));
+        let known = !name.split('/').any(|segment| segment.is_empty() || segment == "..") && path.is_file();
+        if known {
+            let svg = std::fs::read(&path).with_context(|| format!("Failed to read game icon {:?}", &path))?;
+            icons.insert(name, format!("data:image/svg+xml;base64,{}", BASE64_STANDARD.encode(svg)));
+        } else {
+            unknown.insert(name);
+        }
+    }
+
+    debug!("Bundled {} game icon{} from {:?}", icons.len(), if icons.len() == 1 { "" } else { "s" }, icons_dir);
+    for uri in uris {
+        if let Some(name) = game_icon_path(&uri.uri).filter(|name| unknown.contains(name)) {
+            problems.push(Problem::fatal("Unknown game icon", &uri.attribution)
+                .with_help(format!("No icon named `{}` was found in {:?}. Check the spelling against https://game-icons.net", name, icons_dir)));
+        }
+    }
+
+    Ok((icons, problems))
+}
+
 pub fn add_game_icons_credits(model: &mut Model) {
     let icons = gather_game_icons(model);
 
@@ -186,3 +264,44 @@ pub fn add_game_icons_credits(model: &mut Model) {
         model.meta.credits.push(cczero_artist_list);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use worldtree_compiler::{compile_sources, Level, ModelParsingResult, Source};
+    use super::bundle_game_icons;
+
+    fn compile() -> ModelParsingResult {
+        let world = "version: 0.1\nqualities:\n  - name: coins\n    icon: game-icons:lorc/coins\n";
+        compile_sources(&vec!(Source::from_string("world.yaml", world).unwrap()))
+    }
+
+    #[test]
+    fn test_builds_without_icons() {
+        let result = compile();
+        let missing = std::env::temp_dir().join(format!("worldtree-no-game-icons-{}", std::process::id()));
+        for icons_dir in [None, Some(missing.as_path())] {
+            let (icons, problems) = bundle_game_icons(&result.model, &result.uris, icons_dir).unwrap();
+            assert!(icons.is_empty());
+            assert_eq!(problems.len(), 1, "{:?}", problems);
+            assert_eq!(problems[0].level, Level::Warning);
+            assert_eq!(problems[0].message, "Game icons aren't bundled");
+        }
+    }
+
+    #[test]
+    fn test_reports_unknown_icons() {
+        let result = compile();
+        let icons_dir = std::env::temp_dir().join(format!("worldtree-game-icons-{}", std::process::id()));
+        std::fs::create_dir_all(icons_dir.join("lorc")).unwrap();
+        let (icons, problems) = bundle_game_icons(&result.model, &result.uris, Some(&icons_dir)).unwrap();
+        assert!(icons.is_empty());
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert_eq!(problems[0].level, Level::Fatal);
+        assert_eq!(problems[0].message, "Unknown game icon");
+
+        std::fs::write(icons_dir.join("lorc").join("coins.svg"), "<svg/>").unwrap();
+        let (icons, problems) = bundle_game_icons(&result.model, &result.uris, Some(&icons_dir)).unwrap();
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(icons.keys().collect::<Vec<_>>(), vec!("lorc/coins"));
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use log::info;
+use serde_json::{json, Map, Value};
+use url::Url;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.webmanifest";
+
+pub fn web_manifest(title: Option<&str>, description: Option<&str>, favicon: Option<&str>, background_color: Option<&str>) -> Result<String> {
+    let mut manifest = Map::new();
+    if let Some(title) = title {
+        manifest.insert("name".to_string(), json!(title));
+        manifest.insert("short_name".to_string(), json!(title));
+    }
+    if let Some(description) = description {
+        manifest.insert("description".to_string(), json!(description));
+    }
+    manifest.insert("start_url".to_string(), json!("."));
+    manifest.insert("display".to_string(), json!("standalone"));
+    if let Some(background_color) = background_color {
+        manifest.insert("background_color".to_string(), json!(background_color));
+        manifest.insert("theme_color".to_string(), json!(background_color));
+    }
+    if let Some(favicon) = favicon {
+        let mut icon = Map::new();
+        icon.insert("src".to_string(), json!(favicon));
+        icon.insert("sizes".to_string(), json!("any"));
+        if let Some(icon_type) = icon_type(favicon) {
+            icon.insert("type".to_string(), json!(icon_type));
+        }
+        manifest.insert("icons".to_string(), json!([Value::Object(icon)]));
+    }
+
+    serde_json::to_string_pretty(&Value::Object(manifest)).with_context(|| "Failed to serialize web app manifest")
+}
+
+pub fn icon_type(path: &str) -> Option<&'static str> {
+    let path = path.split(['?', '#']).next().unwrap_or(path).to_lowercase();
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("svg") => Some("image/svg+xml"),
+        Some("png") => Some("image/png"),
+        Some("ico") => Some("image/x-icon"),
+        Some("gif") => Some("image/gif"),
+        Some("jpg") | Some("jpeg") => Some("image/jpeg"),
+        Some("webp") => Some("image/webp"),
+        _ => None,
+    }
+}
+
+// Open Graph wants absolute URLs, so relative paths are resolved against the canonical URL when there is one
+pub fn absolute_url(path: &str, base: Option<&str>) -> String {
+    if Url::parse(path).is_ok() {
+        return path.to_string();
+    }
+
+    base.and_then(|base| Url::parse(base).ok())
+        .and_then(|base| base.join(path).ok())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+pub fn write_manifest(out_dir: &Path, manifest_string: &str) -> Result<PathBuf> {
+    let manifest_file_path = out_dir.join(MANIFEST_FILE_NAME);
+    std::fs::File::create(&manifest_file_path)
+        .with_context(|| format!("Failed to create web app manifest {:?}", &manifest_file_path))?
+        .write_all(manifest_string.as_bytes())
+        .with_context(|| "Failed to write web app manifest")?;
+    info!("{} {:.1}kb", manifest_file_path.display(), manifest_string.len() as f32 / 1024.0);
+    Ok(manifest_file_path)
+}
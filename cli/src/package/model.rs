@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use clap::ValueEnum;
+use log::{debug, info};
+use worldtree_compiler::{encode_model, encode_obfuscated_model, Model};
+
+pub const NAME_MAP_FILE_NAME: &str = "names.map.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ModelFormat {
+    #[value(help = "The model as JSON")]
+    Json,
+    #[value(help = "The model as base64 encoded bytecode, which is smaller and faster to load")]
+    Bytecode,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContentEncoding {
+    pub format: ModelFormat,
+    // Whether the bytecode string table is obfuscated. Names are obfuscated separately, before packaging
+    pub obfuscate: bool,
+}
+
+impl ModelFormat {
+    // The value of the model script's `data-format` attribute, which tells the engine how to read it
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModelFormat::Json => "json",
+            ModelFormat::Bytecode => "bytecode",
+        }
+    }
+}
+
+pub fn encode_content(model: &Model, encoding: ContentEncoding) -> Result<String> {
+    match encoding.format {
+        ModelFormat::Json => serde_json::to_string(model).with_context(|| "Failed to serialize model"),
+        ModelFormat::Bytecode => {
+            let bytes = if encoding.obfuscate { encode_obfuscated_model(model) } else { encode_model(model) }
+                .with_context(|| "Failed to encode model as bytecode")?;
+            debug!("Encoded model as {} bytes of bytecode", bytes.len());
+            Ok(BASE64_STANDARD.encode(bytes))
+        },
+    }
+}
+
+// Writes the original names of obfuscated qualities, locations and storylets by ID, for debugging a release build. This
+// isn't meant to be published with the world
+pub fn write_name_map(out_dir: &Path, names: &BTreeMap<String, String>) -> Result<PathBuf> {
+    let name_map_file_path = out_dir.join(NAME_MAP_FILE_NAME);
+    let name_map_string = serde_json::to_string_pretty(names)?;
+    std::fs::File::create(&name_map_file_path)
+        .with_context(|| format!("Failed to create name map {:?}", &name_map_file_path))?
+        .write_all(name_map_string.as_bytes())
+        .with_context(|| "Failed to write name map")?;
+    info!("{} {:.1}kb", name_map_file_path.display(), name_map_string.len() as f32 / 1024.0);
+    Ok(name_map_file_path)
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use worldtree_compiler::{Attribution, Mark, Problem};
+
+lazy_static! {
+    // Liquid reports parse errors as pest does, with a one-based line and column
+    static ref LOCATION_REGEX: Regex = Regex::new(r"-->\s*(\d+):(\d+)").unwrap();
+    static ref EXPECTED_REGEX: Regex = Regex::new(r"(?m)^\s*=\s*(.+)$").unwrap();
+    static ref VARIABLE_REGEX: Regex = Regex::new(r"requested variable=(\S+)").unwrap();
+    static ref INDEX_REGEX: Regex = Regex::new(r"(?s)\bvariable=(\S+).*requested index=(\S+)").unwrap();
+    static ref MARKUP_REGEX: Regex = Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").unwrap();
+}
+
+// Objects the engine can't run without, with the problem to report when a template leaves one out
+const REQUIRED_OBJECTS: [(&str, &str, &str); 2] = [
+    ("content", "Template doesn't embed the world", "Add `{{ content }}` to a script element with the ID `model` and a `data-format=\"{{ contentFormat }}\"` attribute, as the bundled template does"),
+    ("bundle.script", "Template doesn't embed the engine", "Add `{{ bundle.script }}` to a script element, as the bundled template does"),
+];
+
+// A template for index.html from the config file. Its problems are reported against the template, like problems in the
+// world's sources
+pub struct PageTemplate {
+    name: String,
+    source: String,
+    template: liquid::Template,
+}
+
+// Returns no template when it has problems, as they are all fatal
+pub fn parse_template(path: &Path) -> Result<(Option<PageTemplate>, Vec<Problem>)> {
+    let source = std::fs::read_to_string(path).with_context(|| format!("Failed to load user template {:?}", path))?;
+    let name = path.to_string_lossy().into_owned();
+
+    let template = match liquid::ParserBuilder::with_stdlib().build()?.parse(&source) {
+        Ok(template) => template,
+        Err(error) => {
+            let error = error.to_string();
+            let mark = LOCATION_REGEX.captures(&error)
+                .map(|captures| Mark {
+                    line: captures[1].parse::<u64>().unwrap_or(1).saturating_sub(1),
+                    column: captures[2].parse::<u64>().unwrap_or(1).saturating_sub(1),
+                })
+                .unwrap_or_default();
+            let help = EXPECTED_REGEX.captures(&error).map(|captures| captures[1].trim().to_string()).unwrap_or_else(|| first_line(&error));
+            return Ok((None, vec!(Problem::fatal("Invalid template", &Attribution::new(&name, mark, mark)).with_help(help))));
+        },
+    };
+
+    let mut problems = Vec::new();
+    for (object, message, help) in REQUIRED_OBJECTS {
+        if find_in_markup(&source, object).is_none() {
+            problems.push(Problem::fatal(message, &Attribution::new(&name, Mark::default(), Mark::default())).with_help(help.to_string()));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Ok((None, problems));
+    }
+    Ok((Some(PageTemplate { name, source, template }), problems))
+}
+
+impl PageTemplate {
+    // Liquid doesn't say where rendering failed, so an unknown variable or field is reported where it's first used
+    pub fn render(&self, globals: &liquid::Object, problems: &mut Vec<Problem>) -> Option<String> {
+        match self.template.render(globals) {
+            Ok(html) => Some(html),
+            Err(error) => {
+                let error = error.to_string();
+                let (message, variable, help) = if let Some(captures) = VARIABLE_REGEX.captures(&error) {
+                    let help = format!("There is no `{}`. Templates receive `config`, `meta`, `content`, `contentFormat`, `gameIcons` and `bundle`", &captures[1]);
+                    ("Unknown variable in template", Some(captures[1].to_string()), help)
+                } else if let Some(captures) = INDEX_REGEX.captures(&error) {
+                    let help = format!("`{}` has no `{}`", &captures[1], &captures[2]);
+                    ("Unknown variable in template", Some(format!("{}.{}", &captures[1], &captures[2])), help)
+                } else {
+                    ("Failed to render template", None, first_line(&error))
+                };
+                let (start, end) = variable.and_then(|variable| find_in_markup(&self.source, &variable)).unwrap_or((0, 0));
+                let attribution = Attribution::new(&self.name, mark_at(&self.source, start), mark_at(&self.source, end));
+                problems.push(Problem::fatal(message, &attribution).with_help(help));
+                None
+            },
+        }
+    }
+}
+
+// The byte range of the first use of a variable in a template's tags and outputs
+fn find_in_markup(source: &str, variable: &str) -> Option<(usize, usize)> {
+    let pattern = Regex::new(&format!(r"\b{}\b", regex::escape(variable))).unwrap();
+    MARKUP_REGEX.find_iter(source).find_map(|markup| {
+        pattern.find(markup.as_str()).map(|found| (markup.start() + found.start(), markup.start() + found.end()))
+    })
+}
+
+fn mark_at(source: &str, offset: usize) -> Mark {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Mark {
+        line: before.matches('\n').count() as u64,
+        column: before[line_start..].chars().count() as u64,
+    }
+}
+
+fn first_line(error: &str) -> String {
+    let line = error.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    line.strip_prefix("liquid:").unwrap_or(line).trim().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use worldtree_compiler::{Level, Mark};
+    use super::parse_template;
+
+    fn write_template(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("worldtree-template-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reports_syntax_errors_where_they_are() {
+        let path = write_template("broken.html.liquid", "<html>\n<script>{{ content }}</script>\n<script>{{ bundle.script }</script>\n</html>\n");
+        let (template, problems) = parse_template(&path).unwrap();
+        assert!(template.is_none());
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert_eq!(problems[0].level, Level::Fatal);
+        assert_eq!(problems[0].message, "Invalid template");
+        assert_eq!(*problems[0].attribution.source, path.to_string_lossy());
+        assert_eq!(problems[0].attribution.start_mark.line, 2);
+    }
+
+    #[test]
+    fn test_requires_content_and_script() {
+        let path = write_template("empty.html.liquid", "<html>\n<title>{{ meta.title }}</title>\n<script>{{ contentFormat }}</script>\n</html>\n");
+        let (template, problems) = parse_template(&path).unwrap();
+        assert!(template.is_none());
+        let messages: Vec<_> = problems.iter().map(|problem| problem.message).collect();
+        assert_eq!(messages, vec!("Template doesn't embed the world", "Template doesn't embed the engine"));
+    }
+
+    #[test]
+    fn test_reports_unknown_variables_where_they_are_used() {
+        let globals = liquid::object!({
+            "content": "{}",
+            "meta": liquid::object!({ "title": "" }),
+            "bundle": liquid::object!({ "script": "" }),
+        });
+        for (source, start, end) in [("{{ metta.title }}", 8, 13), ("{{ meta.titel }}", 8, 18)] {
+            let path = write_template("unknown.html.liquid", &format!("<html>\n<script>{{{{ content }}}}</script>\n  <p>{}</p>\n<script>{{{{ bundle.script }}}}</script>\n</html>\n", source));
+            let (template, problems) = parse_template(&path).unwrap();
+            assert!(problems.is_empty(), "{:?}", problems);
+            let mut problems = Vec::new();
+            assert_eq!(template.unwrap().render(&globals, &mut problems), None);
+            assert_eq!(problems[0].message, "Unknown variable in template");
+            assert_eq!(problems[0].attribution.start_mark, Mark { line: 2, column: start });
+            assert_eq!(problems[0].attribution.end_mark, Mark { line: 2, column: end });
+        }
+    }
+}
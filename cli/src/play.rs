@@ -0,0 +1,119 @@
+use std::io::{BufRead, Write};
+use anyhow::{Context, Result};
+use worldtree_compiler::{Model, Session, SessionState, State};
+use crate::compile::{human_output, MessageFormat};
+use crate::render::Renderer;
+
+pub fn play(model: &Model, seed: Option<u64>, format: MessageFormat) -> Result<()> {
+    let (mut stdout, terminal) = human_output(format);
+    let renderer = Renderer::new(terminal && std::env::var_os("NO_COLOR").is_none());
+    let mut session = Session::new(model, State::new());
+    if let Some(seed) = seed {
+        session = session.with_seed(seed);
+    }
+
+    let mut stdin = std::io::stdin().lock();
+    let mut state = session.resume();
+    let mut location = None;
+    print_state(&mut stdout, &renderer, &state, &mut location)?;
+
+    loop {
+        write!(stdout, "{} ", renderer.bold(">"))?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line).with_context(|| "Failed to read input")? == 0 {
+            writeln!(stdout)?;
+            break;
+        }
+
+        let input = line.trim();
+        match input {
+            "q" | "quit" => break,
+            "r" | "restart" => {
+                session.reset();
+                location = None;
+                state = session.resume();
+            },
+            "" if state.choices.is_empty() => {
+                state = session.resume();
+            },
+            _ => {
+                match input.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= state.choices.len() => {
+                        state = session.choose(state.choices[n - 1].id);
+                    },
+                    _ => {
+                        if state.choices.is_empty() {
+                            writeln!(stdout, "{}", renderer.dim("Press Enter to continue, r to restart or q to quit."))?;
+                        } else {
+                            writeln!(stdout, "{}", renderer.dim(&format!("Enter a number from 1 to {}, r to restart or q to quit.", state.choices.len())))?;
+                        }
+                        continue;
+                    },
+                }
+            },
+        }
+
+        writeln!(stdout)?;
+        print_state(&mut stdout, &renderer, &state, &mut location)?;
+    }
+
+    Ok(())
+}
+
+fn print_state(out: &mut impl Write, renderer: &Renderer, state: &SessionState, location: &mut Option<String>) -> Result<()> {
+    if let Some(current) = &state.location {
+        if location.as_ref() != Some(&current.name) {
+            writeln!(out, "{}", renderer.bold(&renderer.inline(&current.label).to_uppercase()))?;
+            if let Some(description) = &current.description {
+                writeln!(out, "{}", renderer.dim(&renderer.inline(description)))?;
+            }
+            writeln!(out)?;
+            *location = Some(current.name.clone());
+        }
+    }
+
+    if let Some(label) = state.storylet.as_ref().and_then(|storylet| storylet.label.as_ref()) {
+        writeln!(out, "{}", renderer.bold(&renderer.inline(label)))?;
+        writeln!(out)?;
+    }
+
+    let body = renderer.block(&state.body);
+    if !body.is_empty() {
+        writeln!(out, "{}", body)?;
+        writeln!(out)?;
+    }
+
+    for group in &state.assignments {
+        for result in &group.results {
+            writeln!(out, "  {} {}", renderer.dim("*"), renderer.assignment(result))?;
+        }
+        if let Some(description) = &group.description {
+            writeln!(out, "  {}", renderer.block(description))?;
+        }
+    }
+    if !state.assignments.is_empty() {
+        writeln!(out)?;
+    }
+
+    if let Some(prompt) = &state.prompt {
+        writeln!(out, "{}", renderer.block(prompt))?;
+        writeln!(out)?;
+    }
+
+    if state.choices.is_empty() {
+        writeln!(out, "{}", renderer.dim("Press Enter to continue."))?;
+    } else {
+        for (index, choice) in state.choices.iter().enumerate() {
+            let label = renderer.inline(&choice.label);
+            if let Some(description) = &choice.description {
+                writeln!(out, "  {}. {} {}", index + 1, label, renderer.dim(&format!("- {}", renderer.inline(description))))?;
+            } else {
+                writeln!(out, "  {}. {}", index + 1, label)?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use log::info;
+use worldtree_compiler::{Model, Playthrough};
+
+pub fn run_playthroughs(model: &Model, playthroughs: &[Playthrough]) -> bool {
+    let mut passed = 0;
+    let mut failed = 0;
+
+    info!("Running {} playthrough{}", playthroughs.len(), if playthroughs.len() == 1 { "" } else { "s" });
+    for playthrough in playthroughs {
+        let failures = playthrough.run(model);
+        if failures.is_empty() {
+            info!("    {} ... ok", playthrough.name);
+            passed += 1;
+        } else {
+            eprintln!("    {} ... FAILED", playthrough.name);
+            for failure in failures {
+                eprintln!("        {}", failure.message);
+                eprintln!("            in {}", failure.attribution.source);
+                eprintln!("            at {}", failure.attribution.path);
+                eprintln!("                line {}, column {}", failure.attribution.start_mark.line + 1, failure.attribution.start_mark.column + 1);
+            }
+            failed += 1;
+        }
+    }
+    eprintln!();
+    eprintln!("{} passed, {} failed", passed, failed);
+
+    failed == 0
+}
@@ -0,0 +1,166 @@
+use worldtree_compiler::{AssignmentOperation, AssignmentResult, AssignmentValue, TemplateParse, TemplateParseNode, Text, TextNode};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Clone, Copy)]
+pub struct Renderer {
+    styled: bool,
+}
+
+impl Renderer {
+    pub fn new(styled: bool) -> Self {
+        Self { styled }
+    }
+
+    pub fn bold(&self, s: &str) -> String {
+        self.style(BOLD, s)
+    }
+
+    pub fn dim(&self, s: &str) -> String {
+        self.style(DIM, s)
+    }
+
+    pub fn red(&self, s: &str) -> String {
+        self.style(RED, s)
+    }
+
+    pub fn yellow(&self, s: &str) -> String {
+        self.style(YELLOW, s)
+    }
+
+    pub fn blue(&self, s: &str) -> String {
+        self.style(BLUE, s)
+    }
+
+    pub fn block(&self, text: &Text) -> String {
+        let mut paragraphs = Vec::new();
+        let mut inline = Vec::new();
+        for node in text {
+            if let TextNode::Paragraph(paragraph) = node {
+                if !inline.is_empty() {
+                    paragraphs.push(self.inline(&std::mem::take(&mut inline)));
+                }
+                paragraphs.push(self.inline(paragraph));
+            } else {
+                inline.push(node.clone());
+            }
+        }
+        if !inline.is_empty() {
+            paragraphs.push(self.inline(&inline));
+        }
+        paragraphs.iter().map(|paragraph| paragraph.trim()).filter(|paragraph| !paragraph.is_empty()).collect::<Vec<_>>().join("\n\n")
+    }
+
+    pub fn inline(&self, text: &Text) -> String {
+        let mut result = String::new();
+        for node in text {
+            match node {
+                TextNode::Plain(s) => result.push_str(s),
+                TextNode::Paragraph(t) => {
+                    if !result.is_empty() && !result.ends_with(' ') {
+                        result.push(' ');
+                    }
+                    result.push_str(&self.inline(t));
+                },
+                TextNode::Italic(t) => result.push_str(&self.style(ITALIC, &self.inline(t))),
+                TextNode::Bold(t) => result.push_str(&self.style(BOLD, &self.inline(t))),
+                TextNode::Anchor(href, t) => {
+                    if self.styled {
+                        result.push_str(&format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", href, self.style(UNDERLINE, &self.inline(t))));
+                    } else {
+                        result.push_str(&format!("{} <{}>", self.inline(t), href));
+                    }
+                },
+            }
+        }
+        result
+    }
+
+    pub fn assignment(&self, result: &AssignmentResult) -> String {
+        let style = result.style.unwrap_or_default();
+        let label = self.inline(&result.label);
+        let set = matches!(result.operation, AssignmentOperation::Set);
+        let message = match &result.value {
+            AssignmentValue::Numeric(_) if style.uncounted => {
+                match (style.possessive, style.personal, style.plural, set) {
+                    (true, true, _, true) => format!("You now have {}", label),
+                    (true, true, _, false) => format!("You no longer have {}", label),
+                    (true, false, true, true) => format!("There are now {}", label),
+                    (true, false, true, false) => format!("There are no longer {}", label),
+                    (true, false, false, true) => format!("There is now {}", label),
+                    (true, false, false, false) => format!("There is no longer {}", label),
+                    (false, true, _, true) => format!("You are now {}", label),
+                    (false, true, _, false) => format!("You are no longer {}", label),
+                    (false, false, _, true) => format!("It is now {}", label),
+                    (false, false, _, false) => format!("It is no longer {}", label),
+                }
+            },
+            AssignmentValue::Numeric(value) => {
+                if style.currency {
+                    match (style.personal, value) {
+                        (true, 0) => format!("You no longer have any {}", label),
+                        (true, _) => format!("You now have {} {}", value, label),
+                        (false, 0) => format!("There are no longer any {}", label),
+                        (false, 1) => format!("There is now 1 {}", label),
+                        (false, _) => format!("There are now {} {}", value, label),
+                    }
+                } else {
+                    let verb = if style.plural { "are" } else { "is" };
+                    if style.personal {
+                        format!("Your {} {} now {}", label, verb, value)
+                    } else {
+                        format!("{} {} now {}", capitalize(&label), verb, value)
+                    }
+                }
+            },
+            AssignmentValue::Text(value) => {
+                let verb = if style.plural { "are" } else { "is" };
+                let change = if set { "now" } else { "no longer" };
+                let value = self.inline(value);
+                if style.personal {
+                    format!("Your {} {} {} {}", label, verb, change, value)
+                } else {
+                    format!("{} {} {} {}", capitalize(&label), verb, change, value)
+                }
+            },
+        };
+        format!("{}.", message)
+    }
+
+    fn style(&self, code: &str, s: &str) -> String {
+        if self.styled {
+            format!("{}{}{}", code, s, RESET)
+        } else {
+            s.to_string()
+        }
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// A template's text without formatting, taking the first branch of every condition, for naming something in a report
+pub fn plain_template(template: &TemplateParse) -> String {
+    let mut result = String::new();
+    for node in template {
+        match node {
+            TemplateParseNode::Text(s) => result.push_str(s),
+            TemplateParseNode::Paragraph => result.push(' '),
+            TemplateParseNode::Italic(t) | TemplateParseNode::Bold(t) | TemplateParseNode::Anchor(_, t) | TemplateParseNode::Branch(_, t, _) =>
+                result.push_str(&plain_template(t)),
+        }
+    }
+    result.trim().to_string()
+}
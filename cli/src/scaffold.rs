@@ -0,0 +1,161 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Error, Result};
+use clap::ValueEnum;
+use log::info;
+use crate::package::slug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    #[value(help = "A single location and an `initialize` storylet")]
+    Blank,
+    #[value(help = "Making Wishes, the example world with two rooms and a wishing pool")]
+    Example,
+    #[value(alias = "hub-and-spoke", help = "A town square linking to several places and back")]
+    Hub,
+}
+
+pub fn create(dir: &Path, template: Template, title: Option<String>) -> Result<()> {
+    if dir.is_file() {
+        return Err(Error::msg(format!("{:?} is a file, not a directory", dir)));
+    }
+
+    let title = title.unwrap_or_else(|| match template {
+        Template::Example => "Making Wishes".to_string(),
+        _ => title_from_dir(dir),
+    });
+    let files = files(template, &title)?;
+
+    // Check everything first, so a conflict doesn't leave a half-written world behind
+    for (path, _) in &files {
+        let path = dir.join(path);
+        if path.exists() {
+            return Err(Error::msg(format!("{:?} already exists", path)));
+        }
+    }
+
+    for (path, contents) in &files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        OpenOptions::new().write(true).create_new(true).open(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        info!("    {}", path.strip_prefix(dir).unwrap_or(&path).display());
+    }
+
+    info!("");
+    info!("Created {:?} in {}. To build it, run", title, dir.display());
+    info!("    worldtree build {} --config-file {}", dir.display(), dir.join("config.toml").display());
+    Ok(())
+}
+
+fn files(template: Template, title: &str) -> Result<Vec<(PathBuf, String)>> {
+    let name = slug(title);
+    let name = if name.is_empty() { "world".to_string() } else { name };
+    let globals = liquid::object!({
+        "title": quote(title),
+        "stateKey": quote(&name),
+        "archiveName": quote(&format!("{}.zip", name)),
+    });
+    let render = |source: &str| -> Result<String> {
+        Ok(liquid::ParserBuilder::with_stdlib().build()?.parse(source)?.render(&globals)?)
+    };
+
+    let mut files = vec!(
+        (PathBuf::from(".gitignore"), "dist/\n".to_string()),
+        (PathBuf::from("config.toml"), render(include_str!("../resources/templates/config.toml.liquid"))?),
+    );
+    let content = Path::new("content");
+    match template {
+        Template::Blank => {
+            files.push((content.join("world.yaml"), render(include_str!("../resources/templates/blank/world.yaml.liquid"))?));
+        },
+        Template::Example => {
+            let world = include_str!("../../example/content/world.yaml");
+            files.push((content.join("world.yaml"), world.replacen("title: Making Wishes", &format!("title: {}", quote(title)), 1)));
+            files.push((content.join("red_room.yaml"), include_str!("../../example/content/red_room.yaml").to_string()));
+            files.push((content.join("blue_room.yaml"), include_str!("../../example/content/blue_room.yaml").to_string()));
+            files.push((content.join("coins.test.yaml"), include_str!("../../example/content/coins.test.yaml").to_string()));
+        },
+        Template::Hub => {
+            files.push((content.join("world.yaml"), render(include_str!("../resources/templates/hub/world.yaml.liquid"))?));
+            files.push((content.join("town_square.yaml"), include_str!("../resources/templates/hub/town_square.yaml").to_string()));
+            files.push((content.join("market.yaml"), include_str!("../resources/templates/hub/market.yaml").to_string()));
+            files.push((content.join("harbor.yaml"), include_str!("../resources/templates/hub/harbor.yaml").to_string()));
+            files.push((content.join("tavern.yaml"), include_str!("../resources/templates/hub/tavern.yaml").to_string()));
+        },
+    }
+
+    Ok(files)
+}
+
+// JSON strings are valid double-quoted strings in both YAML and TOML
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn title_from_dir(dir: &Path) -> String {
+    let name = std::path::absolute(dir).ok()
+        .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+    let words: Vec<String> = name.split(['-', '_', ' ', '.']).filter(|word| !word.is_empty()).map(|word| {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }).collect();
+
+    if words.is_empty() {
+        "My World".to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use worldtree_compiler::{compile_sources, ModelParsingResult, Source};
+    use crate::package::{encode_content, ContentEncoding, ModelFormat};
+    use super::{files, Template};
+
+    fn compile(template: Template, title: &str) -> ModelParsingResult {
+        let sources = files(template, title).unwrap().into_iter()
+            .filter(|(path, _)| path.starts_with("content") && !path.to_string_lossy().ends_with(".test.yaml"))
+            .map(|(path, contents)| Source::from_string(&path.to_string_lossy(), &contents).unwrap())
+            .collect();
+        compile_sources(&sources)
+    }
+
+    #[test]
+    fn test_templates_compile() {
+        for template in [Template::Blank, Template::Example, Template::Hub] {
+            let result = compile(template, "Test World");
+            let problems: Vec<_> = result.problems.iter().map(|problem| problem.message).collect();
+            assert!(problems.is_empty(), "{:?}: {:?}", template, problems);
+        }
+    }
+
+    #[test]
+    fn test_example_takes_title() {
+        let world = files(Template::Example, "Wishing \"Well\"").unwrap().into_iter()
+            .find(|(path, _)| path == &Path::new("content").join("world.yaml"))
+            .unwrap().1;
+        assert!(world.contains("title: \"Wishing \\\"Well\\\"\""));
+        assert!(!world.contains("Making Wishes"));
+        let title = compile(Template::Example, "Wishing \"Well\"").model.meta.title;
+        assert!(format!("{:?}", title).contains("Wishing \\\"Well\\\""), "{:?}", title);
+    }
+
+    #[test]
+    fn test_example_bytecode_is_smaller_than_json() {
+        let model = compile(Template::Example, "Test World").model;
+        let encode = |format| encode_content(&model, ContentEncoding { format, obfuscate: false }).unwrap();
+        let (json, bytecode) = (encode(ModelFormat::Json), encode(ModelFormat::Bytecode));
+        assert!(bytecode.len() < json.len(), "{} bytes of bytecode, {} of JSON", bytecode.len(), json.len());
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+use anyhow::{Context, Error, Result};
+use log::{debug, error, info};
+use notify::{RecursiveMode, Watcher};
+use serde_derive::Serialize;
+use tiny_http::{Header, Response, Server};
+use worldtree_compiler::Level;
+use crate::compile::{IncrementalCompiler, MessageFormat, print_problems, render_problems};
+use crate::{load_config, package, write_html};
+use crate::package::{bundle_game_icons, copy_assets, default_game_icons_dir, icon_type, local_assets, write_manifest, ContentEncoding, ModelFormat};
+
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Default, Serialize)]
+struct Status {
+    version: u64,
+    problems: Vec<StatusProblem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip)]
+    html: Option<String>,
+    #[serde(skip)]
+    manifest: Option<String>,
+    #[serde(skip)]
+    assets: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct StatusProblem {
+    fatal: bool,
+    text: String,
+}
+
+struct Builder {
+    context: PathBuf,
+    compiler: IncrementalCompiler,
+    config_file: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
+    format: MessageFormat,
+}
+
+pub fn serve(context: &PathBuf, config_file: Option<PathBuf>, out_dir: Option<PathBuf>, port: u16, format: MessageFormat) -> Result<()> {
+    let context = context.canonicalize().with_context(|| format!("Failed to read context {:?}", context))?;
+    let config_file = config_file.map(|config_file| config_file.canonicalize().with_context(|| format!("Failed to read config file {:?}", config_file))).transpose()?;
+    let mut builder = Builder {
+        context: context.clone(),
+        compiler: IncrementalCompiler::new(&context),
+        config_file: config_file.clone(),
+        out_dir,
+        format,
+    };
+
+    let status = Arc::new(Mutex::new(Status::default()));
+    builder.build(&status);
+
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender).with_context(|| "Failed to watch for changes")?;
+    watcher.watch(&context, RecursiveMode::Recursive).with_context(|| format!("Failed to watch context {:?}", context))?;
+    let mut watched_files = Vec::new();
+    if let Some(config_file) = &config_file {
+        watched_files.push(config_file.clone());
+        if let Ok(config) = load_config(&Some(config_file.clone())) {
+            for file in config.stylesheet.into_iter().chain(config.template) {
+                watched_files.push(file.canonicalize().unwrap_or(file));
+            }
+        }
+    }
+    for file in &watched_files {
+        if let Some(parent) = file.parent().filter(|parent| !parent.starts_with(&context)) {
+            watcher.watch(parent, RecursiveMode::NonRecursive).with_context(|| format!("Failed to watch {:?}", parent))?;
+        }
+    }
+
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| Error::msg(format!("Failed to listen on port {}: {}", port, e)))?;
+    info!("Serving {} at http://127.0.0.1:{}/", context.display(), port);
+
+    let builder_status = status.clone();
+    std::thread::spawn(move || {
+        let _watcher = watcher;
+        while let Ok(event) = receiver.recv() {
+            let mut relevant = event.is_ok_and(|event| event.paths.iter().any(|path| is_relevant(path, &watched_files)));
+            loop {
+                match receiver.recv_timeout(DEBOUNCE) {
+                    Ok(event) => relevant |= event.is_ok_and(|event| event.paths.iter().any(|path| is_relevant(path, &watched_files))),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            if relevant {
+                debug!("Rebuilding after changes");
+                builder.build(&builder_status);
+            }
+        }
+    });
+
+    for request in server.incoming_requests() {
+        let response = match request.url().split('?').next().unwrap_or("") {
+            "/" | "/index.html" => {
+                let status = status.lock().unwrap();
+                let html = inject_development_script(status.html.as_deref().unwrap_or(PLACEHOLDER), status.version);
+                Response::from_string(html).with_header(content_type("text/html; charset=utf-8"))
+            },
+            "/manifest.webmanifest" => {
+                match status.lock().unwrap().manifest.clone() {
+                    Some(manifest) => Response::from_string(manifest).with_header(content_type("application/manifest+json")),
+                    None => Response::from_string("Not found").with_status_code(404),
+                }
+            },
+            "/__worldtree/status" => {
+                let json = serde_json::to_string(&*status.lock().unwrap())?;
+                Response::from_string(json).with_header(content_type("application/json")).with_header(no_store())
+            },
+            path => {
+                // Local images from the meta are read when they're requested, so changes to them show on reload
+                let asset = status.lock().unwrap().assets.iter().find(|asset| path.strip_prefix('/') == Some(asset.as_str())).cloned();
+                match asset.and_then(|asset| std::fs::read(context.join(&asset)).ok().map(|data| (asset, data))) {
+                    Some((asset, data)) => Response::from_data(data).with_header(content_type(icon_type(&asset).unwrap_or("application/octet-stream"))).with_header(no_store()),
+                    None => Response::from_string("Not found").with_status_code(404),
+                }
+            },
+        };
+        let _ = request.respond(response);
+    }
+
+    Ok(())
+}
+
+impl Builder {
+    fn build(&mut self, status: &Mutex<Status>) {
+        let result = self.compiler.compile().and_then(|result| {
+            let mut problems = result.problems;
+            let output = if problems.iter().any(|problem| problem.level == Level::Fatal) {
+                None
+            } else {
+                let mut model = result.model;
+                let config = load_config(&self.config_file)?;
+                let game_icons_dir = config.game_icons_dir.clone().or_else(default_game_icons_dir);
+                let (game_icons, icon_problems) = bundle_game_icons(&model, &result.uris, game_icons_dir.as_deref()).with_context(|| "Failed to bundle game icons")?;
+                problems.extend(icon_problems);
+                if problems.iter().any(|problem| problem.level == Level::Fatal) {
+                    None
+                } else {
+                    let packaged = package(&mut model, config, &game_icons, ContentEncoding { format: ModelFormat::Json, obfuscate: false }, &mut problems)?;
+                    if let (Some((html, manifest)), Some(out_dir)) = (&packaged, &self.out_dir) {
+                        write_html(out_dir, html)?;
+                        write_manifest(out_dir, manifest)?;
+                        copy_assets(&self.context, &model, out_dir)?;
+                    }
+                    let assets = local_assets(&model).into_iter().map(str::to_string).collect();
+                    packaged.map(|(html, manifest)| (html, manifest, assets))
+                }
+            };
+            Ok((output, problems))
+        });
+
+        let mut status = status.lock().unwrap();
+        match result {
+            Ok((output, problems)) => {
+                status.problems = render_problems(&problems).into_iter().zip(&problems)
+                    .map(|(text, problem)| StatusProblem { fatal: problem.level == Level::Fatal, text })
+                    .collect();
+                status.error = None;
+                if let Some((html, manifest, assets)) = output {
+                    status.html = Some(html);
+                    status.manifest = Some(manifest);
+                    status.assets = assets;
+                    status.version += 1;
+                }
+                print_problems(problems, self.format);
+            },
+            Err(e) => {
+                error!("{:#}", e);
+                status.problems.clear();
+                status.error = Some(format!("{:#}", e));
+            },
+        }
+    }
+}
+
+fn is_relevant(path: &Path, watched_files: &[PathBuf]) -> bool {
+    watched_files.iter().any(|file| file == path)
+        || path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("yaml") || extension.eq_ignore_ascii_case("yml"))
+}
+
+fn inject_development_script(html: &str, version: u64) -> String {
+    let script = format!("<script>window.worldtreeVersion = {};</script>\n{}", version, include_str!("../resources/development.html"));
+    if let Some(index) = html.rfind("</body>") {
+        format!("{}{}{}", &html[..index], script, &html[index..])
+    } else {
+        format!("{}{}", html, script)
+    }
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+fn no_store() -> Header {
+    Header::from_bytes("Cache-Control", "no-store").unwrap()
+}
+
+const PLACEHOLDER: &str = "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Worldtree</title></head><body></body></html>\n";
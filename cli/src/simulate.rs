@@ -0,0 +1,64 @@
+use std::io::Write;
+use worldtree_compiler::{Model, SimulationReport};
+use crate::render::plain_template;
+
+pub fn print_report(out: &mut impl Write, model: &Model, report: &SimulationReport) -> std::io::Result<()> {
+    writeln!(out, "Simulated {} playthrough{} of up to {} steps", report.runs, if report.runs == 1 { "" } else { "s" }, report.steps)?;
+    writeln!(out)?;
+
+    let width = model.storylets.iter().map(|storylet| storylet.name.len()).max().unwrap_or(0).max(8);
+    writeln!(out, "Storylet visits")?;
+    writeln!(out, "    {:width$}  {:>10}  {:>8}", "storylet", "visits", "reached", width = width)?;
+    for visits in &report.storylets {
+        let percent = if report.runs == 0 { 0.0 } else { visits.runs as f64 * 100.0 / report.runs as f64 };
+        writeln!(out, "    {:width$}  {:>10}  {:>7.1}%", model.storylets[visits.storylet].name, visits.visits, percent, width = width)?;
+    }
+    writeln!(out)?;
+
+    let unreached_storylets: Vec<_> = report.storylets.iter().filter(|visits| visits.runs == 0).collect();
+    let unreached_choices: Vec<_> = report.choices.iter().filter(|visits| visits.visits == 0).collect();
+    if !unreached_storylets.is_empty() || !unreached_choices.is_empty() {
+        writeln!(out, "Never reached")?;
+        for visits in unreached_storylets {
+            writeln!(out, "    storylet {}", model.storylets[visits.storylet].name)?;
+        }
+        for visits in unreached_choices {
+            let storylet = &model.storylets[visits.storylet];
+            let choice = &storylet.choices.as_ref().unwrap().groups[visits.group].choices[visits.choice];
+            writeln!(out, "    choice \"{}\" in storylet {}", plain_template(&choice.label), storylet.name)?;
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "Quality distributions (minimum / median / mean / maximum)")?;
+    for distribution in &report.qualities {
+        let quality = &model.qualities[distribution.quality];
+        if distribution.samples.iter().all(|sample| sample.maximum == 0) {
+            writeln!(out, "    {}: never set", quality.name)?;
+            continue;
+        }
+        writeln!(out, "    {}", quality.name)?;
+        for sample in &distribution.samples {
+            writeln!(out, "        step {:>6}: {} / {} / {:.2} / {}", sample.step, sample.minimum, sample.median, sample.mean, sample.maximum)?;
+        }
+    }
+    writeln!(out)?;
+
+    if report.dead_ends.is_empty() {
+        writeln!(out, "No dead ends")?;
+    } else {
+        writeln!(out, "Dead ends")?;
+        for dead_end in &report.dead_ends {
+            writeln!(out, "    in {}: {} run{}, earliest at step {}",
+                dead_end.location.as_deref().unwrap_or("no location"),
+                dead_end.runs,
+                if dead_end.runs == 1 { "" } else { "s" },
+                dead_end.earliest_step)?;
+            let qualities: Vec<String> = dead_end.example.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+            if !qualities.is_empty() {
+                writeln!(out, "        for example {}", qualities.join(", "))?;
+            }
+        }
+    }
+    Ok(())
+}
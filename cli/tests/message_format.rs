@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+const WORLD: &str = r#"
+version: 0.1
+qualities:
+  - name: coins
+storylets:
+  - name: initialize
+    lable: Begin
+    assign:
+      - increase: coins
+"#;
+
+fn world(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("worldtree-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("content")).unwrap();
+    std::fs::write(dir.join("content").join("world.yaml"), WORLD).unwrap();
+    dir
+}
+
+#[test]
+fn test_json_messages_are_alone_on_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_worldtree"))
+        .args(["--message-format", "json", "simulate", "--runs", "3"])
+        .arg(world("message-format"))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+
+    assert!(!stdout.is_empty());
+    for line in stdout.lines() {
+        let problem: serde_json::Value = serde_json::from_str(line).unwrap_or_else(|error| panic!("{:?} isn't JSON: {}", line, error));
+        assert_eq!(problem["level"], "warning");
+    }
+    assert!(stderr.contains("Simulated 3 playthroughs"), "{}", stderr);
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+const WORLD: &str = r#"
+version: 0.1
+meta:
+  title: A World
+  favicon: images/favicon.svg
+  image: cover.png
+storylets:
+  - name: initialize
+    label: Begin
+"#;
+
+fn world(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("worldtree-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("content")).unwrap();
+    std::fs::create_dir_all(dir.join("images")).unwrap();
+    std::fs::write(dir.join("content").join("world.yaml"), WORLD).unwrap();
+    std::fs::write(dir.join("images").join("favicon.svg"), "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+    std::fs::write(dir.join("cover.png"), "cover").unwrap();
+    dir
+}
+
+#[test]
+fn test_manifest_icon_resolves() {
+    let context = world("package");
+    let out_dir = context.join("dist");
+    let output = Command::new(env!("CARGO_BIN_EXE_worldtree"))
+        .args(["build", "--zip", "--out-dir"])
+        .arg(&out_dir)
+        .arg(&context)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(out_dir.join("manifest.webmanifest")).unwrap()).unwrap();
+    let icon = manifest["icons"][0]["src"].as_str().unwrap();
+    assert_eq!(std::fs::read_to_string(out_dir.join(icon)).unwrap(), std::fs::read_to_string(context.join("images").join("favicon.svg")).unwrap());
+    assert!(out_dir.join("cover.png").is_file());
+
+    let archive = zip::ZipArchive::new(std::fs::File::open(out_dir.join("a-world.zip")).unwrap()).unwrap();
+    let entries: Vec<&str> = archive.file_names().collect();
+    assert!(entries.contains(&icon), "{:?}", entries);
+    assert!(entries.contains(&"cover.png"), "{:?}", entries);
+}
@@ -0,0 +1,128 @@
+// A compact encoding of a model, as an alternative to JSON. Strings are stored once, in a string table, and everything
+// else is a sequence of unsigned 32-bit words.
+//
+// A file is the magic bytes `WTBC`, followed by LEB128 varints: the format version, the string table key, the length in
+// bytes of the string table, the UTF-8 string table itself (not varints), the number of words, and then the words.
+// Strings are referred to by two words, the byte offsets of their start and end in the string table.
+//
+// When the key isn't zero, the string table is obfuscated by XORing each byte with the next byte of an xorshift32
+// sequence seeded with the key. This keeps the text from being read at a glance, but anyone with the decoder can read it.
+//
+// The words describe the model in the order its fields are declared, with these encodings:
+//
+// - A record with optional fields starts with a word of flags, where bit n is set when its nth optional field is
+//   present, and boolean fields are stored as flags too. Fields that are absent are left out.
+// - A list is its length followed by its elements.
+// - An expression, template or text is its length in words followed by its code.
+// - A conditional value is the number of conditions, then each condition and the value it selects, then the value
+//   used when none of the conditions are met.
+//
+// The records are:
+//
+// - model: meta, list of qualities, list of locations, list of storylets
+// - meta: flags (title, description, lang, author, image, favicon, url), title text, description text, list of credit
+//   texts, lang string, author text, image string, favicon string, url string, list of keyword strings
+// - quality: name string, flags (label, singular label, plural label, description, icon, hidden, style, values,
+//   exclusive), label, singular label, plural label and description templates, icon conditional string, style flags
+//   (currency, personal, plural, possessive, uncounted), list of values
+// - quality value: name string, flags (label, description, icon), label and description templates, icon conditional
+//   string
+// - location: name string, label template, flags (description, body), description and body templates
+// - storylet: name string, flags (condition, label, description, icon, body, navigation, push, shift, assignments,
+//   choices), condition expression, label, description templates, icon conditional string, body template, navigation
+//   conditional string, push and shift conditional lists of strings, list of assignment groups, choices
+// - assignment group: flags (description), list of assignments, description template
+// - assignment: flags (condition), condition expression, subject string, operation (0 set, 1 unset, 2 increment,
+//   3 decrement), operand expression
+// - choices: flags (prompt), prompt template, list of choice groups
+// - choice group: flags (limit, shuffle), limit and shuffle expressions, list of choices
+// - choice: flags (condition, description, icon, body, navigation, push, shift, assignments), condition expression,
+//   label, description templates, icon conditional string, body template, navigation conditional string, push and
+//   shift conditional lists of strings, list of assignment groups
+//
+// Code runs on a stack. Expression instructions below 100 push values: `PUSH n`, `PUSH_LOGICAL b`,
+// `PUSH_VALUE_OF string` and `IN_LOCATION string`, or pop the number of values given by their argument and push the
+// result of their operation, as with `ADD n`. `cond BRANCH k then JUMP m else` pops a condition and skips the k words
+// after it when the condition is false, so that `JUMP m` only skips the m words of `else` when it is true.
+//
+// Template instructions from 100 write text: `STRING_PUSH string`, `PARAGRAPH_PUSH` to start a new paragraph, and
+// formatting that encloses its text, as with `ITALIC_PUSH ... ITALIC_POP` or `ANCHOR_PUSH ... ANCHOR_POP href`. Branches
+// are `cond TEMPLATE_BRANCH k then JUMP m else`, which works like `BRANCH`. Text is written the same way, except that
+// each paragraph is enclosed by `PARAGRAPH_PUSH ... PARAGRAPH_POP`.
+
+mod encode;
+mod decode;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub use crate::bytecode::encode::*;
+pub use crate::bytecode::decode::*;
+
+pub const MAGIC: &[u8; 4] = b"WTBC";
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytecodeError {
+    UnsupportedExpression(String),
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    UnexpectedEnd,
+    InvalidString(u32, u32),
+    InvalidOpcode(u32, usize),
+    InvalidCode(&'static str, usize),
+    InvalidLanguage(String),
+}
+
+impl Display for BytecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BytecodeError::UnsupportedExpression(expression) => write!(f, "Expression can't be encoded: {}", expression),
+            BytecodeError::InvalidHeader => f.write_str("Not a Worldtree bytecode model"),
+            BytecodeError::UnsupportedVersion(version) => write!(f, "Unsupported bytecode version {}, expected {}", version, VERSION),
+            BytecodeError::UnexpectedEnd => f.write_str("Unexpected end of bytecode"),
+            BytecodeError::InvalidString(start, end) => write!(f, "Invalid string table address {}..{}", start, end),
+            BytecodeError::InvalidOpcode(opcode, position) => write!(f, "Invalid opcode {} at word {}", opcode, position),
+            BytecodeError::InvalidCode(reason, position) => write!(f, "Invalid code at word {}: {}", position, reason),
+            BytecodeError::InvalidLanguage(tag) => write!(f, "Invalid language tag {:?}", tag),
+        }
+    }
+}
+
+impl Error for BytecodeError {}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Obfuscates the string table, or restores it, as applying the same key twice cancels out
+fn scramble(bytes: &mut [u8], key: u32) {
+    let mut state = key;
+    for byte in bytes {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *byte ^= state as u8;
+    }
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u32, BytecodeError> {
+    let mut value: u32 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*position).ok_or(BytecodeError::UnexpectedEnd)?;
+        *position += 1;
+        if shift > 28 {
+            return Err(BytecodeError::InvalidHeader);
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
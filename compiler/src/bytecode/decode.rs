@@ -0,0 +1,662 @@
+use crate::{Assignment, AssignmentGroup, AssignmentOperation, Choice, ChoiceGroup, Choices, Conditional, Language, Location, Meta, Model, Quality, QualityStyle, QualityValue, Storylet};
+use crate::bytecode::{read_varint, scramble, BytecodeError, MAGIC, VERSION};
+use crate::expression::{operation_operator, ExpressionAtom, ExpressionOperator, ExpressionParse, BRANCH, IN_LOCATION, JUMP, PUSH, PUSH_LOGICAL, PUSH_VALUE_OF};
+use crate::template::{TemplateParse, TemplateParseNode, ANCHOR_POP, ANCHOR_PUSH, BOLD_POP, BOLD_PUSH, ITALIC_POP, ITALIC_PUSH, PARAGRAPH_POP, PARAGRAPH_PUSH, STRING_PUSH, TEMPLATE_BRANCH};
+use crate::text::{Text, TextNode};
+
+struct Decoder {
+    strings: String,
+    words: Vec<u32>,
+    position: usize,
+    // Disassembly, when it's wanted, written as the model is decoded
+    listing: Option<String>,
+    depth: usize,
+}
+
+pub fn decode_model(bytes: &[u8]) -> Result<Model, BytecodeError> {
+    Decoder::new(bytes, false)?.model()
+}
+
+// Lists the records in encoded model and the instructions in its code, for debugging and tests
+pub fn disassemble(bytes: &[u8]) -> Result<String, BytecodeError> {
+    let mut decoder = Decoder::new(bytes, true)?;
+    decoder.model()?;
+    Ok(decoder.listing.unwrap_or_default())
+}
+
+pub fn mnemonic(opcode: u32) -> Option<&'static str> {
+    use crate::expression::*;
+    use crate::template::*;
+
+    Some(match opcode {
+        PUSH => "PUSH",
+        NOT => "NOT",
+        AND => "AND",
+        OR => "OR",
+        ADD => "ADD",
+        SUBTRACT => "SUBTRACT",
+        MULTIPLY => "MULTIPLY",
+        DIVIDE => "DIVIDE",
+        BRANCH => "BRANCH",
+        JUMP => "JUMP",
+        PUSH_LOGICAL => "PUSH_LOGICAL",
+        PUSH_VALUE_OF => "PUSH_VALUE_OF",
+        IN_LOCATION => "IN_LOCATION",
+        EITHER => "EITHER",
+        EQ => "EQ",
+        NEQ => "NEQ",
+        GT => "GT",
+        GTE => "GTE",
+        LT => "LT",
+        LTE => "LTE",
+        MAX => "MAX",
+        MIN => "MIN",
+        RAND => "RAND",
+        BETWEEN => "BETWEEN",
+        IS => "IS",
+        STRING_PUSH => "STRING_PUSH",
+        ITALIC_PUSH => "ITALIC_PUSH",
+        ITALIC_POP => "ITALIC_POP",
+        BOLD_PUSH => "BOLD_PUSH",
+        BOLD_POP => "BOLD_POP",
+        ANCHOR_PUSH => "ANCHOR_PUSH",
+        ANCHOR_POP => "ANCHOR_POP",
+        PARAGRAPH_PUSH => "PARAGRAPH_PUSH",
+        PARAGRAPH_POP => "PARAGRAPH_POP",
+        TEMPLATE_BRANCH => "TEMPLATE_BRANCH",
+        _ => return None,
+    })
+}
+
+fn closing_opcode(opcode: u32) -> u32 {
+    match opcode {
+        ITALIC_PUSH => ITALIC_POP,
+        BOLD_PUSH => BOLD_POP,
+        ANCHOR_PUSH => ANCHOR_POP,
+        _ => PARAGRAPH_POP,
+    }
+}
+
+fn is_set(flags: u32, bit: u32) -> bool {
+    flags & (1 << bit) != 0
+}
+
+impl Decoder {
+    fn new(bytes: &[u8], listing: bool) -> Result<Self, BytecodeError> {
+        if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+            return Err(BytecodeError::InvalidHeader);
+        }
+
+        let mut position = MAGIC.len();
+        let version = read_varint(bytes, &mut position)?;
+        if version != VERSION {
+            return Err(BytecodeError::UnsupportedVersion(version));
+        }
+
+        let key = read_varint(bytes, &mut position)?;
+        let strings_len = read_varint(bytes, &mut position)? as usize;
+        let mut strings = bytes.get(position..position + strings_len).ok_or(BytecodeError::UnexpectedEnd)?.to_vec();
+        scramble(&mut strings, key);
+        let strings = String::from_utf8(strings).map_err(|_| BytecodeError::InvalidHeader)?;
+        position += strings_len;
+
+        let words_len = read_varint(bytes, &mut position)? as usize;
+        let mut words = Vec::with_capacity(words_len.min(bytes.len()));
+        for _ in 0..words_len {
+            words.push(read_varint(bytes, &mut position)?);
+        }
+
+        Ok(Decoder {
+            strings,
+            words,
+            position: 0,
+            listing: if listing { Some(String::new()) } else { None },
+            depth: 0,
+        })
+    }
+
+    fn model(&mut self) -> Result<Model, BytecodeError> {
+        let meta = self.section("meta", Self::meta)?;
+        let qualities = self.list(|decoder| decoder.section("quality", Self::quality))?;
+        let locations = self.list(|decoder| decoder.section("location", Self::location))?;
+        let storylets = self.list(|decoder| decoder.section("storylet", Self::storylet))?;
+        if self.position != self.words.len() {
+            return Err(BytecodeError::InvalidCode("unexpected words after the model", self.position));
+        }
+        Ok(Model { meta, qualities, locations, storylets })
+    }
+
+    fn meta(&mut self) -> Result<Meta, BytecodeError> {
+        let flags = self.word()?;
+        let title = self.optional(flags, 0, |decoder| decoder.section("title", Self::text))?;
+        let description = self.optional(flags, 1, |decoder| decoder.section("description", Self::text))?;
+        let credits = self.list(|decoder| decoder.section("credit", Self::text))?;
+        let lang = self.optional(flags, 2, |decoder| {
+            let tag = decoder.section("lang", Self::string)?;
+            Language::parse(&tag).map_err(|_| BytecodeError::InvalidLanguage(tag))
+        })?;
+        let author = self.optional(flags, 3, |decoder| decoder.section("author", Self::text))?;
+        let image = self.optional(flags, 4, |decoder| decoder.section("image", Self::string))?;
+        let favicon = self.optional(flags, 5, |decoder| decoder.section("favicon", Self::string))?;
+        let url = self.optional(flags, 6, |decoder| decoder.section("url", Self::string))?;
+        let keywords = self.list(|decoder| decoder.section("keyword", Self::string))?;
+        Ok(Meta { title, description, credits, lang, author, image, favicon, url, keywords })
+    }
+
+    fn quality(&mut self) -> Result<Quality, BytecodeError> {
+        let name = self.section("name", Self::string)?;
+        let flags = self.word()?;
+        let label = self.optional(flags, 0, |decoder| decoder.section("label", Self::template))?;
+        let singular_label = self.optional(flags, 1, |decoder| decoder.section("singular label", Self::template))?;
+        let plural_label = self.optional(flags, 2, |decoder| decoder.section("plural label", Self::template))?;
+        let description = self.optional(flags, 3, |decoder| decoder.section("description", Self::template))?;
+        let icon = self.optional(flags, 4, |decoder| decoder.section("icon", |decoder| decoder.conditional(Self::string)))?;
+        let style = self.optional(flags, 6, |decoder| {
+            let style = decoder.word()?;
+            Ok(QualityStyle {
+                currency: is_set(style, 0),
+                personal: is_set(style, 1),
+                plural: is_set(style, 2),
+                possessive: is_set(style, 3),
+                uncounted: is_set(style, 4),
+            })
+        })?;
+        let values = self.optional(flags, 7, |decoder| decoder.list(|decoder| decoder.section("value", |decoder| {
+            let name = decoder.section("name", Self::string)?;
+            let flags = decoder.word()?;
+            let label = decoder.optional(flags, 0, |decoder| decoder.section("label", Self::template))?;
+            let description = decoder.optional(flags, 1, |decoder| decoder.section("description", Self::template))?;
+            let icon = decoder.optional(flags, 2, |decoder| decoder.section("icon", |decoder| decoder.conditional(Self::string)))?;
+            Ok(QualityValue { name, label, description, icon })
+        })))?;
+        Ok(Quality {
+            name,
+            label,
+            singular_label,
+            plural_label,
+            description,
+            icon,
+            hidden: is_set(flags, 5),
+            style,
+            values,
+            exclusive: is_set(flags, 8),
+        })
+    }
+
+    fn location(&mut self) -> Result<Location, BytecodeError> {
+        let name = self.section("name", Self::string)?;
+        let label = self.section("label", Self::template)?;
+        let flags = self.word()?;
+        let description = self.optional(flags, 0, |decoder| decoder.section("description", Self::template))?;
+        let body = self.optional(flags, 1, |decoder| decoder.section("body", Self::template))?;
+        Ok(Location { name, label, description, body })
+    }
+
+    fn storylet(&mut self) -> Result<Storylet, BytecodeError> {
+        let name = self.section("name", Self::string)?;
+        let flags = self.word()?;
+        let condition = self.optional(flags, 0, |decoder| decoder.section("condition", Self::expression))?;
+        let label = self.optional(flags, 1, |decoder| decoder.section("label", Self::template))?;
+        let description = self.optional(flags, 2, |decoder| decoder.section("description", Self::template))?;
+        let icon = self.optional(flags, 3, |decoder| decoder.section("icon", |decoder| decoder.conditional(Self::string)))?;
+        let body = self.optional(flags, 4, |decoder| decoder.section("body", Self::template))?;
+        let navigation = self.optional(flags, 5, |decoder| decoder.section("navigation", |decoder| decoder.conditional(Self::string)))?;
+        let push = self.optional(flags, 6, |decoder| decoder.section("push", |decoder| decoder.conditional(Self::names)))?;
+        let shift = self.optional(flags, 7, |decoder| decoder.section("shift", |decoder| decoder.conditional(Self::names)))?;
+        let assignments = self.optional(flags, 8, |decoder| decoder.list(|decoder| decoder.section("assignments", Self::assignment_group)))?;
+        let choices = self.optional(flags, 9, |decoder| decoder.section("choices", Self::choices))?;
+        Ok(Storylet { name, condition, label, description, icon, body, navigation, push, shift, assignments, choices })
+    }
+
+    fn assignment_group(&mut self) -> Result<AssignmentGroup, BytecodeError> {
+        let flags = self.word()?;
+        let assignments = self.list(|decoder| decoder.section("assignment", |decoder| {
+            let flags = decoder.word()?;
+            let condition = decoder.optional(flags, 0, |decoder| decoder.section("condition", Self::expression))?;
+            let subject = decoder.section("subject", Self::string)?;
+            let position = decoder.position;
+            let operation = match decoder.word()? {
+                0 => AssignmentOperation::Set,
+                1 => AssignmentOperation::Unset,
+                2 => AssignmentOperation::Increment,
+                3 => AssignmentOperation::Decrement,
+                _ => return Err(BytecodeError::InvalidCode("unknown assignment operation", position)),
+            };
+            decoder.line(format!("operation {:?}", operation));
+            let operand = decoder.section("operand", Self::expression)?;
+            Ok(Assignment { condition, subject, operation, operand })
+        }))?;
+        let description = self.optional(flags, 0, |decoder| decoder.section("description", Self::template))?;
+        Ok(AssignmentGroup { assignments, description })
+    }
+
+    fn choices(&mut self) -> Result<Choices, BytecodeError> {
+        let flags = self.word()?;
+        let prompt = self.optional(flags, 0, |decoder| decoder.section("prompt", Self::template))?;
+        let groups = self.list(|decoder| decoder.section("group", |decoder| {
+            let flags = decoder.word()?;
+            let limit = decoder.optional(flags, 0, |decoder| decoder.section("limit", Self::expression))?;
+            let shuffle = decoder.optional(flags, 1, |decoder| decoder.section("shuffle", Self::expression))?;
+            let choices = decoder.list(|decoder| decoder.section("choice", Self::choice))?;
+            Ok(ChoiceGroup { limit, shuffle, choices })
+        }))?;
+        Ok(Choices { prompt, groups })
+    }
+
+    fn choice(&mut self) -> Result<Choice, BytecodeError> {
+        let flags = self.word()?;
+        let condition = self.optional(flags, 0, |decoder| decoder.section("condition", Self::expression))?;
+        let label = self.section("label", Self::template)?;
+        let description = self.optional(flags, 1, |decoder| decoder.section("description", Self::template))?;
+        let icon = self.optional(flags, 2, |decoder| decoder.section("icon", |decoder| decoder.conditional(Self::string)))?;
+        let body = self.optional(flags, 3, |decoder| decoder.section("body", Self::template))?;
+        let navigation = self.optional(flags, 4, |decoder| decoder.section("navigation", |decoder| decoder.conditional(Self::string)))?;
+        let push = self.optional(flags, 5, |decoder| decoder.section("push", |decoder| decoder.conditional(Self::names)))?;
+        let shift = self.optional(flags, 6, |decoder| decoder.section("shift", |decoder| decoder.conditional(Self::names)))?;
+        let assignments = self.optional(flags, 7, |decoder| decoder.list(|decoder| decoder.section("assignments", Self::assignment_group)))?;
+        Ok(Choice { condition, label, description, icon, body, navigation, push, shift, assignments })
+    }
+
+    fn names(&mut self) -> Result<Vec<String>, BytecodeError> {
+        self.list(Self::string)
+    }
+
+    fn conditional<T>(&mut self, mut value: impl FnMut(&mut Self) -> Result<T, BytecodeError>) -> Result<Conditional<T>, BytecodeError> {
+        let count = self.word()?;
+        let mut conditions = Vec::new();
+        for _ in 0..count {
+            let condition = self.section("when", Self::expression)?;
+            conditions.push((condition, value(self)?));
+        }
+        let mut result = Conditional::Always(value(self)?);
+        for (condition, then) in conditions.into_iter().rev() {
+            result = Conditional::Conditionally(condition, then, Box::new(result));
+        }
+        Ok(result)
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, BytecodeError>) -> Result<Vec<T>, BytecodeError> {
+        let count = self.word()?;
+        let mut result = Vec::new();
+        for _ in 0..count {
+            result.push(item(self)?);
+        }
+        Ok(result)
+    }
+
+    fn optional<T>(&mut self, flags: u32, bit: u32, value: impl FnOnce(&mut Self) -> Result<T, BytecodeError>) -> Result<Option<T>, BytecodeError> {
+        if is_set(flags, bit) {
+            value(self).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn section<T>(&mut self, name: &str, value: impl FnOnce(&mut Self) -> Result<T, BytecodeError>) -> Result<T, BytecodeError> {
+        self.line(name.to_string());
+        self.depth += 1;
+        let result = value(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn line(&mut self, line: String) {
+        if let Some(listing) = &mut self.listing {
+            listing.push_str(&"  ".repeat(self.depth));
+            listing.push_str(&line);
+            listing.push('\n');
+        }
+    }
+
+    fn instruction(&mut self, position: usize, opcode: u32, arguments: &[u32], string: Option<&str>) {
+        if self.listing.is_some() {
+            let mut line = format!("{:04} {}", position, mnemonic(opcode).unwrap_or("?"));
+            for argument in arguments {
+                line.push_str(&format!(" {}", argument));
+            }
+            if let Some(string) = string {
+                line.push_str(&format!(" {:?}", string));
+            }
+            self.line(line);
+        }
+    }
+
+    fn word(&mut self) -> Result<u32, BytecodeError> {
+        let word = *self.words.get(self.position).ok_or(BytecodeError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(word)
+    }
+
+    fn address(&mut self) -> Result<(u32, u32, String), BytecodeError> {
+        let start = self.word()?;
+        let end = self.word()?;
+        let string = self.strings.get(start as usize..end as usize).ok_or(BytecodeError::InvalidString(start, end))?;
+        Ok((start, end, string.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String, BytecodeError> {
+        let (_, _, string) = self.address()?;
+        self.line(format!("{:?}", string));
+        Ok(string)
+    }
+
+    // Code is preceded by its length, which must contain it exactly
+    fn code_end(&mut self) -> Result<usize, BytecodeError> {
+        let len = self.word()? as usize;
+        let end = self.position + len;
+        if end > self.words.len() {
+            return Err(BytecodeError::UnexpectedEnd);
+        }
+        Ok(end)
+    }
+
+    fn expression(&mut self) -> Result<ExpressionParse, BytecodeError> {
+        let end = self.code_end()?;
+        self.expression_code(end)
+    }
+
+    fn template(&mut self) -> Result<TemplateParse, BytecodeError> {
+        let end = self.code_end()?;
+        self.template_nodes(end, None)
+    }
+
+    fn text(&mut self) -> Result<Text, BytecodeError> {
+        let end = self.code_end()?;
+        self.text_nodes(end, None)
+    }
+
+    fn expression_code(&mut self, end: usize) -> Result<ExpressionParse, BytecodeError> {
+        let start = self.position;
+        let mut stack = Vec::new();
+        while self.position < end {
+            self.expression_instruction(&mut stack, end)?;
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(expression), true) if self.position == end => Ok(expression),
+            _ => Err(BytecodeError::InvalidCode("expected code that leaves one value", start)),
+        }
+    }
+
+    fn expression_instruction(&mut self, stack: &mut Vec<ExpressionParse>, end: usize) -> Result<(), BytecodeError> {
+        let position = self.position;
+        let opcode = self.word()?;
+        match opcode {
+            PUSH => {
+                let n = self.word()?;
+                self.instruction(position, opcode, &[n], None);
+                stack.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n)));
+            },
+            PUSH_LOGICAL => {
+                let b = self.word()?;
+                self.instruction(position, opcode, &[b], None);
+                stack.push(ExpressionParse::Atom(ExpressionAtom::LogicalLiteral(b != 0)));
+            },
+            PUSH_VALUE_OF | IN_LOCATION => {
+                let (start, string_end, string) = self.address()?;
+                self.instruction(position, opcode, &[start, string_end], Some(&string));
+                let reference = ExpressionParse::Atom(ExpressionAtom::Reference(string));
+                stack.push(if opcode == IN_LOCATION { ExpressionParse::Operation(ExpressionOperator::In, vec!(reference)) } else { reference });
+            },
+            BRANCH => {
+                let skip = self.word()? as usize;
+                self.instruction(position, opcode, &[skip as u32], None);
+                let condition = stack.pop().ok_or(BytecodeError::InvalidCode("branch without a condition", position))?;
+                if skip < 2 || self.position + skip > end {
+                    return Err(BytecodeError::InvalidCode("branch out of bounds", position));
+                }
+                let consequent = self.expression_code(self.position + skip - 2)?;
+                let alternative_end = self.jump(end)?;
+                let alternative = self.expression_code(alternative_end)?;
+                stack.push(ExpressionParse::Operation(ExpressionOperator::Then, vec!(condition, consequent, alternative)));
+            },
+            _ => {
+                let operator = operation_operator(opcode).ok_or(BytecodeError::InvalidOpcode(opcode, position))?;
+                let count = self.word()? as usize;
+                self.instruction(position, opcode, &[count as u32], None);
+                if count > stack.len() {
+                    return Err(BytecodeError::InvalidCode("operation has fewer values than it takes", position));
+                }
+                let operands = stack.split_off(stack.len() - count);
+                stack.push(ExpressionParse::Operation(operator, operands));
+            },
+        }
+        Ok(())
+    }
+
+    // Reads the jump past an alternative, returning where the alternative ends
+    fn jump(&mut self, end: usize) -> Result<usize, BytecodeError> {
+        let position = self.position;
+        if self.word()? != JUMP {
+            return Err(BytecodeError::InvalidCode("expected a jump after a branch", position));
+        }
+        let skip = self.word()? as usize;
+        self.instruction(position, JUMP, &[skip as u32], None);
+        if self.position + skip > end {
+            return Err(BytecodeError::InvalidCode("jump out of bounds", position));
+        }
+        Ok(self.position + skip)
+    }
+
+    // Reads nodes until the end of the code or, inside formatting, the instruction that closes it
+    fn template_nodes(&mut self, end: usize, closing: Option<u32>) -> Result<TemplateParse, BytecodeError> {
+        let mut nodes = Vec::new();
+        let mut conditions = Vec::new();
+        loop {
+            let position = self.position;
+            if position >= end || Some(self.words[position]) == closing {
+                if position >= end && closing.is_some() {
+                    return Err(BytecodeError::InvalidCode("formatting is never closed", position));
+                }
+                if !conditions.is_empty() {
+                    return Err(BytecodeError::InvalidCode("condition without a branch", position));
+                }
+                return Ok(nodes);
+            }
+
+            let opcode = self.words[position];
+            if opcode < STRING_PUSH {
+                self.expression_instruction(&mut conditions, end)?;
+                continue;
+            }
+
+            self.position += 1;
+            match opcode {
+                STRING_PUSH => {
+                    let (start, string_end, string) = self.address()?;
+                    self.instruction(position, opcode, &[start, string_end], Some(&string));
+                    nodes.push(TemplateParseNode::Text(string));
+                },
+                PARAGRAPH_PUSH => {
+                    self.instruction(position, opcode, &[], None);
+                    nodes.push(TemplateParseNode::Paragraph);
+                },
+                ITALIC_PUSH | BOLD_PUSH | ANCHOR_PUSH => {
+                    self.instruction(position, opcode, &[], None);
+                    let closing = closing_opcode(opcode);
+                    let inner = self.template_nodes(end, Some(closing))?;
+                    nodes.push(match opcode {
+                        ITALIC_PUSH => {
+                            self.closing(closing)?;
+                            TemplateParseNode::Italic(inner)
+                        },
+                        BOLD_PUSH => {
+                            self.closing(closing)?;
+                            TemplateParseNode::Bold(inner)
+                        },
+                        _ => TemplateParseNode::Anchor(self.closing(closing)?.unwrap_or_default(), inner),
+                    });
+                },
+                TEMPLATE_BRANCH => {
+                    let skip = self.word()? as usize;
+                    self.instruction(position, opcode, &[skip as u32], None);
+                    let condition = conditions.pop().ok_or(BytecodeError::InvalidCode("branch without a condition", position))?;
+                    if skip < 2 || self.position + skip > end {
+                        return Err(BytecodeError::InvalidCode("branch out of bounds", position));
+                    }
+                    let then = self.template_nodes(self.position + skip - 2, None)?;
+                    let otherwise_end = self.jump(end)?;
+                    let otherwise = self.template_nodes(otherwise_end, None)?;
+                    nodes.push(TemplateParseNode::Branch(condition, then, if otherwise.is_empty() { None } else { Some(otherwise) }));
+                },
+                _ => return Err(BytecodeError::InvalidOpcode(opcode, position)),
+            }
+        }
+    }
+
+    fn text_nodes(&mut self, end: usize, closing: Option<u32>) -> Result<Text, BytecodeError> {
+        let mut nodes = Vec::new();
+        loop {
+            let position = self.position;
+            if position >= end || Some(self.words[position]) == closing {
+                if position >= end && closing.is_some() {
+                    return Err(BytecodeError::InvalidCode("formatting is never closed", position));
+                }
+                return Ok(nodes);
+            }
+
+            let opcode = self.word()?;
+            match opcode {
+                STRING_PUSH => {
+                    let (start, string_end, string) = self.address()?;
+                    self.instruction(position, opcode, &[start, string_end], Some(&string));
+                    nodes.push(TextNode::Plain(string));
+                },
+                PARAGRAPH_PUSH | ITALIC_PUSH | BOLD_PUSH | ANCHOR_PUSH => {
+                    self.instruction(position, opcode, &[], None);
+                    let closing = closing_opcode(opcode);
+                    let inner = self.text_nodes(end, Some(closing))?;
+                    let href = self.closing(closing)?;
+                    nodes.push(match opcode {
+                        PARAGRAPH_PUSH => TextNode::Paragraph(inner),
+                        ITALIC_PUSH => TextNode::Italic(inner),
+                        BOLD_PUSH => TextNode::Bold(inner),
+                        _ => TextNode::Anchor(href.unwrap_or_default(), inner),
+                    });
+                },
+                _ => return Err(BytecodeError::InvalidOpcode(opcode, position)),
+            }
+        }
+    }
+
+    // Reads the instruction that closes formatting, and the link it encloses when it's an anchor
+    fn closing(&mut self, opcode: u32) -> Result<Option<String>, BytecodeError> {
+        let position = self.position;
+        self.position += 1;
+        if opcode == ANCHOR_POP {
+            let (start, end, href) = self.address()?;
+            self.instruction(position, opcode, &[start, end], Some(&href));
+            Ok(Some(href))
+        } else {
+            self.instruction(position, opcode, &[], None);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{decode_model, disassemble, encode_model, encode_obfuscated_model, BytecodeError, Model};
+    use crate::testing::compile_model;
+
+    const WORLD: &str = r#"
+version: 0.1
+meta:
+  title: The _Harbor_
+  lang: en-GB
+  credits:
+    - Written by [someone](https://example.com)
+qualities:
+  - name: coins
+    label: coin
+    pluralLabel: coins
+    style:
+      - currency
+  - name: mood
+    values:
+      - name: calm
+        label: Calm
+      - name: angry
+        label: Angry
+    exclusive: true
+locations:
+  - name: harbor
+    label: The Harbor
+    body: Boats bob at their _moorings_.{ if coins > 2 } You're rich.{ else } You're poor.{ end }
+storylets:
+  - name: initialize
+    go: harbor
+    assign:
+      - set: coins
+        to: 2
+  - name: fish
+    label: Go fishing
+    when: in harbor and not angry
+    icon:
+      - when: coins >= 5
+        then: game-icons:fishing-boat
+      - game-icons:fishing-pole
+    choose:
+      - label: Cast a line
+        assign:
+          - increase: coins
+            by: (coins > 3 then 1 else 2)
+          - set: angry
+            when: either yes or no
+      - label: Give up
+        go: harbor
+"#;
+
+    fn model() -> Model {
+        compile_model(WORLD)
+    }
+
+    #[test]
+    fn test_decodes_encoded_model() {
+        let model = model();
+        let bytes = encode_model(&model).unwrap();
+        let decoded = decode_model(&bytes).unwrap();
+        assert_eq!(decoded, model);
+    }
+
+    #[test]
+    fn test_decodes_obfuscated_model() {
+        let model = model();
+        let bytes = encode_obfuscated_model(&model).unwrap();
+        assert!(!bytes.windows(8).any(|window| window == b"moorings"));
+        assert_ne!(bytes, encode_model(&model).unwrap());
+        let decoded = decode_model(&bytes).unwrap();
+        assert_eq!(decoded, model);
+    }
+
+    #[test]
+    fn test_disassembles_code() {
+        let bytes = encode_model(&model()).unwrap();
+        let listing = disassemble(&bytes).unwrap();
+        let body: Vec<&str> = listing.lines().skip_while(|line| line.trim() != "body").skip(1).take_while(|line| line.starts_with("    ")).map(str::trim).collect();
+        assert_eq!(body, vec!(
+            "0069 STRING_PUSH 85 104 \"Boats bob at their \"",
+            "0072 ITALIC_PUSH",
+            "0073 STRING_PUSH 104 112 \"moorings\"",
+            "0076 ITALIC_POP",
+            "0077 STRING_PUSH 43 44 \".\"",
+            "0080 PUSH_VALUE_OF 52 57 \"coins\"",
+            "0083 PUSH 2",
+            "0085 GT 2",
+            "0087 TEMPLATE_BRANCH 5",
+            "0089 STRING_PUSH 112 125 \" You're rich.\"",
+            "0092 JUMP 3",
+            "0094 STRING_PUSH 125 138 \" You're poor.\"",
+        ));
+    }
+
+    #[test]
+    fn test_rejects_invalid_bytecode() {
+        let bytes = encode_model(&model()).unwrap();
+        assert_eq!(decode_model(b"{}").err(), Some(BytecodeError::InvalidHeader));
+        assert_eq!(decode_model(&bytes[..bytes.len() - 1]).err(), Some(BytecodeError::UnexpectedEnd));
+
+        let mut version = bytes.clone();
+        version[4] = 9;
+        assert_eq!(decode_model(&version).err(), Some(BytecodeError::UnsupportedVersion(9)));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{AssignmentGroup, AssignmentOperation, Choices, Conditional, Location, Meta, Model, Quality, Storylet};
+use crate::bytecode::{scramble, write_varint, BytecodeError, MAGIC, VERSION};
+use crate::model::fnv1a;
+use crate::expression::{ExpressionCompiler, ExpressionParse};
+use crate::string_table::StringTable;
+use crate::template::{compile_template, compile_text, TemplateParse};
+use crate::text::Text;
+
+struct Encoder {
+    strings: StringTable,
+    words: Vec<u32>,
+    expression_compiler: ExpressionCompiler,
+}
+
+pub fn encode_model(model: &Model) -> Result<Vec<u8>, BytecodeError> {
+    encode(model, false)
+}
+
+// Encodes the model with its string table obfuscated, keyed by a hash of the strings so that builds are reproducible
+pub fn encode_obfuscated_model(model: &Model) -> Result<Vec<u8>, BytecodeError> {
+    encode(model, true)
+}
+
+fn encode(model: &Model, obfuscate: bool) -> Result<Vec<u8>, BytecodeError> {
+    let mut encoder = Encoder {
+        strings: StringTable::new(),
+        words: Vec::new(),
+        expression_compiler: ExpressionCompiler::new(),
+    };
+    encoder.model(model)?;
+
+    let mut bytes = Vec::with_capacity(encoder.strings.len() + encoder.words.len() + 16);
+    bytes.extend_from_slice(MAGIC);
+    let mut strings = encoder.strings.get_string().as_bytes().to_vec();
+    let key = if obfuscate { fnv1a(&strings) as u32 | 1 } else { 0 };
+    scramble(&mut strings, key);
+
+    write_varint(&mut bytes, VERSION);
+    write_varint(&mut bytes, key);
+    write_varint(&mut bytes, strings.len() as u32);
+    bytes.extend_from_slice(&strings);
+    write_varint(&mut bytes, encoder.words.len() as u32);
+    for word in encoder.words {
+        write_varint(&mut bytes, word);
+    }
+    Ok(bytes)
+}
+
+// Sets bit n of the flags for the nth of the fields that are present
+fn flags(fields: &[bool]) -> u32 {
+    fields.iter().enumerate().filter(|(_, present)| **present).fold(0, |flags, (bit, _)| flags | (1 << bit))
+}
+
+impl Encoder {
+    fn model(&mut self, model: &Model) -> Result<(), BytecodeError> {
+        self.meta(&model.meta);
+        self.list(&model.qualities, Self::quality)?;
+        self.list(&model.locations, Self::location)?;
+        self.list(&model.storylets, Self::storylet)
+    }
+
+    fn meta(&mut self, meta: &Meta) {
+        self.words.push(flags(&[
+            meta.title.is_some(),
+            meta.description.is_some(),
+            meta.lang.is_some(),
+            meta.author.is_some(),
+            meta.image.is_some(),
+            meta.favicon.is_some(),
+            meta.url.is_some(),
+        ]));
+        if let Some(title) = &meta.title {
+            self.text(title);
+        }
+        if let Some(description) = &meta.description {
+            self.text(description);
+        }
+        self.words.push(meta.credits.len() as u32);
+        for credit in &meta.credits {
+            self.text(credit);
+        }
+        if let Some(lang) = &meta.lang {
+            self.string(lang.tag());
+        }
+        if let Some(author) = &meta.author {
+            self.text(author);
+        }
+        for string in [&meta.image, &meta.favicon, &meta.url].into_iter().flatten() {
+            self.string(string);
+        }
+        self.words.push(meta.keywords.len() as u32);
+        for keyword in &meta.keywords {
+            self.string(keyword);
+        }
+    }
+
+    fn quality(&mut self, quality: &Quality) -> Result<(), BytecodeError> {
+        self.string(&quality.name);
+        self.words.push(flags(&[
+            quality.label.is_some(),
+            quality.singular_label.is_some(),
+            quality.plural_label.is_some(),
+            quality.description.is_some(),
+            quality.icon.is_some(),
+            quality.hidden,
+            quality.style.is_some(),
+            quality.values.is_some(),
+            quality.exclusive,
+        ]));
+        for template in [&quality.label, &quality.singular_label, &quality.plural_label, &quality.description].into_iter().flatten() {
+            self.template(template)?;
+        }
+        if let Some(icon) = &quality.icon {
+            self.conditional(icon, |encoder, icon| encoder.string(icon))?;
+        }
+        if let Some(style) = &quality.style {
+            self.words.push(flags(&[style.currency, style.personal, style.plural, style.possessive, style.uncounted]));
+        }
+        if let Some(values) = &quality.values {
+            self.list(values, |encoder, value| {
+                encoder.string(&value.name);
+                encoder.words.push(flags(&[value.label.is_some(), value.description.is_some(), value.icon.is_some()]));
+                for template in [&value.label, &value.description].into_iter().flatten() {
+                    encoder.template(template)?;
+                }
+                if let Some(icon) = &value.icon {
+                    encoder.conditional(icon, |encoder, icon| encoder.string(icon))?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    fn location(&mut self, location: &Location) -> Result<(), BytecodeError> {
+        self.string(&location.name);
+        self.template(&location.label)?;
+        self.words.push(flags(&[location.description.is_some(), location.body.is_some()]));
+        for template in [&location.description, &location.body].into_iter().flatten() {
+            self.template(template)?;
+        }
+        Ok(())
+    }
+
+    fn storylet(&mut self, storylet: &Storylet) -> Result<(), BytecodeError> {
+        self.string(&storylet.name);
+        self.words.push(flags(&[
+            storylet.condition.is_some(),
+            storylet.label.is_some(),
+            storylet.description.is_some(),
+            storylet.icon.is_some(),
+            storylet.body.is_some(),
+            storylet.navigation.is_some(),
+            storylet.push.is_some(),
+            storylet.shift.is_some(),
+            storylet.assignments.is_some(),
+            storylet.choices.is_some(),
+        ]));
+        if let Some(condition) = &storylet.condition {
+            self.expression(condition)?;
+        }
+        for template in [&storylet.label, &storylet.description].into_iter().flatten() {
+            self.template(template)?;
+        }
+        self.outcome(&storylet.icon, &storylet.body, &storylet.navigation, &storylet.push, &storylet.shift, &storylet.assignments)?;
+        if let Some(choices) = &storylet.choices {
+            self.choices(choices)?;
+        }
+        Ok(())
+    }
+
+    // The fields that storylets and choices share, in the order both declare them
+    fn outcome(
+        &mut self,
+        icon: &Option<Conditional<String>>,
+        body: &Option<TemplateParse>,
+        navigation: &Option<Conditional<String>>,
+        push: &Option<Conditional<Vec<String>>>,
+        shift: &Option<Conditional<Vec<String>>>,
+        assignments: &Option<Vec<AssignmentGroup>>,
+    ) -> Result<(), BytecodeError> {
+        if let Some(icon) = icon {
+            self.conditional(icon, |encoder, icon| encoder.string(icon))?;
+        }
+        if let Some(body) = body {
+            self.template(body)?;
+        }
+        if let Some(navigation) = navigation {
+            self.conditional(navigation, |encoder, navigation| encoder.string(navigation))?;
+        }
+        for names in [push, shift].into_iter().flatten() {
+            self.conditional(names, |encoder, names| {
+                encoder.words.push(names.len() as u32);
+                for name in names {
+                    encoder.string(name);
+                }
+            })?;
+        }
+        if let Some(assignments) = assignments {
+            self.list(assignments, Self::assignment_group)?;
+        }
+        Ok(())
+    }
+
+    fn assignment_group(&mut self, group: &AssignmentGroup) -> Result<(), BytecodeError> {
+        self.words.push(flags(&[group.description.is_some()]));
+        self.list(&group.assignments, |encoder, assignment| {
+            encoder.words.push(flags(&[assignment.condition.is_some()]));
+            if let Some(condition) = &assignment.condition {
+                encoder.expression(condition)?;
+            }
+            encoder.string(&assignment.subject);
+            encoder.words.push(match assignment.operation {
+                AssignmentOperation::Set => 0,
+                AssignmentOperation::Unset => 1,
+                AssignmentOperation::Increment => 2,
+                AssignmentOperation::Decrement => 3,
+            });
+            encoder.expression(&assignment.operand)
+        })?;
+        if let Some(description) = &group.description {
+            self.template(description)?;
+        }
+        Ok(())
+    }
+
+    fn choices(&mut self, choices: &Choices) -> Result<(), BytecodeError> {
+        self.words.push(flags(&[choices.prompt.is_some()]));
+        if let Some(prompt) = &choices.prompt {
+            self.template(prompt)?;
+        }
+        self.list(&choices.groups, |encoder, group| {
+            encoder.words.push(flags(&[group.limit.is_some(), group.shuffle.is_some()]));
+            for expression in [&group.limit, &group.shuffle].into_iter().flatten() {
+                encoder.expression(expression)?;
+            }
+            encoder.list(&group.choices, |encoder, choice| {
+                encoder.words.push(flags(&[
+                    choice.condition.is_some(),
+                    choice.description.is_some(),
+                    choice.icon.is_some(),
+                    choice.body.is_some(),
+                    choice.navigation.is_some(),
+                    choice.push.is_some(),
+                    choice.shift.is_some(),
+                    choice.assignments.is_some(),
+                ]));
+                if let Some(condition) = &choice.condition {
+                    encoder.expression(condition)?;
+                }
+                encoder.template(&choice.label)?;
+                if let Some(description) = &choice.description {
+                    encoder.template(description)?;
+                }
+                encoder.outcome(&choice.icon, &choice.body, &choice.navigation, &choice.push, &choice.shift, &choice.assignments)
+            })
+        })
+    }
+
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T) -> Result<(), BytecodeError>) -> Result<(), BytecodeError> {
+        self.words.push(items.len() as u32);
+        for value in items {
+            item(self, value)?;
+        }
+        Ok(())
+    }
+
+    fn conditional<T>(&mut self, conditional: &Conditional<T>, mut value: impl FnMut(&mut Self, &T)) -> Result<(), BytecodeError> {
+        let mut conditions = Vec::new();
+        let mut next = conditional;
+        while let Conditional::Conditionally(condition, then, otherwise) = next {
+            conditions.push((condition, then));
+            next = otherwise;
+        }
+
+        self.words.push(conditions.len() as u32);
+        for (condition, then) in conditions {
+            self.expression(condition)?;
+            value(self, then);
+        }
+        if let Conditional::Always(always) = next {
+            value(self, always);
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, string: &str) {
+        let address = self.strings.put(string);
+        self.words.push(address.start as u32);
+        self.words.push(address.end as u32);
+    }
+
+    fn expression(&mut self, expression: &ExpressionParse) -> Result<(), BytecodeError> {
+        let code = self.expression_compiler.compile(expression, &mut self.strings)?;
+        self.code(code);
+        Ok(())
+    }
+
+    fn template(&mut self, template: &TemplateParse) -> Result<(), BytecodeError> {
+        let code = compile_template(template, &mut self.strings)?;
+        self.code(code);
+        Ok(())
+    }
+
+    fn text(&mut self, text: &Text) {
+        let code = compile_text(text, &mut self.strings);
+        self.code(code);
+    }
+
+    fn code(&mut self, code: Vec<u32>) {
+        self.words.push(code.len() as u32);
+        self.words.extend(code);
+    }
+}
@@ -1,6 +1,7 @@
 mod conditional;
 mod element;
 mod expression;
+mod keys;
 mod list;
 mod location;
 mod logical;
@@ -8,6 +9,7 @@ mod meta;
 mod name;
 mod named;
 mod numeric;
+mod playthrough;
 mod quality;
 mod storylet;
 mod text;
@@ -15,18 +17,21 @@ mod template;
 mod uri;
 mod tag;
 
-use crate::{Attribution, Mark, Source};
+use log::debug;
+use crate::{Attribution, Language, Mark, Source};
 use crate::problem::Problem;
 use crate::yaml::{Document, Node, Value};
 
 pub use crate::element::conditional::*;
 pub use crate::element::element::*;
 pub use crate::element::expression::*;
+pub(crate) use crate::element::keys::check_keys;
 pub use crate::element::list::*;
 pub use crate::element::location::*;
 pub use crate::element::meta::*;
 pub use crate::element::name::*;
 pub use crate::element::named::*;
+pub use crate::element::playthrough::*;
 pub use crate::element::quality::*;
 pub use crate::element::storylet::*;
 pub use crate::element::tag::*;
@@ -164,7 +169,9 @@ impl ElementTree {
                 } else {
                     Attribution::new_at_index(source, i, node.start_mark, node.end_mark)
                 };
-                tree = tree.merge(Self::from_node(node, attribution, problems), problems);
+                let document_tree = Self::from_node(node, attribution.clone(), problems);
+                check_keys(node, &attribution, problems);
+                tree = tree.merge(document_tree, problems);
             }
         }
 
@@ -187,6 +194,8 @@ impl ElementTree {
             last_source = Some(source);
         }
 
+        debug!("Built element tree with {} qualities, {} top-level storylets and {} locations from {} sources", tree.qualities.len(), tree.storylets.len(), tree.locations.len(), sources.len());
+
         if let Some(last_source) = last_source {
             if let None = tree.version {
                 let attribution = Attribution::new(&last_source.path, Mark { line: 0, column: 0 }, Mark { line: 0, column: 0 });
@@ -196,4 +205,12 @@ impl ElementTree {
 
         tree
     }
+
+    // The language names are compared in. Problems with the tag are reported when the model is parsed
+    pub fn language(&self) -> Language {
+        self.meta.as_ref()
+            .and_then(|meta| meta.lang.as_ref())
+            .and_then(|lang| Language::parse(&lang.source).ok())
+            .unwrap_or_default()
+    }
 }
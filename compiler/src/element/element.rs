@@ -1,13 +1,12 @@
-use std::collections::BTreeMap;
 use crate::{Attribution, Problem};
-use crate::yaml::Node;
+use crate::yaml::{Mapping, Node};
 
 pub trait Element {
     fn attribution(&self) -> &Attribution;
     fn from_node(node: &Node, attribution: Attribution, problems: &mut Vec<Problem>) -> Self;
 
-    fn from_key(mapping: &BTreeMap<Node, Node>, attribution: &Attribution, key: &str, problems: &mut Vec<Problem>) -> Option<Self> where Self: Sized {
-        if let Some(node) = mapping.get(&Node::string(key)) {
+    fn from_key(mapping: &Mapping, attribution: &Attribution, key: &str, problems: &mut Vec<Problem>) -> Option<Self> where Self: Sized {
+        if let Some(node) = mapping.lookup(key) {
             let node_attribution = attribution.at_key(key, node.start_mark, node.end_mark);
             Some(Self::from_node(node, node_attribution, problems))
         } else {
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+use crate::{Attribution, Mark, Problem};
+use crate::yaml::{Node, Value};
+
+// Warns about the keys of every mapping in a document that its elements didn't look up, in document order. Mappings
+// where nothing was looked up, such as those under unrecognized keys, are left alone
+pub(crate) fn check_keys(node: &Node, attribution: &Attribution, problems: &mut Vec<Problem>) {
+    let mut unexpected = Vec::new();
+    unexpected_keys(node, attribution, &mut unexpected);
+    unexpected.sort_by_key(|(start_mark, _)| *start_mark);
+    problems.extend(unexpected.into_iter().map(|(_, problem)| problem));
+}
+
+fn unexpected_keys(node: &Node, attribution: &Attribution, unexpected: &mut Vec<(Mark, Problem)>) {
+    match &node.value {
+        Value::Scalar(_) => {},
+        Value::Sequence(sequence) => {
+            for (i, node) in sequence.iter().enumerate() {
+                unexpected_keys(node, &attribution.at_index(i, node.start_mark, node.end_mark), unexpected);
+            }
+        },
+        Value::Mapping(mapping) => {
+            let expected: BTreeSet<String> = mapping.lookups().iter().cloned().collect();
+            for (key, value) in mapping {
+                if let Value::Scalar(key_string) = &key.value {
+                    if !expected.is_empty() && !expected.contains(key_string) {
+                        let help = if let Some(suggestion) = suggest(key_string, &expected) {
+                            format!("did you mean `{}`?", suggestion)
+                        } else {
+                            format!("expected one of {}", expected.iter().map(|key| format!("`{}`", key)).collect::<Vec<String>>().join(", "))
+                        };
+                        let key_attribution = attribution.at_key(key_string, key.start_mark, key.end_mark);
+                        unexpected.push((key.start_mark, Problem::warning("Unrecognized key, which will be ignored", &key_attribution).with_help(help)));
+                    }
+                    unexpected_keys(value, &attribution.at_key(key_string, value.start_mark, value.end_mark), unexpected);
+                }
+            }
+        },
+    }
+}
+
+fn suggest<'a>(key: &str, expected: &'a BTreeSet<String>) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    let threshold = (key.chars().count() / 3).max(1);
+    expected.iter()
+        .map(|candidate| (distance(&key, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec!(i + 1);
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::element_tree;
+
+    fn problems(source: &str) -> Vec<(&'static str, String, Option<String>)> {
+        let (_, problems) = element_tree(source);
+        problems.into_iter().map(|problem| (problem.message, problem.attribution.path, problem.help)).collect()
+    }
+
+    #[test]
+    fn test_unrecognized_keys_suggest_known_keys() {
+        let problems = problems(r#"
+version: 0.1
+qualities:
+  - name: coins
+    singular_label: coin
+storylets:
+  - name: spend
+    reapeatable: true
+    choose:
+      - label: Spend
+        wen: coins
+        assign:
+          - decrement: coins
+            bye: 2
+"#);
+        assert_eq!(problems, vec!(
+            ("Unrecognized key, which will be ignored", ".qualities[0].singular_label".to_string(), Some("did you mean `singularLabel`?".to_string())),
+            ("Unrecognized key, which will be ignored", ".storylets[0].reapeatable".to_string(), Some("did you mean `repeatable`?".to_string())),
+            ("Unrecognized key, which will be ignored", ".storylets[0].choose[0].wen".to_string(), Some("did you mean `when`?".to_string())),
+            ("Unrecognized key, which will be ignored", ".storylets[0].choose[0].assign[0].bye".to_string(), Some("did you mean `by`?".to_string())),
+        ));
+    }
+
+    #[test]
+    fn test_known_keys_are_accepted() {
+        let problems = problems(r#"
+version: 0.1
+meta:
+  title: A World
+  author: Someone
+  image: cover.png
+  favicon: favicon.svg
+  url: https://example.com/a-world/
+  keywords:
+    - fantasy
+    - short
+qualities:
+  mood:
+    exclusive: true
+    values:
+      calm:
+        label: Calm
+storylets:
+  - name: pause
+    when: calm
+    label: Pause
+    choose:
+      prompt: What now?
+      limit: 1
+      label: Wait
+      assign:
+        description: You wait.
+        set: calm
+"#);
+        assert_eq!(problems, Vec::new());
+    }
+}
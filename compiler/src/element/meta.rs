@@ -10,6 +10,12 @@ pub struct MetaElement {
     pub title: Option<TextElement>,
     pub description: Option<TextElement>,
     pub credits: Option<ListElement<TextElement>>,
+    pub lang: Option<TextElement>,
+    pub author: Option<TextElement>,
+    pub image: Option<TextElement>,
+    pub favicon: Option<TextElement>,
+    pub url: Option<TextElement>,
+    pub keywords: Option<ListElement<TextElement>>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,6 +68,12 @@ impl Element for MetaElement {
                     title: None,
                     description: None,
                     credits: None,
+                    lang: None,
+                    author: None,
+                    image: None,
+                    favicon: None,
+                    url: None,
+                    keywords: None,
                 }
             },
             Value::Sequence(sequence) => {
@@ -71,6 +83,12 @@ impl Element for MetaElement {
                         title: None,
                         description: None,
                         credits: None,
+                        lang: None,
+                        author: None,
+                        image: None,
+                        favicon: None,
+                        url: None,
+                        keywords: None,
                     }
                 } else {
                     if sequence.len() > 0 {
@@ -84,12 +102,32 @@ impl Element for MetaElement {
                 let title = TextElement::from_key(map, &attribution, "title", problems);
                 let description = TextElement::from_key(map, &attribution, "description", problems);
                 let credits = ListElement::from_key(map, &attribution, "credits", problems);
+                let lang = TextElement::from_key(map, &attribution, "lang", problems);
+                let author = TextElement::from_key(map, &attribution, "author", problems);
+                let image = TextElement::from_key(map, &attribution, "image", problems);
+                let favicon = TextElement::from_key(map, &attribution, "favicon", problems);
+                let url = TextElement::from_key(map, &attribution, "url", problems);
+                let keywords = ListElement::from_key(map, &attribution, "keywords", problems);
+
+                if let Some(url) = &url {
+                    let trimmed = url.source.trim();
+                    if !trimmed.starts_with("https://") && !trimmed.starts_with("http://") {
+                        problems.push(Problem::warning("Expected an absolute URL, which is needed to link to the world when it is shared", &url.attribution)
+                            .with_help(format!("did you mean `https://{}`?", trimmed.trim_start_matches('/'))));
+                    }
+                }
 
                 Self {
                     attribution,
                     title,
                     description,
                     credits,
+                    lang,
+                    author,
+                    image,
+                    favicon,
+                    url,
+                    keywords,
                 }
             }
         }
@@ -0,0 +1,138 @@
+use crate::{Attribution, Problem};
+use crate::element::element::Element;
+use crate::element::list::ListElement;
+use crate::element::logical::LogicalValueElement;
+use crate::element::name::NameElement;
+use crate::element::numeric::NumericValueElement;
+use crate::element::text::TextElement;
+use crate::yaml::{Node, Value};
+
+#[derive(Debug, Clone)]
+pub struct PlaythroughElement {
+    pub attribution: Attribution,
+    pub name: Option<NameElement>,
+    pub seed: Option<NumericValueElement>,
+    pub steps: Option<ListElement<PlaythroughStepElement>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaythroughStepElement {
+    pub attribution: Attribution,
+    pub choose: Option<TextElement>,
+    pub r#continue: Option<LogicalValueElement>,
+    pub location: Option<NameElement>,
+    pub storylet: Option<NameElement>,
+    pub qualities: Option<QualityExpectationsElement>,
+    pub body: Option<ListElement<TextElement>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityExpectationsElement {
+    pub attribution: Attribution,
+    pub expectations: Vec<(NameElement, NumericValueElement)>,
+}
+
+impl Element for PlaythroughElement {
+    fn attribution(&self) -> &Attribution {
+        &self.attribution
+    }
+
+    fn from_node(node: &Node, attribution: Attribution, problems: &mut Vec<Problem>) -> Self {
+        match &node.value {
+            Value::Mapping(mapping) => {
+                let name = NameElement::from_key(mapping, &attribution, "name", problems);
+                let seed = NumericValueElement::from_key(mapping, &attribution, "seed", problems);
+                let steps = ListElement::from_key(mapping, &attribution, "steps", problems);
+
+                Self {
+                    attribution,
+                    name,
+                    seed,
+                    steps,
+                }
+            },
+            _ => {
+                problems.push(Problem::fatal("Expected a playthrough", &attribution));
+
+                Self {
+                    attribution,
+                    name: None,
+                    seed: None,
+                    steps: None,
+                }
+            },
+        }
+    }
+}
+
+impl Element for PlaythroughStepElement {
+    fn attribution(&self) -> &Attribution {
+        &self.attribution
+    }
+
+    fn from_node(node: &Node, attribution: Attribution, problems: &mut Vec<Problem>) -> Self {
+        match &node.value {
+            Value::Mapping(mapping) => {
+                let choose = TextElement::from_key(mapping, &attribution, "choose", problems);
+                let r#continue = LogicalValueElement::from_key(mapping, &attribution, "continue", problems);
+                let location = NameElement::from_key(mapping, &attribution, "location", problems);
+                let storylet = NameElement::from_key(mapping, &attribution, "storylet", problems);
+                let qualities = QualityExpectationsElement::from_key(mapping, &attribution, "qualities", problems);
+                let body = ListElement::from_key(mapping, &attribution, "body", problems);
+
+                if choose.is_some() && r#continue.as_ref().is_some_and(|c| c.value) {
+                    problems.push(Problem::fatal("A step can either choose or continue, but not both", &attribution));
+                }
+
+                Self {
+                    attribution,
+                    choose,
+                    r#continue,
+                    location,
+                    storylet,
+                    qualities,
+                    body,
+                }
+            },
+            _ => {
+                problems.push(Problem::fatal("Expected a playthrough step", &attribution));
+
+                Self {
+                    attribution,
+                    choose: None,
+                    r#continue: None,
+                    location: None,
+                    storylet: None,
+                    qualities: None,
+                    body: None,
+                }
+            },
+        }
+    }
+}
+
+impl Element for QualityExpectationsElement {
+    fn attribution(&self) -> &Attribution {
+        &self.attribution
+    }
+
+    fn from_node(node: &Node, attribution: Attribution, problems: &mut Vec<Problem>) -> Self {
+        match &node.value {
+            Value::Mapping(mapping) => {
+                let mut expectations = Vec::new();
+                for (key, value) in mapping {
+                    let key_string = if let Value::Scalar(scalar) = &key.value { scalar.clone() } else { String::new() };
+                    let name = NameElement::from_node(key, attribution.at_key(&key_string, key.start_mark, key.end_mark), problems);
+                    let value_attribution = attribution.at_key(&key_string, value.start_mark, value.end_mark);
+                    expectations.push((name, NumericValueElement::from_node(value, value_attribution, problems)));
+                }
+
+                Self { attribution, expectations }
+            },
+            _ => {
+                problems.push(Problem::fatal("Expected a mapping of qualities to values", &attribution));
+                Self { attribution, expectations: Vec::new() }
+            },
+        }
+    }
+}
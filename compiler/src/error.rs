@@ -1,5 +1,7 @@
 use std::error::{Error};
 use std::fmt::{Debug, Display, Formatter};
+use crate::Mark;
+use crate::yaml::Error as YamlError;
 
 pub struct SourceError {
     underlying: Box<dyn Error + Send>,
@@ -31,4 +33,19 @@ impl SourceError {
             underlying: Box::new(error),
         }
     }
+
+    pub fn problem(&self) -> Option<&'static str> {
+        match self.underlying.downcast_ref::<YamlError>()? {
+            YamlError::IoError(_) => None,
+            YamlError::SyntaxError { problem, .. } | YamlError::DatasetError { problem, .. } => Some(*problem),
+        }
+    }
+
+    pub fn mark(&self) -> Option<Mark> {
+        match self.underlying.downcast_ref::<YamlError>()? {
+            YamlError::IoError(_) => None,
+            YamlError::SyntaxError { problem_mark, .. } => *problem_mark,
+            YamlError::DatasetError { problem_mark, .. } => Some(*problem_mark),
+        }
+    }
 }
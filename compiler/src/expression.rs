@@ -2,6 +2,7 @@ mod token;
 mod lexer;
 mod parser;
 mod compile;
+mod print;
 
 pub use crate::expression::token::*;
 pub use crate::expression::lexer::*;
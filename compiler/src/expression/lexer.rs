@@ -158,7 +158,7 @@ impl<'a> ExpressionLex<'a> {
                 if let Some((length, symbol)) = self.lexer.symbols.starts_with(&self.source[offset..]) {
                     let mut after_line = line;
                     let mut after_column = column;
-                    for char in self.source[offset..].chars() {
+                    for char in self.source[offset..offset + length].chars() {
                         if char == '\n' {
                             after_line += 1;
                             after_column = 0;
@@ -186,7 +186,7 @@ impl<'a> ExpressionLex<'a> {
                 let word = &self.source[start..offset].trim().to_lowercase();
                 return (offset, match word.as_str() {
                     "no" | "false" | "never" => Some(ExpressionToken::Atom(ExpressionAtom::LogicalLiteral(false))),
-                    "yes" | "true" | "always" => Some(ExpressionToken::Atom(ExpressionAtom::LogicalLiteral(false))),
+                    "yes" | "true" | "always" => Some(ExpressionToken::Atom(ExpressionAtom::LogicalLiteral(true))),
                     "and" => Some(ExpressionToken::Operator(ExpressionOperator::And)),
                     "or" => Some(ExpressionToken::Operator(ExpressionOperator::Or)),
                     "not" => Some(ExpressionToken::Operator(ExpressionOperator::Not)),
@@ -209,8 +209,8 @@ impl<'a> ExpressionLex<'a> {
                 }, line, after_column);
             }
 
+            let start = offset;
             offset += char.len_utf8();
-            let after_column = column + char.len_utf8();
             let token = match char {
                 '(' => Some(ExpressionToken::Operator(ExpressionOperator::OpenParen)),
                 ')' => Some(ExpressionToken::Operator(ExpressionOperator::CloseParen)),
@@ -296,9 +296,29 @@ impl<'a> ExpressionLex<'a> {
                 _ => Some(ExpressionToken::UnrecognizedToken)
             };
 
-            return (offset, token, line, after_column)
+            return (offset, token, line, column + offset - start)
         }
 
         (offset, None, line, column)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::expression::lexer::ExpressionLexer;
+    use crate::expression::token::{ExpressionAtom, ExpressionToken};
+    use crate::symbol::SymbolList;
+
+    #[test]
+    fn test_logical_literals() {
+        let symbols = SymbolList::new();
+        let lexer = ExpressionLexer::new(&symbols);
+        let literal = |source: &str| lexer.lex(source).next().map(|token| token.token);
+        for source in ["yes", "True", "always"] {
+            assert_eq!(literal(source), Some(ExpressionToken::Atom(ExpressionAtom::LogicalLiteral(true))), "{}", source);
+        }
+        for source in ["no", "FALSE", "never"] {
+            assert_eq!(literal(source), Some(ExpressionToken::Atom(ExpressionAtom::LogicalLiteral(false))), "{}", source);
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::expression::token::{ExpressionAtom, ExpressionOperator, ExpressionTok
 use crate::{Attribution, Problem};
 use crate::symbol::{SymbolList};
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum ExpressionParse {
     Atom(ExpressionAtom),
     Operation(ExpressionOperator, Vec<ExpressionParse>),
@@ -393,11 +393,14 @@ pub fn normalize_expression(expression: &ExpressionParse) -> ExpressionParse {
                             _ => result.push(operand),
                         };
                     }
+                    if result.is_empty() {
+                        return ExpressionParse::Atom(ExpressionAtom::NumericLiteral(constant));
+                    }
                     if constant > 0 {
-                        result.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(0)));
+                        result.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(constant)));
                     }
-                    if result.is_empty() {
-                        ExpressionParse::Atom(ExpressionAtom::NumericLiteral(0))
+                    if result.len() == 1 {
+                        result.pop().unwrap()
                     } else {
                         ExpressionParse::Operation(ExpressionOperator::Plus, result)
                     }
@@ -422,11 +425,14 @@ pub fn normalize_expression(expression: &ExpressionParse) -> ExpressionParse {
                             _ => result.push(operand),
                         };
                     }
+                    if result.is_empty() {
+                        return ExpressionParse::Atom(ExpressionAtom::NumericLiteral(constant));
+                    }
                     if constant > 1 {
-                        result.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(0)));
+                        result.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(constant)));
                     }
-                    if result.is_empty() {
-                        ExpressionParse::Atom(ExpressionAtom::NumericLiteral(1))
+                    if result.len() == 1 {
+                        result.pop().unwrap()
                     } else {
                         ExpressionParse::Operation(ExpressionOperator::Multiply, result)
                     }
@@ -594,7 +600,7 @@ pub fn normalize_expression(expression: &ExpressionParse) -> ExpressionParse {
                         if let Some(m) = constant {
                             result.push(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(m)));
                         }
-                        ExpressionParse::Operation(ExpressionOperator::Maximum, result)
+                        ExpressionParse::Operation(ExpressionOperator::Minimum, result)
                     }
                 },
                 _ => {
@@ -913,4 +919,33 @@ mod test {
         assert_eq!(result.parse, Some(TagParse::End));
         assert!(result.problems.is_empty());
     }
+
+    #[test]
+    pub fn test_problem_marks() {
+        let mut symbols = SymbolList::new();
+        symbols.push("coins on the floor");
+        let parser = ExpressionParser::new(&symbols);
+
+        let attribution = Attribution::new("test", Mark { line: 2, column: 10 }, Mark { line: 2, column: 34 });
+
+        let result = parser.parse("coins on the floor > > 2", &attribution);
+        assert_eq!(result.problems.len(), 1);
+        assert_eq!(result.problems[0].attribution.start_mark, Mark { line: 2, column: 30 });
+        assert_eq!(result.problems[0].attribution.end_mark, Mark { line: 2, column: 32 });
+    }
+
+    #[test]
+    pub fn test_normalize_expression() {
+        let mut symbols = SymbolList::new();
+        symbols.push("coins");
+        let parser = ExpressionParser::new(&symbols);
+        let attribution = Attribution::new("test", Mark::default(), Mark::default());
+        let normalize = |source: &str| normalize_expression(&parser.parse(source, &attribution).parse.unwrap());
+
+        let coins = ExpressionParse::Atom(ExpressionAtom::Reference(String::from("coins")));
+        assert_eq!(normalize("coins + 2 + 3"), ExpressionParse::Operation(ExpressionOperator::Plus, vec!(coins.clone(), ExpressionParse::Atom(ExpressionAtom::NumericLiteral(5)))));
+        assert_eq!(normalize("2 * 3"), ExpressionParse::Atom(ExpressionAtom::NumericLiteral(6)));
+        assert_eq!(normalize("coins * 1"), coins.clone());
+        assert_eq!(normalize("minimum of coins, 3"), ExpressionParse::Operation(ExpressionOperator::Minimum, vec!(coins, ExpressionParse::Atom(ExpressionAtom::NumericLiteral(3)))));
+    }
 }
@@ -0,0 +1,149 @@
+use std::fmt::{Debug, Display, Formatter, Result};
+use crate::expression::parser::ExpressionParse;
+use crate::expression::token::{ExpressionAtom, ExpressionOperator};
+
+// Operators bind more tightly the later they're declared, so printing only needs parens where an operand binds no
+// more tightly than the operator around it, which would otherwise absorb or regroup it when parsed back
+impl Display for ExpressionParse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ExpressionParse::Atom(atom) => Display::fmt(atom, f),
+            ExpressionParse::Operation(operator, operands) => match operator {
+                ExpressionOperator::And | ExpressionOperator::Or if operands.is_empty() => {
+                    f.write_str(if *operator == ExpressionOperator::And { "yes" } else { "no" })
+                },
+                ExpressionOperator::And | ExpressionOperator::Or | ExpressionOperator::Is
+                    | ExpressionOperator::Equal | ExpressionOperator::NotEqual
+                    | ExpressionOperator::GreaterThan | ExpressionOperator::GreaterThanOrEqual
+                    | ExpressionOperator::LessThan | ExpressionOperator::LessThanOrEqual
+                    | ExpressionOperator::Plus | ExpressionOperator::Minus
+                    | ExpressionOperator::Multiply | ExpressionOperator::Divide => {
+                    join(f, operands, &format!(" {} ", operator), *operator)
+                },
+                ExpressionOperator::Not | ExpressionOperator::In => {
+                    for (i, operand) in operands.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(if *operator == ExpressionOperator::Not { " and " } else { " or " })?;
+                        }
+                        write!(f, "{} ", operator)?;
+                        group(f, operand, binds(operand).is_some_and(|bound| bound < ExpressionOperator::Not))?;
+                    }
+                    Ok(())
+                },
+                ExpressionOperator::Then if operands.len() == 3 => {
+                    f.write_str("if ")?;
+                    group(f, &operands[0], looser(&operands[0], ExpressionOperator::When))?;
+                    f.write_str(" then ")?;
+                    group(f, &operands[1], looser(&operands[1], ExpressionOperator::Then))?;
+                    f.write_str(" else ")?;
+                    group(f, &operands[2], looser(&operands[2], ExpressionOperator::Then))
+                },
+                ExpressionOperator::Either => phrase(f, "either", operands, " or ", ExpressionOperator::Or),
+                ExpressionOperator::Random => phrase(f, "random", operands, " or ", ExpressionOperator::Or),
+                ExpressionOperator::Between => phrase(f, "between", operands, " and ", ExpressionOperator::And),
+                ExpressionOperator::Maximum => phrase(f, "maximum of", operands, " and ", ExpressionOperator::And),
+                ExpressionOperator::Minimum => phrase(f, "minimum of", operands, " and ", ExpressionOperator::And),
+                _ => phrase(f, &operator.to_string(), operands, ", ", ExpressionOperator::Comma),
+            },
+        }
+    }
+}
+
+impl Debug for ExpressionParse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Expression({})", self)
+    }
+}
+
+impl Display for ExpressionAtom {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ExpressionAtom::LogicalLiteral(b) => f.write_str(if *b { "yes" } else { "no" }),
+            ExpressionAtom::NumericLiteral(n) => write!(f, "{}", n),
+            ExpressionAtom::Reference(name) => f.write_str(name),
+        }
+    }
+}
+
+// How tightly an expression holds together when printed, where atoms can't be split at all
+fn binds(parse: &ExpressionParse) -> Option<ExpressionOperator> {
+    match parse {
+        ExpressionParse::Atom(_) => None,
+        ExpressionParse::Operation(ExpressionOperator::And | ExpressionOperator::Or, operands) if operands.is_empty() => None,
+        ExpressionParse::Operation(_, operands) if operands.len() == 1 && !matches!(parse, ExpressionParse::Operation(ExpressionOperator::Not | ExpressionOperator::In, _)) => binds(&operands[0]),
+        ExpressionParse::Operation(operator, _) => Some(*operator),
+    }
+}
+
+fn looser(parse: &ExpressionParse, operator: ExpressionOperator) -> bool {
+    binds(parse).is_some_and(|bound| bound <= operator)
+}
+
+fn group(f: &mut Formatter<'_>, parse: &ExpressionParse, parens: bool) -> Result {
+    if parens {
+        write!(f, "({})", parse)
+    } else {
+        Display::fmt(parse, f)
+    }
+}
+
+fn join(f: &mut Formatter<'_>, operands: &[ExpressionParse], separator: &str, operator: ExpressionOperator) -> Result {
+    for (i, operand) in operands.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        group(f, operand, looser(operand, operator))?;
+    }
+    Ok(())
+}
+
+fn phrase(f: &mut Formatter<'_>, prefix: &str, operands: &[ExpressionParse], separator: &str, operator: ExpressionOperator) -> Result {
+    write!(f, "{} ", prefix)?;
+    join(f, operands, separator, operator)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Attribution, Mark};
+    use crate::expression::parser::ExpressionParser;
+    use crate::symbol::SymbolList;
+
+    #[test]
+    fn test_print_round_trips() {
+        let symbols = SymbolList::builder().push("coins").push("wishes").push("red room").build();
+        let parser = ExpressionParser::new(&symbols);
+        let attribution = Attribution::new("test", Mark::default(), Mark::default());
+        let print = |source: &str| {
+            let result = parser.parse(source, &attribution);
+            assert!(result.problems.is_empty(), "{}", source);
+            let parse = result.parse.unwrap();
+            let printed = parse.to_string();
+            assert_eq!(parser.parse(&printed, &attribution).parse, Some(parse), "{} printed as {}", source, printed);
+            printed
+        };
+
+        assert_eq!(print("coins"), "coins");
+        assert_eq!(print("  Coins   >1"), "coins > 1");
+        assert_eq!(print("true"), "yes");
+        assert_eq!(print("1 + 2 * 3"), "1 + 2 * 3");
+        assert_eq!(print("(1 + 2) * 3"), "(1 + 2) * 3");
+        assert_eq!(print("coins - (wishes + 1)"), "coins - (wishes + 1)");
+        assert_eq!(print("coins - wishes - 1"), "coins - wishes - 1");
+        assert_eq!(print("coins > 1 and wishes"), "coins > 1 and wishes");
+        assert_eq!(print("(coins and wishes) or red room"), "(coins and wishes) or red room");
+        assert_eq!(print("coins and (wishes or red room)"), "coins and wishes or red room");
+        assert_eq!(print("not coins"), "not coins");
+        assert_eq!(print("not (coins > 1)"), "not (coins > 1)");
+        assert_eq!(print("unless coins"), "not coins");
+        assert_eq!(print("in red room"), "in red room");
+        assert_eq!(print("not in red room"), "not in red room");
+        assert_eq!(print("either coins or wishes"), "either coins or wishes");
+        assert_eq!(print("one of coins, wishes or 3"), "either coins or wishes or 3");
+        assert_eq!(print("between 1 and 6"), "between 1 and 6");
+        assert_eq!(print("maximum of coins, 3"), "maximum of coins and 3");
+        assert_eq!(print("min of coins and wishes * 2"), "minimum of coins and wishes * 2");
+        assert_eq!(print("(either coins or wishes) + 1"), "(either coins or wishes) + 1");
+        assert_eq!(print("if coins then 1 else 2"), "if coins then 1 else 2");
+        assert_eq!(print("(if coins then 1 else 2) + 1"), "(if coins then 1 else 2) + 1");
+    }
+}
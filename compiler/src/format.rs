@@ -0,0 +1,655 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use crate::{Attribution, ExpressionParse, ExpressionParser, Mark, Source};
+use crate::element::{Element, ElementTree, ListElement, PlaythroughElement};
+use crate::error::SourceError;
+use crate::symbol::SymbolList;
+use crate::yaml::{FailsafeSchema, Mapping, Node, Value};
+
+const EXPRESSION_KEYS: [&str; 3] = ["when", "if", "unless"];
+const TEMPLATE_KEYS: [&str; 6] = ["label", "singularLabel", "pluralLabel", "description", "body", "prompt"];
+
+pub struct FormatError {
+    message: String,
+}
+
+impl Debug for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for FormatError {}
+
+pub struct SourceFormatter {
+    symbols: SymbolList,
+}
+
+impl SourceFormatter {
+    /// Collects names from all of a world's sources, so conditions in any one of them can be read and reprinted.
+    pub fn new(sources: &Vec<Source>) -> Self {
+        let mut problems = Vec::new();
+        let tree = ElementTree::from_sources(sources, &mut problems);
+        Self { symbols: SymbolList::extract(&tree, &mut problems) }
+    }
+
+    /// Rewrites world content in canonical style, leaving comments and unrecognized keys where they were.
+    pub fn format_source(&self, text: &str) -> Result<String, SourceError> {
+        self.format(text, false)
+    }
+
+    /// Rewrites a playthrough file in canonical style. Bodies are expected output, so their text is left alone.
+    pub fn format_playthrough(&self, text: &str) -> Result<String, SourceError> {
+        self.format(text, true)
+    }
+
+    fn format(&self, text: &str, playthrough: bool) -> Result<String, SourceError> {
+        let mut text = text.to_string();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        let documents = FailsafeSchema::parse_string(&text).map_err(SourceError::from)?;
+        let mut rewrite = Rewrite::new(&text, ExpressionParser::new(&self.symbols), !playthrough);
+        for document in &documents {
+            if let Some(node) = &document.root {
+                // Reading the document records which keys its elements ask for, and in what order, on each of its mappings
+                let attribution = Attribution::new("", node.start_mark, node.end_mark);
+                let mut problems = Vec::new();
+                if playthrough {
+                    ListElement::<PlaythroughElement>::from_node(node, attribution, &mut problems);
+                } else {
+                    ElementTree::from_node(node, attribution, &mut problems);
+                }
+                rewrite.visit(node, -1, None, true);
+            }
+        }
+        let formatted = rewrite.render(0, text.len());
+
+        // Anything the formatter can't handle safely should fail loudly rather than change what the content means
+        let reparsed = FailsafeSchema::parse_string(&formatted).map_err(|_| SourceError::from(FormatError { message: "Formatting produced invalid YAML".to_string() }))?;
+        let equivalent = reparsed.len() == documents.len() && documents.iter().zip(reparsed.iter()).all(|(a, b)| match (&a.root, &b.root) {
+            (Some(a), Some(b)) => rewrite.equivalent(a, b, None),
+            (None, None) => true,
+            _ => false,
+        });
+        if !equivalent {
+            return Err(SourceError::from(FormatError { message: "Formatting would have changed the meaning of this file".to_string() }));
+        }
+
+        Ok(formatted)
+    }
+}
+
+struct Chunk {
+    start: usize,
+    key: usize,
+    end: usize,
+}
+
+struct Reorder {
+    end: usize,
+    prefix: String,
+    pad: String,
+    chunks: Vec<Chunk>,
+}
+
+struct Rewrite<'t> {
+    text: &'t str,
+    starts: Vec<usize>,
+    parser: ExpressionParser<'t>,
+    templates: bool,
+    edits: BTreeMap<usize, (usize, String)>,
+    reorders: BTreeMap<usize, Reorder>,
+}
+
+impl<'t> Rewrite<'t> {
+    fn new(text: &'t str, parser: ExpressionParser<'t>, templates: bool) -> Self {
+        let mut starts = vec!(0);
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1).filter(|i| *i < text.len()));
+        Rewrite {
+            text,
+            starts,
+            parser,
+            templates,
+            edits: BTreeMap::new(),
+            reorders: BTreeMap::new(),
+        }
+    }
+
+    fn line_start(&self, line: usize) -> usize {
+        self.starts.get(line).copied().unwrap_or(self.text.len())
+    }
+
+    fn line(&self, line: usize) -> &'t str {
+        let start = self.line_start(line);
+        let end = self.line_start(line + 1);
+        self.text[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    fn offset(&self, mark: Mark) -> usize {
+        let line = mark.line as usize;
+        let start = self.line_start(line);
+        start + self.line(line).char_indices().nth(mark.column as usize).map_or(self.line(line).len(), |(i, _)| i)
+    }
+
+    // Some parsers mark a block scalar from its content rather than its header, so look back for one
+    fn start(&self, node: &Node) -> usize {
+        let start = self.offset(node.start_mark);
+        let (Value::Scalar(_), true) = (&node.value, self.text[self.line_start(self.line_of(start))..start].trim().is_empty()) else { return start };
+        let mut line = self.line_of(start);
+        while line > 0 {
+            line -= 1;
+            let text = plain_line(self.line(line));
+            if text.is_empty() {
+                continue;
+            }
+            let header = text.rsplit([' ', '-']).next().unwrap_or(text);
+            let before = text[..text.len() - header.len()].trim_end();
+            if header.starts_with(['|', '>']) && header[1..].chars().all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+                && (before.is_empty() || before.ends_with([':', '-'])) {
+                return self.line_start(line) + text.len() - header.len();
+            }
+            break;
+        }
+        start
+    }
+
+    fn indent(&self, line: usize) -> i64 {
+        self.line(line).chars().take_while(|c| *c == ' ').count() as i64
+    }
+
+    fn is_blank(&self, line: usize) -> bool {
+        self.line(line).trim().is_empty()
+    }
+
+    fn visit(&mut self, node: &Node, indent: i64, key: Option<&str>, root: bool) {
+        let start = self.start(node);
+        if self.text[start..].starts_with(['[', '{']) {
+            return;
+        }
+
+        match &node.value {
+            Value::Scalar(scalar) => self.visit_scalar(node, scalar, indent, key),
+            Value::Sequence(sequence) => {
+                for item in sequence {
+                    let dash = self.dash_column(item);
+                    self.visit(item, dash, key, false);
+                }
+            },
+            Value::Mapping(mapping) => {
+                if !root {
+                    self.reorder(mapping);
+                }
+                for (k, v) in mapping {
+                    if let Value::Scalar(name) = &k.value {
+                        self.normalize_separator(k, name, v);
+                        let child = if name == "then" { key } else { Some(name.as_str()) };
+                        self.visit(v, k.start_mark.column as i64, child, false);
+                    }
+                }
+            },
+        }
+    }
+
+    fn visit_scalar(&mut self, node: &Node, scalar: &str, indent: i64, key: Option<&str>) {
+        let Some(key) = key else { return };
+        let start = self.start(node);
+        let rest = &self.text[start..];
+        if scalar.is_empty() && !rest.starts_with(['"', '\'']) {
+            return;
+        }
+
+        let line = self.line_of(start);
+        let last = self.last_line(node, indent);
+        if EXPRESSION_KEYS.contains(&key) {
+            if !is_plain(rest) || last != line {
+                return;
+            }
+            let raw = plain_line(&self.text[start..self.line_start(line) + self.line(line).len()]);
+            let printed = self.expression(raw).map_or_else(|| raw.split_whitespace().collect::<Vec<&str>>().join(" "), |parse| parse.to_string());
+            if printed != raw {
+                self.edit(start, start + raw.len(), printed);
+            }
+        } else if self.templates && TEMPLATE_KEYS.contains(&key) {
+            let end = self.line_start(last) + self.line(last).len();
+            if rest.starts_with(['|', '>']) {
+                self.normalize_tags(self.line_start(line + 1).min(end), end);
+            } else if key == "body" {
+                if !is_plain(rest) && !rest.starts_with(['"', '\'']) {
+                    return;
+                }
+                let raw = if !is_plain(rest) {
+                    &self.text[start..=self.closing(start)]
+                } else if last == line {
+                    plain_line(&self.text[start..end])
+                } else {
+                    &self.text[start..end]
+                };
+                let collapsed = raw.split_whitespace().collect::<Vec<&str>>().join(" ");
+                // A comment after a plain scalar isn't part of its value, and would be lost
+                if is_plain(rest) && collapsed != scalar.split_whitespace().collect::<Vec<&str>>().join(" ") {
+                    return;
+                }
+                if let Some(block) = block_scalar(&normalize_tags(scalar), indent + 2) {
+                    self.edit(start, start + raw.len(), block);
+                }
+            } else if is_plain(rest) {
+                let end = if last == line { start + plain_line(&self.text[start..end]).len() } else { end };
+                self.normalize_tags(start, end);
+            }
+        }
+    }
+
+    fn normalize_separator(&mut self, key: &Node, name: &str, value: &Node) {
+        let key_start = self.offset(key.start_mark);
+        let value_start = self.start(value);
+        if !self.text[key_start..].starts_with(name) || self.line_of(value_start) != key.start_mark.line as usize {
+            return;
+        }
+        let separator = key_start + name.len();
+        if value_start <= separator || !self.text[separator..].starts_with(':') {
+            return;
+        }
+        let gap = &self.text[separator + 1..value_start];
+        if gap != " " && !gap.is_empty() && gap.chars().all(|c| c == ' ' || c == '\t') {
+            self.edit(separator + 1, value_start, " ".to_string());
+        }
+    }
+
+    fn normalize_tags(&mut self, start: usize, end: usize) {
+        let mut position = start;
+        while let Some(open) = self.text[position..end].find('{').map(|i| position + i) {
+            let Some(close) = self.text[open..end].find('}').map(|i| open + i) else { break };
+            let tag = normalize_tag(&self.text[open + 1..close]);
+            if tag != self.text[open..=close] {
+                self.edit(open, close + 1, tag);
+            }
+            position = close + 1;
+        }
+    }
+
+    fn edit(&mut self, start: usize, end: usize, replacement: String) {
+        self.edits.entry(start).or_insert((end, replacement));
+    }
+
+    fn reorder(&mut self, mapping: &Mapping) {
+        let order = mapping.lookups();
+        if order.is_empty() {
+            return;
+        }
+        let mut entries: Vec<(&Node, &Node)> = mapping.iter().collect();
+        entries.sort_by_key(|(key, _)| key.start_mark);
+        let Some(first) = entries.first() else { return };
+
+        // Only block mappings with one plain key per line, all lined up, can be moved around line by line
+        let column = first.0.start_mark.column;
+        let mut names = Vec::new();
+        let mut lines = Vec::new();
+        let mut previous_last = None;
+        for (key, value) in &entries {
+            let Value::Scalar(name) = &key.value else { return };
+            let line = key.start_mark.line as usize;
+            let key_start = self.offset(key.start_mark);
+            if key.start_mark.column != column || !self.text[key_start..].starts_with(name.as_str()) || !self.text[key_start + name.len()..].starts_with(':') {
+                return;
+            }
+            if previous_last.is_some_and(|previous_last| line <= previous_last) {
+                return;
+            }
+            if !names.is_empty() && self.text[self.line_start(line)..key_start].chars().any(|c| c != ' ') {
+                return;
+            }
+            let last = self.last_line(value, column as i64).max(line);
+            names.push(name.as_str());
+            lines.push((line, key_start, last));
+            previous_last = Some(last);
+        }
+
+        // Keys the elements don't know about keep their place after the ones they do
+        let ranks: Vec<usize> = names.iter().map(|name| order.iter().position(|key| key == name).unwrap_or(order.len())).collect();
+        if ranks.windows(2).all(|pair| pair[0] <= pair[1]) {
+            return;
+        }
+
+        // Comments directly above a key travel with it, and whatever follows the last value stays put
+        let mut chunks = Vec::new();
+        for i in 0..lines.len() {
+            let (line, key_start, _) = lines[i];
+            let start = if i == 0 {
+                self.line_start(line)
+            } else {
+                let mut first = line;
+                while first > lines[i - 1].2 + 1 && self.line(first - 1).trim_start().starts_with('#') {
+                    first -= 1;
+                }
+                self.line_start(first)
+            };
+            chunks.push(Chunk { start, key: key_start, end: 0 });
+        }
+        for i in 0..chunks.len() {
+            chunks[i].end = if i + 1 < chunks.len() { chunks[i + 1].start } else { self.line_start(lines[i].2 + 1) };
+        }
+        // The first key's line may also start the sequence item that holds the mapping
+        let start = chunks[0].start;
+        let prefix = &self.text[start..lines[0].1];
+        if prefix.chars().any(|c| c != ' ' && c != '-') {
+            return;
+        }
+
+        let end = chunks[chunks.len() - 1].end;
+        let mut chunks: Vec<(usize, Chunk)> = ranks.into_iter().zip(chunks).collect();
+        chunks.sort_by_key(|(rank, _)| *rank);
+        let chunks = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+        self.reorders.entry(start).or_insert(Reorder { end, prefix: prefix.to_string(), pad: " ".repeat(column as usize), chunks });
+    }
+
+    fn last_line(&self, node: &Node, indent: i64) -> usize {
+        let start = self.start(node);
+        let line = self.line_of(start);
+        let rest = &self.text[start..];
+        if rest.starts_with(['[', '{', '"', '\'']) {
+            return self.line_of(self.closing(start));
+        }
+
+        match &node.value {
+            Value::Scalar(scalar) => {
+                if scalar.is_empty() {
+                    line
+                } else if rest.starts_with(['|', '>']) {
+                    let mut last = line;
+                    let mut next = line + 1;
+                    while next < self.starts.len() && (self.is_blank(next) || self.indent(next) > indent) {
+                        if !self.is_blank(next) {
+                            last = next;
+                        }
+                        next += 1;
+                    }
+                    last
+                } else {
+                    if plain_line(self.line(line)).len() < self.line(line).trim_end().len() {
+                        return line;
+                    }
+                    let mut last = line;
+                    let mut next = line + 1;
+                    while next < self.starts.len() && (self.is_blank(next) || (self.indent(next) > indent && !self.line(next).trim_start().starts_with('#'))) {
+                        if !self.is_blank(next) {
+                            last = next;
+                        }
+                        next += 1;
+                    }
+                    last
+                }
+            },
+            Value::Sequence(sequence) => sequence.iter()
+                .map(|item| self.last_line(item, self.dash_column(item)))
+                .fold(line, usize::max),
+            Value::Mapping(mapping) => mapping.iter()
+                .map(|(key, value)| self.last_line(value, key.start_mark.column as i64).max(key.start_mark.line as usize))
+                .fold(line, usize::max),
+        }
+    }
+
+    fn dash_column(&self, item: &Node) -> i64 {
+        let start = self.start(item);
+        let before: Vec<char> = self.text[self.line_start(self.line_of(start))..start].chars().collect();
+        match before.iter().rposition(|c| *c != ' ') {
+            Some(i) if before[i] == '-' => i as i64,
+            _ => before.len() as i64 - 1,
+        }
+    }
+
+    // Finds the offset of the bracket or quote that closes the flow collection or quoted scalar starting at `start`
+    fn closing(&self, start: usize) -> usize {
+        let mut depth = 0;
+        let mut quote = None;
+        let mut chars = self.text[start..].char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                (Some('"'), '\\') => { chars.next(); },
+                (Some('\''), '\'') if chars.peek().is_some_and(|(_, c)| *c == '\'') => { chars.next(); },
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    if depth == 0 {
+                        return start + i;
+                    }
+                },
+                (Some(_), _) => {},
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '[' | '{') => depth += 1,
+                (None, ']' | '}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return start + i;
+                    }
+                },
+                _ => {},
+            }
+        }
+        self.text.len().saturating_sub(1)
+    }
+
+    // Conditions that don't parse, such as ones naming something undefined, are only tidied up
+    fn expression(&self, source: &str) -> Option<ExpressionParse> {
+        let result = self.parser.parse(source, &Attribution::new("", Mark::default(), Mark::default()));
+        if result.problems.is_empty() { result.parse } else { None }
+    }
+
+    fn equivalent(&self, a: &Node, b: &Node, key: Option<&str>) -> bool {
+        a.tag == b.tag && match (&a.value, &b.value) {
+            (Value::Scalar(a), Value::Scalar(b)) => match key.filter(|key| EXPRESSION_KEYS.contains(key)).and(self.expression(a)) {
+                Some(parse) => self.expression(b) == Some(parse),
+                None => canonical(a) == canonical(b),
+            },
+            (Value::Sequence(a), Value::Sequence(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| self.equivalent(a, b, key)),
+            (Value::Mapping(a), Value::Mapping(b)) => a.len() == b.len() && a.iter().all(|(name, a)| b.get(name).is_some_and(|b| {
+                let child = match &name.value {
+                    Value::Scalar(name) if name != "then" => Some(name.as_str()),
+                    Value::Scalar(_) => key,
+                    _ => None,
+                };
+                self.equivalent(a, b, child)
+            })),
+            _ => false,
+        }
+    }
+
+    fn render(&self, start: usize, end: usize) -> String {
+        let mut result = String::new();
+        let mut position = start;
+        while position < end {
+            if let Some(reorder) = self.reorders.get(&position).filter(|reorder| reorder.end <= end) {
+                for (i, chunk) in reorder.chunks.iter().enumerate() {
+                    if chunk.start < chunk.key {
+                        let key_line = self.line_start(self.line_of(chunk.key));
+                        result.push_str(&self.render(chunk.start, key_line));
+                    }
+                    result.push_str(if i == 0 { &reorder.prefix } else { &reorder.pad });
+                    result.push_str(&self.render(chunk.key, chunk.end));
+                }
+                position = reorder.end;
+            } else if let Some((edit_end, replacement)) = self.edits.get(&position).filter(|(edit_end, _)| *edit_end <= end) {
+                result.push_str(replacement);
+                position = *edit_end;
+            } else {
+                let next_reorder = self.reorders.range(position + 1..end).next().map_or(end, |(start, _)| *start);
+                let next_edit = self.edits.range(position + 1..end).next().map_or(end, |(start, _)| *start);
+                let next = next_reorder.min(next_edit);
+                result.push_str(&self.text[position..next]);
+                position = next;
+            }
+        }
+        result
+    }
+}
+
+fn is_plain(text: &str) -> bool {
+    !text.starts_with(['|', '>', '"', '\'', '[', '{', '&', '*', '!'])
+}
+
+// The part of a single line of plain scalar before any trailing comment
+fn plain_line(line: &str) -> &str {
+    let line = line.lines().next().unwrap_or("");
+    let end = line.char_indices()
+        .find(|(i, c)| *c == '#' && line[..*i].ends_with([' ', '\t']))
+        .map_or(line.len(), |(i, _)| i);
+    line[..end].trim_end()
+}
+
+fn normalize_tag(inner: &str) -> String {
+    let inner = inner.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if inner.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", inner)
+    }
+}
+
+fn normalize_tags(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else { break };
+        result.push_str(&rest[..open]);
+        result.push_str(&normalize_tag(&rest[open + 1..close]));
+        rest = &rest[close + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Renders a value as a literal block scalar, if it can be written as one without changing it
+fn block_scalar(value: &str, indent: i64) -> Option<String> {
+    let content = value.strip_suffix('\n').unwrap_or(value);
+    if content.is_empty() || content.starts_with([' ', '\t', '\n']) || content.ends_with('\n')
+        || content.chars().any(|c| c.is_control() && c != '\n')
+        || content.lines().any(|line| line.ends_with([' ', '\t'])) {
+        return None;
+    }
+
+    let pad = " ".repeat(indent.max(1) as usize);
+    let lines: Vec<String> = content.lines().map(|line| if line.is_empty() { String::new() } else { format!("{}{}", pad, line) }).collect();
+    Some(format!("|\n{}", lines.join("\n")))
+}
+
+fn canonical(scalar: &str) -> String {
+    normalize_tags(scalar).split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{compile_sources, Source, SourceError, SourceFormatter};
+
+    const EXAMPLE: [(&str, &str); 3] = [
+        ("world.yaml", include_str!("../../example/content/world.yaml")),
+        ("red_room.yaml", include_str!("../../example/content/red_room.yaml")),
+        ("blue_room.yaml", include_str!("../../example/content/blue_room.yaml")),
+    ];
+
+    fn format_source(text: &str) -> Result<String, SourceError> {
+        SourceFormatter::new(&vec!(Source::from_string("world.yaml", text)?)).format_source(text)
+    }
+
+    #[test]
+    fn test_format_reorders_keys_and_normalizes_spacing() {
+        let formatted = format_source(r#"version: 0.1
+storylets:
+  # Comments stay with the key below them
+  - label: Take a coin
+    when:   coins  >  1
+    # The name comes first
+    name: take-coin
+    body: You take {when coins == 1}the last{else} a{ end } coin.
+    description: It's {coins}.
+  - name: wait
+    unless: (Coins>3) or   wishes
+    label:    Wait
+    choose:
+      - assign:
+          - increment: coins
+        label: Wait {   a while   }
+qualities:
+  - name: coins
+  - name: wishes
+"#).unwrap();
+        assert_eq!(formatted, r#"version: 0.1
+storylets:
+  # Comments stay with the key below them
+    # The name comes first
+  - name: take-coin
+    when: coins > 1
+    label: Take a coin
+    description: It's { coins }.
+    body: |
+      You take { when coins == 1 }the last{ else } a{ end } coin.
+  - name: wait
+    unless: coins > 3 or wishes
+    label: Wait
+    choose:
+      - label: Wait { a while }
+        assign:
+          - increment: coins
+qualities:
+  - name: coins
+  - name: wishes
+"#);
+    }
+
+    #[test]
+    fn test_format_preserves_comments_and_unrecognized_keys() {
+        let source = r#"---
+# A quality
+qualities:
+  - name: coins # the currency
+    colour: gold
+    # Hidden for now
+    hidden: true
+    label: Coins
+"#;
+        assert_eq!(format_source(source).unwrap(), r#"---
+# A quality
+qualities:
+  - name: coins # the currency
+    # Hidden for now
+    hidden: true
+    label: Coins
+    colour: gold
+"#);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let formatter = SourceFormatter::new(&EXAMPLE.iter().map(|(path, text)| Source::from_string(path, text).unwrap()).collect());
+        for (path, text) in EXAMPLE {
+            let formatted = formatter.format_source(text).unwrap();
+            assert_eq!(formatter.format_source(&formatted).unwrap(), formatted, "{}", path);
+        }
+        let playthrough = include_str!("../../example/content/coins.test.yaml");
+        let formatted = formatter.format_playthrough(playthrough).unwrap();
+        assert_eq!(formatter.format_playthrough(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_does_not_change_model() {
+        let formatter = SourceFormatter::new(&EXAMPLE.iter().map(|(path, text)| Source::from_string(path, text).unwrap()).collect());
+        let compile = |format: bool| {
+            let sources = EXAMPLE.iter().map(|(path, text)| {
+                let text = if format { formatter.format_source(text).unwrap() } else { text.to_string() };
+                Source::from_string(path, &text).unwrap()
+            }).collect();
+            format!("{:?}", compile_sources(&sources).model)
+        };
+        assert_eq!(compile(true), compile(false));
+    }
+}
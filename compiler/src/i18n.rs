@@ -0,0 +1,6 @@
+mod catalog;
+mod localize;
+
+pub use crate::i18n::catalog::*;
+pub use crate::i18n::localize::Message;
+pub(crate) use crate::i18n::localize::Localizer;
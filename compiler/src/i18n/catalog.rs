@@ -0,0 +1,286 @@
+use crate::{Attribution, Mark, Problem};
+
+// A gettext catalog, where every entry's msgctxt is the stable ID of the text it translates
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub path: String,
+    pub language: Option<String>,
+    pub entries: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub source: String,
+    pub translation: String,
+    pub fuzzy: bool,
+    pub references: Vec<String>,
+    pub attribution: Attribution,
+}
+
+#[derive(Default)]
+struct PendingEntry {
+    start: u64,
+    context: Option<String>,
+    source: Option<String>,
+    translation: Option<String>,
+    fuzzy: bool,
+    references: Vec<String>,
+}
+
+impl PendingEntry {
+    fn is_empty(&self) -> bool {
+        self.context.is_none() && self.source.is_none() && self.translation.is_none() && self.references.is_empty() && !self.fuzzy
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Context,
+    Source,
+    Translation,
+    Other,
+}
+
+impl Catalog {
+    pub fn new(path: &str, language: Option<String>) -> Self {
+        Catalog {
+            path: path.to_string(),
+            language,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn parse(path: &str, input: &str) -> (Catalog, Vec<Problem>) {
+        let mut catalog = Catalog::new(path, None);
+        let mut problems = Vec::new();
+        let mut pending = PendingEntry::default();
+        let mut field = Field::Other;
+        let mut end = 0;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index as u64;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#~") {
+                if line.is_empty() {
+                    catalog.finish(std::mem::take(&mut pending), end, &mut problems);
+                    field = Field::Other;
+                }
+                continue;
+            }
+
+            let (keyword, rest) = if line.starts_with('#') {
+                (line, "")
+            } else {
+                match line.find('"') {
+                    Some(quote) => (line[..quote].trim(), &line[quote..]),
+                    None => (line, ""),
+                }
+            };
+
+            // Anything but a continuation or `msgstr` after a translation starts the next entry
+            if pending.translation.is_some() && !keyword.is_empty() && !keyword.starts_with("msgstr") {
+                catalog.finish(std::mem::take(&mut pending), end, &mut problems);
+            }
+            if pending.is_empty() {
+                pending.start = line_number;
+            }
+            end = line_number;
+
+            if let Some(flags) = keyword.strip_prefix("#,") {
+                pending.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+                field = Field::Other;
+                continue;
+            } else if let Some(references) = keyword.strip_prefix("#:") {
+                pending.references.extend(references.split_whitespace().map(String::from));
+                field = Field::Other;
+                continue;
+            } else if keyword.starts_with('#') {
+                continue;
+            }
+
+            let attribution = Attribution::new(path, Mark { line: line_number, column: 0 }, Mark { line: line_number, column: line.len() as u64 });
+            let Some(value) = unquote(rest) else {
+                problems.push(Problem::fatal("Expected a quoted string", &attribution));
+                continue;
+            };
+
+            field = match keyword {
+                "" => {
+                    let target = match field {
+                        Field::Context => &mut pending.context,
+                        Field::Source => &mut pending.source,
+                        Field::Translation => &mut pending.translation,
+                        Field::Other => {
+                            problems.push(Problem::fatal("Expected `msgctxt`, `msgid` or `msgstr` before this string", &attribution));
+                            continue;
+                        },
+                    };
+                    target.get_or_insert_with(String::new).push_str(&value);
+                    field
+                },
+                "msgctxt" => {
+                    pending.context = Some(value);
+                    Field::Context
+                },
+                "msgid" => {
+                    pending.source = Some(value);
+                    Field::Source
+                },
+                "msgstr" | "msgstr[0]" => {
+                    pending.translation = Some(value);
+                    Field::Translation
+                },
+                "msgid_plural" => Field::Other,
+                _ if keyword.starts_with("msgstr[") => Field::Other,
+                _ => {
+                    problems.push(Problem::fatal("Unrecognized keyword, expected `msgctxt`, `msgid` or `msgstr`", &attribution));
+                    Field::Other
+                },
+            };
+        }
+
+        catalog.finish(pending, end, &mut problems);
+        (catalog, problems)
+    }
+
+    fn finish(&mut self, pending: PendingEntry, end: u64, problems: &mut Vec<Problem>) {
+        let PendingEntry { start, context, source, translation, fuzzy, references } = pending;
+        let Some(source) = source else {
+            return;
+        };
+
+        let attribution = Attribution {
+            path: context.clone().unwrap_or_default(),
+            ..Attribution::new(&self.path, Mark { line: start, column: 0 }, Mark { line: end.max(start), column: 0 })
+        };
+
+        match context {
+            Some(id) => self.entries.push(CatalogEntry { id, source, translation: translation.unwrap_or_default(), fuzzy, references, attribution }),
+            None if source.is_empty() => {
+                let headers = translation.unwrap_or_default();
+                self.language = headers.lines()
+                    .filter_map(|header| header.split_once(':'))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("language"))
+                    .map(|(_, value)| value.trim().to_string())
+                    .filter(|value| !value.is_empty());
+            },
+            None => problems.push(Problem::warning("Translation has no `msgctxt`, so it can't be matched to any text", &attribution)
+                .with_help("extract the catalog again with `worldtree i18n extract`, and merge it with `msgmerge`".to_string())),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn to_po(&self) -> String {
+        let mut result = String::new();
+        result.push_str("msgid \"\"\nmsgstr \"\"\n");
+        result.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+        result.push_str(&format!("\"Language: {}\\n\"\n", escape(self.language.as_deref().unwrap_or(""))));
+        result.push_str("\"X-Generator: Worldtree\\n\"\n");
+
+        for entry in &self.entries {
+            result.push('\n');
+            if !entry.references.is_empty() {
+                result.push_str(&format!("#: {}\n", entry.references.join(" ")));
+            }
+            if entry.fuzzy {
+                result.push_str("#, fuzzy\n");
+            }
+            result.push_str(&format!("msgctxt {}\n", quote(&entry.id)));
+            result.push_str(&format!("msgid {}\n", quote(&entry.source)));
+            result.push_str(&format!("msgstr {}\n", quote(&entry.translation)));
+        }
+
+        result
+    }
+}
+
+// Multi-line strings are written one line per string, after an empty one, as msgmerge does
+fn quote(value: &str) -> String {
+    if !value.trim_end_matches('\n').contains('\n') {
+        return format!("\"{}\"", escape(value));
+    }
+
+    let mut result = String::from("\"\"");
+    for line in value.split_inclusive('\n') {
+        result.push_str(&format!("\n\"{}\"", escape(line)));
+    }
+    result
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                'n' => result.push('\n'),
+                't' => result.push('\t'),
+                'r' => result.push('\r'),
+                other => result.push(other),
+            }
+        } else if c == '"' {
+            return None;
+        } else {
+            result.push(c);
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_catalog_round_trips() {
+        let mut catalog = Catalog::new("fr.po", Some("fr".to_string()));
+        catalog.entries.push(CatalogEntry {
+            id: "world.yaml:storylets[0].label".to_string(),
+            source: "Say \"hello\"".to_string(),
+            translation: "Dire « bonjour »".to_string(),
+            fuzzy: false,
+            references: vec!("world.yaml:4".to_string()),
+            attribution: Attribution::new("fr.po", Mark { line: 0, column: 0 }, Mark { line: 0, column: 0 }),
+        });
+        catalog.entries.push(CatalogEntry {
+            id: "world.yaml:storylets[0].body".to_string(),
+            source: "One.\nTwo.".to_string(),
+            translation: String::new(),
+            fuzzy: true,
+            references: Vec::new(),
+            attribution: Attribution::new("fr.po", Mark { line: 0, column: 0 }, Mark { line: 0, column: 0 }),
+        });
+
+        let po = catalog.to_po();
+        let (parsed, problems) = Catalog::parse("fr.po", &po);
+        assert_eq!(problems, Vec::new());
+        assert_eq!(parsed.language.as_deref(), Some("fr"));
+        let entries: Vec<(&str, &str, &str, bool, usize)> = parsed.entries.iter()
+            .map(|entry| (entry.id.as_str(), entry.source.as_str(), entry.translation.as_str(), entry.fuzzy, entry.attribution.start_mark.line as usize))
+            .collect();
+        assert_eq!(entries, vec!(
+            ("world.yaml:storylets[0].label", "Say \"hello\"", "Dire « bonjour »", false, 6),
+            ("world.yaml:storylets[0].body", "One.\nTwo.", "", true, 11),
+        ));
+    }
+
+    #[test]
+    fn test_catalog_reports_malformed_lines() {
+        let (catalog, problems) = Catalog::parse("fr.po", "msgctxt \"a\"\nmsgid \"A\"\nmsgstr A\n\nmsgid \"B\"\nmsgstr \"b\"\n");
+        assert_eq!(catalog.entries.len(), 1);
+        let problems: Vec<(&str, u64)> = problems.iter().map(|problem| (problem.message, problem.attribution.start_mark.line)).collect();
+        assert_eq!(problems, vec!(
+            ("Expected a quoted string", 2),
+            ("Translation has no `msgctxt`, so it can't be matched to any text", 4),
+        ));
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use crate::{Attribution, Problem};
+use crate::element::ElementTree;
+use crate::i18n::catalog::{Catalog, CatalogEntry};
+use crate::template::{TemplateParse, TemplateParseNode, TemplateParser};
+use crate::text::{Text, TextParser};
+
+// Text to translate, as found in a source file, with the ID it's translated by
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: String,
+    pub source: String,
+    pub attribution: Attribution,
+}
+
+pub(crate) struct Localizer<'c> {
+    root: String,
+    catalog: Option<&'c Catalog>,
+    used: HashSet<String>,
+    messages: Vec<Message>,
+}
+
+impl<'c> Localizer<'c> {
+    pub fn new(element_tree: &ElementTree, catalog: Option<&'c Catalog>) -> Self {
+        Localizer {
+            root: root(element_tree),
+            catalog,
+            used: HashSet::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    // Only the text between branches is translated, so translations can't change conditions
+    pub fn template(&mut self, parse: TemplateParse, attribution: &Attribution, parser: &TemplateParser, problems: &mut Vec<Problem>) -> TemplateParse {
+        let mut index = 0;
+        self.template_nodes(parse, attribution, parser, problems, &mut index)
+    }
+
+    fn template_nodes(&mut self, parse: TemplateParse, attribution: &Attribution, parser: &TemplateParser, problems: &mut Vec<Problem>, index: &mut usize) -> TemplateParse {
+        let mut result = Vec::new();
+        let mut run = Vec::new();
+        for node in parse {
+            if is_inline(&node) {
+                run.push(node);
+                continue;
+            }
+
+            self.template_run(std::mem::take(&mut run), &mut result, attribution, parser, problems, index);
+            result.push(match node {
+                TemplateParseNode::Italic(nodes) => TemplateParseNode::Italic(self.template_nodes(nodes, attribution, parser, problems, index)),
+                TemplateParseNode::Bold(nodes) => TemplateParseNode::Bold(self.template_nodes(nodes, attribution, parser, problems, index)),
+                TemplateParseNode::Anchor(href, nodes) => TemplateParseNode::Anchor(href, self.template_nodes(nodes, attribution, parser, problems, index)),
+                TemplateParseNode::Branch(condition, then, otherwise) => {
+                    let then = self.template_nodes(then, attribution, parser, problems, index);
+                    let otherwise = otherwise.map(|otherwise| self.template_nodes(otherwise, attribution, parser, problems, index));
+                    TemplateParseNode::Branch(condition, then, otherwise)
+                },
+                node => node,
+            });
+        }
+        self.template_run(run, &mut result, attribution, parser, problems, index);
+        result
+    }
+
+    fn template_run(&mut self, run: TemplateParse, result: &mut TemplateParse, attribution: &Attribution, parser: &TemplateParser, problems: &mut Vec<Problem>, index: &mut usize) {
+        let source = render(&run);
+        let trimmed = source.trim();
+        if trimmed.is_empty() {
+            result.extend(run);
+            return;
+        }
+
+        let id = self.id(attribution, *index);
+        *index += 1;
+        let Some(entry) = self.lookup(id, trimmed, attribution, problems) else {
+            result.extend(run);
+            return;
+        };
+
+        let translation = parser.parse(&entry.translation, &entry.attribution);
+        if !translation.problems.is_empty() || !translation.parse.iter().all(is_inline) {
+            problems.push(Problem::warning("Translations can only contain text and formatting, so this one will be ignored", &entry.attribution)
+                .with_help("conditions stay in the source text, and each of their branches is translated separately".to_string()));
+            result.extend(run);
+            return;
+        }
+
+        // Translators see the text without the spacing around it, which joins it to its neighbours
+        let leading = &source[..source.len() - source.trim_start().len()];
+        let trailing = &source[source.trim_end().len()..];
+        if !leading.is_empty() {
+            result.push(TemplateParseNode::Text(leading.to_string()));
+        }
+        result.extend(translation.parse);
+        if !trailing.is_empty() {
+            result.push(TemplateParseNode::Text(trailing.to_string()));
+        }
+    }
+
+    pub fn text(&mut self, text: Text, source: &str, attribution: &Attribution, parser: &TextParser, problems: &mut Vec<Problem>) -> Text {
+        let source = source.trim();
+        if source.is_empty() {
+            return text;
+        }
+
+        let id = self.id(attribution, 0);
+        let Some(entry) = self.lookup(id, source, attribution, problems) else {
+            return text;
+        };
+
+        let translation = parser.parse(&entry.translation);
+        if !translation.problems.is_empty() {
+            problems.push(Problem::warning("Translation could not be parsed, so it will be ignored", &entry.attribution));
+            return text;
+        }
+        translation.text
+    }
+
+    // Reports translations that no longer match any text, and returns the messages that were found
+    pub fn finish(self, problems: &mut Vec<Problem>) -> Vec<Message> {
+        if let Some(catalog) = self.catalog {
+            for entry in &catalog.entries {
+                if !self.used.contains(&entry.id) {
+                    problems.push(Problem::warning("Translation doesn't match any text, so it will be ignored", &entry.attribution)
+                        .with_help(format!("was `{}` moved or removed? Extract the catalog again with `worldtree i18n extract`", entry.id)));
+                }
+            }
+        }
+        self.messages
+    }
+
+    fn lookup(&mut self, id: String, source: &str, attribution: &Attribution, problems: &mut Vec<Problem>) -> Option<&'c CatalogEntry> {
+        self.messages.push(Message { id: id.clone(), source: source.to_string(), attribution: attribution.clone() });
+        let catalog = self.catalog?;
+        self.used.insert(id.clone());
+
+        let Some(entry) = catalog.get(&id).filter(|entry| !entry.translation.trim().is_empty()) else {
+            problems.push(Problem::warning("Missing translation", attribution)
+                .with_help(format!("add a translation of `{}` to {}", id, catalog.path)));
+            return None;
+        };
+
+        if entry.source != source {
+            problems.push(Problem::warning("Translation is out of date, so it will be ignored", attribution)
+                .with_context("Translated from different text here", &entry.attribution)
+                .with_help(format!("update the translation of `{}` and its msgid", id)));
+            None
+        } else if entry.fuzzy {
+            problems.push(Problem::warning("Translation is marked fuzzy, so it will be ignored", attribution)
+                .with_context("Marked here", &entry.attribution)
+                .with_help("remove the fuzzy flag once the translation has been checked".to_string()));
+            None
+        } else {
+            Some(entry)
+        }
+    }
+
+    // IDs are the element's path, qualified by its file relative to the other sources and numbered after the first
+    // message in a template
+    fn id(&self, attribution: &Attribution, index: usize) -> String {
+        let source = attribution.source.strip_prefix(&self.root).unwrap_or(&attribution.source).replace('\\', "/");
+        let path = attribution.path.trim_start_matches('.');
+        if index == 0 {
+            format!("{}:{}", source, path)
+        } else {
+            format!("{}:{}#{}", source, path, index)
+        }
+    }
+}
+
+fn is_inline(node: &TemplateParseNode) -> bool {
+    match node {
+        TemplateParseNode::Text(_) => true,
+        TemplateParseNode::Paragraph | TemplateParseNode::Branch(_, _, _) => false,
+        TemplateParseNode::Italic(nodes) | TemplateParseNode::Bold(nodes) | TemplateParseNode::Anchor(_, nodes) => nodes.iter().all(is_inline),
+    }
+}
+
+// Writes inline nodes back in the syntax they're parsed from
+fn render(nodes: &[TemplateParseNode]) -> String {
+    let mut result = String::new();
+    for node in nodes {
+        match node {
+            TemplateParseNode::Text(text) => result.push_str(text),
+            TemplateParseNode::Italic(nodes) => result.push_str(&format!("_{}_", render(nodes))),
+            TemplateParseNode::Bold(nodes) => result.push_str(&format!("**{}**", render(nodes))),
+            TemplateParseNode::Anchor(href, nodes) => result.push_str(&format!("[{}]({})", render(nodes), href)),
+            TemplateParseNode::Paragraph | TemplateParseNode::Branch(_, _, _) => {},
+        }
+    }
+    result
+}
+
+// The directory shared by every source, so that IDs don't depend on where the world is checked out
+fn root(element_tree: &ElementTree) -> String {
+    let mut sources = element_tree.meta.iter().map(|meta| &meta.attribution.source)
+        .chain(element_tree.locations.iter().map(|location| &location.attribution.source))
+        .chain(element_tree.qualities.iter().map(|quality| &quality.attribution.source))
+        .chain(element_tree.storylets.iter().map(|storylet| &storylet.attribution.source));
+
+    let Some(first) = sources.next() else {
+        return String::new();
+    };
+    let mut root = match first.rfind(['/', '\\']) {
+        Some(index) => first[..=index].to_string(),
+        None => String::new(),
+    };
+    for source in sources {
+        while !source.starts_with(&root) {
+            root.pop();
+            match root.rfind(['/', '\\']) {
+                Some(index) => root.truncate(index + 1),
+                None => root.clear(),
+            }
+        }
+    }
+    root
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Catalog, Model, ModelParser, Problem};
+    use crate::template::TemplateParseNode;
+    use crate::testing::compile_world_with;
+
+    const WORLD: &str = r#"
+version: 0.1
+meta:
+  title: The _Harbor_
+storylets:
+  - name: initialize
+    go: harbor
+locations:
+  - name: harbor
+    label: The Harbor
+    body: Boats bob at their moorings.{ if harbor } You've been here before.{ end }
+"#;
+
+    fn compile(catalog: Option<&str>) -> (Model, Vec<(String, String)>, Vec<Problem>) {
+        let catalog = catalog.map(|catalog| Catalog::parse("fr.po", catalog).0);
+        let parser = match &catalog {
+            Some(catalog) => ModelParser::new().with_catalog(catalog),
+            None => ModelParser::new(),
+        };
+        let result = compile_world_with(WORLD, parser);
+        let messages = result.messages.iter().map(|message| (message.id.clone(), message.source.clone())).collect();
+        (result.model, messages, result.problems)
+    }
+
+    #[test]
+    fn test_extracts_messages_between_branches() {
+        let (_, messages, problems) = compile(None);
+        assert!(problems.is_empty());
+        assert_eq!(messages, vec!(
+            ("world.yaml:meta.title".to_string(), "The _Harbor_".to_string()),
+            ("world.yaml:locations[0].label".to_string(), "The Harbor".to_string()),
+            ("world.yaml:locations[0].body".to_string(), "Boats bob at their moorings.".to_string()),
+            ("world.yaml:locations[0].body#1".to_string(), "You've been here before.".to_string()),
+        ));
+    }
+
+    #[test]
+    fn test_translates_text_and_keeps_conditions() {
+        let (source, _, _) = compile(None);
+        let (model, _, problems) = compile(Some(r#"
+msgctxt "world.yaml:meta.title"
+msgid "The _Harbor_"
+msgstr "Le _Port_"
+
+msgctxt "world.yaml:locations[0].label"
+msgid "The Harbor"
+msgstr "Le Port"
+
+msgctxt "world.yaml:locations[0].body"
+msgid "Boats bob in the water."
+msgstr "Les bateaux flottent."
+
+msgctxt "world.yaml:locations[0].body#1"
+msgid "You've been here before."
+msgstr "Vous êtes déjà venu ici."
+
+msgctxt "world.yaml:locations[1].label"
+msgid "The Market"
+msgstr "Le Marché"
+"#));
+
+        assert_eq!(format!("{:?}", model.meta.title), r#"Some([Paragraph([Plain("Le "), Italic([Plain("Port")])])])"#);
+        assert_eq!(model.locations[0].label, vec!(TemplateParseNode::Text("Le Port".to_string())));
+        let Some(TemplateParseNode::Branch(source_condition, _, _)) = source.locations[0].body.as_ref().unwrap().get(1) else {
+            panic!("expected a branch");
+        };
+        assert_eq!(model.locations[0].body, Some(vec!(
+            TemplateParseNode::Text("Boats bob at their moorings.".to_string()),
+            TemplateParseNode::Branch(source_condition.clone(), vec!(
+                TemplateParseNode::Text(" ".to_string()),
+                TemplateParseNode::Text("Vous êtes déjà venu ici.".to_string()),
+            ), None),
+        )));
+
+        let problems: Vec<(&str, String)> = problems.iter().map(|problem| (problem.message, problem.attribution.path.clone())).collect();
+        assert_eq!(problems, vec!(
+            ("Translation is out of date, so it will be ignored", ".locations[0].body".to_string()),
+            ("Translation doesn't match any text, so it will be ignored", "world.yaml:locations[1].label".to_string()),
+        ));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{Attribution, Mark, Problem};
+use crate::element::{AssignElement, ConditionalElement, ElementTree, ExpressionElement, ListElement, NameElement, StoryletElement, TextTemplateElement, UriElement};
+use crate::expression::{ExpressionAtom, ExpressionLex, ExpressionLexer, ExpressionToken};
+use crate::symbol::SymbolList;
+use crate::template::{TemplateLexer, TemplateToken};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Quality,
+    Value,
+    Location,
+    Storylet,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolDefinition {
+    pub symbol: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub attribution: Attribution,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolReference {
+    pub symbol: String,
+    pub attribution: Attribution,
+    pub field: FieldKind,
+    // Followed by words that aren't part of any name, so a longer name may have been intended
+    pub trailing_words: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Expression,
+    Name,
+    Template,
+}
+
+// A scalar whose content can refer to symbols, either directly or inside template tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub kind: FieldKind,
+    pub attribution: Attribution,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    pub definitions: Vec<SymbolDefinition>,
+    pub references: Vec<SymbolReference>,
+    pub fields: Vec<Field>,
+}
+
+struct Index<'a> {
+    symbols: &'a SymbolList,
+    index: SymbolIndex,
+}
+
+impl SymbolIndex {
+    pub(crate) fn extract(element_tree: &ElementTree) -> Self {
+        let mut problems: Vec<Problem> = Vec::new();
+        let symbols = SymbolList::extract(element_tree, &mut problems);
+        let mut index = Index {
+            symbols: &symbols,
+            index: SymbolIndex::default(),
+        };
+
+        for quality in &element_tree.qualities {
+            index.define(&quality.name, SymbolKind::Quality);
+            index.template(&quality.label);
+            index.template(&quality.singular_label);
+            index.template(&quality.plural_label);
+            index.template(&quality.description);
+            index.uris(&quality.icon);
+            if let Some(values) = &quality.values {
+                for value in &values.elements {
+                    index.define(&value.name, SymbolKind::Value);
+                    index.template(&value.label);
+                    index.template(&value.description);
+                    index.uris(&value.icon);
+                }
+            }
+        }
+
+        for storylet in &element_tree.storylets {
+            index.storylet(storylet);
+        }
+
+        for location in &element_tree.locations {
+            index.define(&location.name, SymbolKind::Location);
+            index.template(&location.label);
+            index.template(&location.description);
+            index.template(&location.body);
+            if let Some(storylets) = &location.storylets {
+                for storylet in &storylets.elements {
+                    index.storylet(storylet);
+                }
+            }
+        }
+
+        index.index
+    }
+
+    pub fn definitions_of<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item=&'a SymbolDefinition> + 'a {
+        self.definitions.iter().filter(move |definition| definition.symbol == symbol)
+    }
+
+    pub fn references_to<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item=&'a SymbolReference> + 'a {
+        self.references.iter().filter(move |reference| reference.symbol == symbol)
+    }
+}
+
+impl<'a> Index<'a> {
+    fn define(&mut self, name: &Option<NameElement>, kind: SymbolKind) {
+        if let Some(name) = name {
+            self.index.definitions.push(SymbolDefinition {
+                symbol: self.symbols.normalize(&name.name),
+                name: name.name.trim().to_string(),
+                kind,
+                attribution: clamp(&name.attribution, &name.name),
+            });
+        }
+    }
+
+    fn storylet(&mut self, storylet: &StoryletElement) {
+        self.define(&storylet.name, SymbolKind::Storylet);
+        self.expression(&storylet.when);
+        self.expression(&storylet.r#if);
+        self.expression(&storylet.unless);
+        self.template(&storylet.label);
+        self.template(&storylet.description);
+        self.uris(&storylet.icon);
+        self.template(&storylet.body);
+        self.names(&storylet.go);
+        self.name_lists(&storylet.push);
+        self.name_lists(&storylet.shift);
+        self.assignments(&storylet.assign);
+
+        if let Some(choose) = &storylet.choose {
+            self.template(&choose.prompt);
+            for group in &choose.groups.elements {
+                self.expression(&group.limit);
+                self.expression(&group.shuffle);
+                for choice in &group.choices.elements {
+                    self.condition(choice);
+                    self.template(&choice.then.label);
+                    self.template(&choice.then.description);
+                    self.uris(&choice.then.icon);
+                    self.template(&choice.then.body);
+                    self.names(&choice.then.go);
+                    self.name_lists(&choice.then.push);
+                    self.name_lists(&choice.then.shift);
+                    self.assignments(&choice.then.assign);
+                }
+            }
+        }
+    }
+
+    fn assignments(&mut self, assignments: &Option<ListElement<AssignElement>>) {
+        if let Some(assignments) = assignments {
+            for group in &assignments.elements {
+                self.template(&group.description);
+                for assignment in &group.assignments.elements {
+                    self.condition(assignment);
+                    let then = &assignment.then;
+                    for name in [&then.set, &then.unset, &then.increase, &then.decrease, &then.increment, &then.decrement].into_iter().flatten() {
+                        self.name(name);
+                    }
+                    self.expression(&then.to);
+                    self.expression(&then.by);
+                }
+            }
+        }
+    }
+
+    fn condition<E>(&mut self, conditional: &ConditionalElement<E>) {
+        self.expression(&conditional.when);
+        self.expression(&conditional.r#if);
+        self.expression(&conditional.unless);
+    }
+
+    fn uris(&mut self, uris: &Option<ListElement<ConditionalElement<UriElement>>>) {
+        if let Some(uris) = uris {
+            for uri in &uris.elements {
+                self.condition(uri);
+            }
+        }
+    }
+
+    fn names(&mut self, names: &Option<ListElement<ConditionalElement<NameElement>>>) {
+        if let Some(names) = names {
+            for name in &names.elements {
+                self.condition(name);
+                self.name(&name.then);
+            }
+        }
+    }
+
+    fn name_lists(&mut self, name_lists: &Option<ListElement<ConditionalElement<ListElement<NameElement>>>>) {
+        if let Some(name_lists) = name_lists {
+            for name_list in &name_lists.elements {
+                self.condition(name_list);
+                for name in &name_list.then.elements {
+                    self.name(name);
+                }
+            }
+        }
+    }
+
+    fn name(&mut self, name: &NameElement) {
+        let attribution = clamp(&name.attribution, &name.name);
+        self.index.fields.push(Field {
+            kind: FieldKind::Name,
+            attribution: attribution.clone(),
+            source: name.name.clone(),
+        });
+
+        let symbol = self.symbols.normalize(&name.name);
+        if self.symbols.contains(&symbol) {
+            self.index.references.push(SymbolReference {
+                symbol,
+                attribution,
+                field: FieldKind::Name,
+                trailing_words: false,
+            });
+        }
+    }
+
+    fn expression(&mut self, expression: &Option<ExpressionElement>) {
+        if let Some(expression) = expression {
+            self.index.fields.push(Field {
+                kind: FieldKind::Expression,
+                attribution: clamp(&expression.attribution, &expression.source),
+                source: expression.source.clone(),
+            });
+
+            let lexer = ExpressionLexer::new(self.symbols);
+            self.references(&mut lexer.lex(&expression.source), &expression.source, &expression.attribution, FieldKind::Expression);
+        }
+    }
+
+    fn template(&mut self, template: &Option<TextTemplateElement>) {
+        if let Some(template) = template {
+            self.index.fields.push(Field {
+                kind: FieldKind::Template,
+                attribution: clamp(&template.attribution, &template.source),
+                source: template.source.clone(),
+            });
+
+            let lexer = TemplateLexer::new(self.symbols);
+            let mut lex = lexer.lex(&template.source, &template.attribution);
+            while let Some(token) = lex.next() {
+                if let TemplateToken::Tag(mut expression_lex) = token {
+                    self.references(&mut expression_lex, &template.source, &template.attribution, FieldKind::Template);
+                }
+            }
+        }
+    }
+
+    fn references(&mut self, lex: &mut ExpressionLex, source: &str, attribution: &Attribution, field: FieldKind) {
+        while let Some(marked_token) = lex.next() {
+            if let ExpressionToken::Atom(ExpressionAtom::Reference(symbol)) = marked_token.token {
+                let start_mark = skip_whitespace(source, marked_token.start_mark);
+                let end_mark = trim_whitespace(source, marked_token.end_mark);
+                self.index.references.push(SymbolReference {
+                    symbol,
+                    attribution: attribution.at_marks(start_mark, end_mark),
+                    field,
+                    trailing_words: lex.peek().is_some_and(|next| next.token == ExpressionToken::UnrecognizedToken),
+                });
+            }
+        }
+    }
+}
+
+// Plain scalars end at the start of the following line
+fn clamp(attribution: &Attribution, source: &str) -> Attribution {
+    if source.contains('\n') {
+        attribution.clone()
+    } else {
+        attribution.at_marks(Mark::default(), Mark { line: 0, column: source.chars().count() as u64 })
+    }
+}
+
+// Token marks include the whitespace preceding the token, and names matched before other words include the whitespace following them
+fn skip_whitespace(source: &str, mark: Mark) -> Mark {
+    let Some(line) = source.split('\n').nth(mark.line as usize) else {
+        return mark;
+    };
+
+    let mut mark = mark;
+    let skipped = line.get(mark.column as usize..).map_or(0, |rest| rest.len() - rest.trim_start().len());
+    mark.column += skipped as u64;
+    mark
+}
+
+fn trim_whitespace(source: &str, mark: Mark) -> Mark {
+    let Some(line) = source.split('\n').nth(mark.line as usize) else {
+        return mark;
+    };
+
+    let mut mark = mark;
+    let trimmed = line.get(..mark.column as usize).map_or(0, |before| before.len() - before.trim_end().len());
+    mark.column -= trimmed as u64;
+    mark
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Mark;
+    use crate::index::{SymbolIndex, SymbolKind};
+    use crate::testing::element_tree;
+
+    #[test]
+    fn test_symbol_index() {
+        let (tree, _) = element_tree(r#"
+version: 0.1
+qualities:
+  coins on the floor:
+    label: Coins
+storylets:
+  - name: pick up
+    when: not coins on the floor
+    body: |
+      You see
+      {when coins on the floor}coins{end}.
+    assign:
+      increment: coins on the floor
+    go: pick up
+"#);
+        let index = SymbolIndex::extract(&tree);
+
+        let definitions: Vec<(&str, SymbolKind, Mark)> = index.definitions.iter()
+            .map(|definition| (definition.symbol.as_str(), definition.kind, definition.attribution.start_mark))
+            .collect();
+        assert_eq!(definitions, vec!(
+            ("coins on the floor", SymbolKind::Quality, Mark { line: 3, column: 2 }),
+            ("pick up", SymbolKind::Storylet, Mark { line: 6, column: 10 }),
+        ));
+
+        // Past the first line of a scalar, marks count from the start of the line in its value
+        let body = index.fields.iter().find(|field| field.source.starts_with("You see")).unwrap().attribution.start_mark.line;
+        let references: Vec<(&str, Mark, Mark)> = index.references.iter()
+            .map(|reference| (reference.symbol.as_str(), reference.attribution.start_mark, reference.attribution.end_mark))
+            .collect();
+        assert_eq!(references, vec!(
+            ("coins on the floor", Mark { line: 7, column: 14 }, Mark { line: 7, column: 32 }),
+            ("coins on the floor", Mark { line: body + 1, column: 6 }, Mark { line: body + 1, column: 24 }),
+            ("pick up", Mark { line: 13, column: 8 }, Mark { line: 13, column: 15 }),
+            ("coins on the floor", Mark { line: 12, column: 17 }, Mark { line: 12, column: 35 }),
+        ));
+        assert!(index.references.iter().all(|reference| !reference.trailing_words));
+    }
+
+    #[test]
+    fn test_trailing_words() {
+        let (tree, _) = element_tree(r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: coins on the floor
+storylets:
+  - name: pick up
+    when: coins on the flor
+"#);
+        let index = SymbolIndex::extract(&tree);
+
+        let references: Vec<(&str, Mark, bool)> = index.references.iter()
+            .map(|reference| (reference.symbol.as_str(), reference.attribution.end_mark, reference.trailing_words))
+            .collect();
+        assert_eq!(references, vec!(("coins", Mark { line: 7, column: 15 }, true)));
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Serializer};
+
+// Tags from RFC 5646 that predate its syntax and don't follow it
+const IRREGULAR: [&str; 17] = [
+    "en-gb-oed", "i-ami", "i-bnn", "i-default", "i-enochian", "i-hak", "i-klingon", "i-lux", "i-mingo",
+    "i-navajo", "i-pwn", "i-tao", "i-tay", "i-tsu", "sgn-be-fr", "sgn-be-nl", "sgn-ch-de",
+];
+
+// Plural rules follow the CLDR rules for whole numbers, reduced to whether a count takes the form for one. The engine
+// has a copy of these tables, so that both runtimes choose the same label
+
+// Languages whose nouns don't change with number
+const UNINFLECTED: [&str; 28] = [
+    "bo", "dz", "id", "ig", "ii", "ja", "jbo", "jv", "kde", "kea", "km", "ko", "lkt", "lo", "ms", "my", "nqo", "sah",
+    "ses", "sg", "su", "th", "to", "vi", "wuu", "yo", "yue", "zh",
+];
+
+// Languages that treat zero like one, e.g. French
+const ZERO_IS_SINGULAR: [&str; 26] = [
+    "ak", "am", "as", "bho", "bn", "doi", "fa", "ff", "fr", "gu", "guw", "hi", "hy", "kab", "kn", "ln", "mg", "nso",
+    "pa", "pcm", "pt", "shi", "si", "ti", "wa", "zu",
+];
+
+// Languages that use the singular for 1, 21, 31 and so on, but not 11, e.g. Russian
+const ENDS_IN_ONE_IS_SINGULAR: [&str; 11] = ["be", "bs", "hr", "is", "lt", "lv", "mk", "ru", "sh", "sr", "uk"];
+
+// Languages that use the singular for 1, 101, 201 and so on, e.g. Slovenian
+const HUNDREDS_END_IN_ONE_IS_SINGULAR: [&str; 3] = ["dsb", "hsb", "sl"];
+
+// Languages that use the singular for every count that doesn't end in 4, 6 or 9, e.g. Filipino
+const ENDS_IN_FOUR_SIX_OR_NINE_IS_PLURAL: [&str; 3] = ["ceb", "fil", "tl"];
+
+// Languages that pair dotted and dotless i differently, lowercasing `I` to `ı` and `İ` to `i`
+const DOTLESS_I: [&str; 3] = ["az", "crh", "tr"];
+
+// A BCP 47 language tag, such as `en-US`, in canonical case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    tag: String,
+}
+
+impl Language {
+    pub fn parse(source: &str) -> Result<Language, &'static str> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err("the tag is empty");
+        }
+
+        let lower = source.to_lowercase();
+        if IRREGULAR.contains(&lower.as_str()) {
+            return Ok(Language { tag: canonical_case(source) });
+        }
+
+        let subtags: Vec<&str> = source.split('-').collect();
+        if subtags.iter().any(|subtag| subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric())) {
+            return Err("every subtag must be 1 to 8 letters or digits, separated by hyphens");
+        }
+
+        let mut index = 0;
+        if !subtags[0].eq_ignore_ascii_case("x") {
+            let language = subtags[0];
+            if language.len() < 2 || !is_alpha(language) {
+                return Err("the tag must start with a language of 2 to 8 letters, such as `en`");
+            }
+            index += 1;
+
+            // Up to three extended language subtags may follow a two or three letter language
+            if language.len() <= 3 {
+                let mut extlangs = 0;
+                while extlangs < 3 && index < subtags.len() && subtags[index].len() == 3 && is_alpha(subtags[index]) {
+                    index += 1;
+                    extlangs += 1;
+                }
+            }
+
+            if index < subtags.len() && subtags[index].len() == 4 && is_alpha(subtags[index]) {
+                index += 1;
+            }
+
+            if index < subtags.len() && ((subtags[index].len() == 2 && is_alpha(subtags[index])) || (subtags[index].len() == 3 && is_digit(subtags[index]))) {
+                index += 1;
+            }
+
+            let mut variants = Vec::new();
+            while index < subtags.len() && is_variant(subtags[index]) {
+                let variant = subtags[index].to_lowercase();
+                if variants.contains(&variant) {
+                    return Err("the same variant appears more than once");
+                }
+                variants.push(variant);
+                index += 1;
+            }
+
+            let mut singletons = Vec::new();
+            while index < subtags.len() && subtags[index].len() == 1 && !subtags[index].eq_ignore_ascii_case("x") {
+                let singleton = subtags[index].to_lowercase();
+                if singletons.contains(&singleton) {
+                    return Err("the same extension appears more than once");
+                }
+                singletons.push(singleton);
+                index += 1;
+
+                let start = index;
+                while index < subtags.len() && subtags[index].len() >= 2 {
+                    index += 1;
+                }
+                if index == start {
+                    return Err("every extension needs at least one subtag of 2 to 8 letters or digits");
+                }
+            }
+        }
+
+        if index < subtags.len() {
+            if !subtags[index].eq_ignore_ascii_case("x") {
+                return Err("subtags must appear in order: language, script, region, variants, extensions, then private use");
+            }
+            if index + 1 == subtags.len() {
+                return Err("private use needs at least one subtag after `x`");
+            }
+        }
+
+        Ok(Language { tag: canonical_case(source) })
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn primary(&self) -> &str {
+        self.tag.split('-').next().unwrap_or(&self.tag)
+    }
+
+    // Whether a count takes the singular form of a label. Labels only have two forms, so languages with more are
+    // approximated by the form used for one
+    pub fn is_singular(&self, count: u32) -> bool {
+        let primary = self.primary();
+        if UNINFLECTED.contains(&primary) {
+            false
+        } else if ZERO_IS_SINGULAR.contains(&primary) && !self.tag.eq_ignore_ascii_case("pt-PT") {
+            count <= 1
+        } else if ENDS_IN_ONE_IS_SINGULAR.contains(&primary) {
+            count % 10 == 1 && count % 100 != 11
+        } else if HUNDREDS_END_IN_ONE_IS_SINGULAR.contains(&primary) {
+            count % 100 == 1
+        } else if ENDS_IN_FOUR_SIX_OR_NINE_IS_PLURAL.contains(&primary) {
+            !matches!(count % 10, 4 | 6 | 9)
+        } else if primary == "br" {
+            count % 10 == 1 && !matches!(count % 100, 11 | 71 | 91)
+        } else if primary == "gd" {
+            count == 1 || count == 11
+        } else if primary == "gv" {
+            count % 10 == 1
+        } else if primary == "tzm" {
+            count <= 1 || (11..=99).contains(&count)
+        } else {
+            count == 1
+        }
+    }
+
+    // Lowercases one character at a time, so that names lowercase the same way whole or as they're read
+    pub fn to_lowercase(&self, source: &str) -> String {
+        let dotless_i = DOTLESS_I.contains(&self.primary());
+        let mut lowercase = String::with_capacity(source.len());
+        for c in source.chars() {
+            match c {
+                'I' if dotless_i => lowercase.push('ı'),
+                '\u{130}' if dotless_i => lowercase.push('i'),
+                _ => lowercase.extend(c.to_lowercase()),
+            }
+        }
+        lowercase
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language { tag: "en".to_string() }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tag)
+    }
+}
+
+impl Serialize for Language {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.tag)
+    }
+}
+
+fn is_alpha(subtag: &str) -> bool {
+    subtag.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn is_digit(subtag: &str) -> bool {
+    subtag.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_variant(subtag: &str) -> bool {
+    subtag.len() >= 5 || (subtag.len() == 4 && subtag.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+// Languages are lowercase, scripts are title case and regions are uppercase, except after a singleton
+fn canonical_case(source: &str) -> String {
+    let mut subtags: Vec<String> = Vec::new();
+    let mut after_singleton = false;
+    for (index, subtag) in source.split('-').enumerate() {
+        let canonical = if index > 0 && !after_singleton && subtag.len() == 2 {
+            subtag.to_uppercase()
+        } else if index > 0 && !after_singleton && subtag.len() == 4 && is_alpha(subtag) {
+            let lower = subtag.to_lowercase();
+            lower[..1].to_uppercase() + &lower[1..]
+        } else {
+            subtag.to_lowercase()
+        };
+        after_singleton |= subtag.len() == 1;
+        subtags.push(canonical);
+    }
+    subtags.join("-")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parses_language_tags() {
+        let tag = |source: &str| Language::parse(source).map(|language| language.tag().to_string());
+        assert_eq!(tag("en-us"), Ok("en-US".to_string()));
+        assert_eq!(tag(" zh-hant-tw "), Ok("zh-Hant-TW".to_string()));
+        assert_eq!(tag("sl-rozaj-biske"), Ok("sl-rozaj-biske".to_string()));
+        assert_eq!(tag("de-CH-1901"), Ok("de-CH-1901".to_string()));
+        assert_eq!(tag("es-419"), Ok("es-419".to_string()));
+        assert_eq!(tag("en-a-bbb-x-a-ccc"), Ok("en-a-bbb-x-a-ccc".to_string()));
+        assert_eq!(tag("x-whatever"), Ok("x-whatever".to_string()));
+        assert_eq!(tag("i-klingon"), Ok("i-klingon".to_string()));
+
+        assert!(tag("").is_err());
+        assert!(tag("en_US").is_err());
+        assert!(tag("e").is_err());
+        assert!(tag("en-US-").is_err());
+        assert!(tag("en-a").is_err());
+        assert!(tag("en-x").is_err());
+        assert!(tag("de-419-DE").is_err());
+        assert!(tag("sl-rozaj-rozaj").is_err());
+        assert!(tag("english").is_ok());
+        assert!(tag("englishes").is_err());
+    }
+
+    #[test]
+    fn test_plural_rules() {
+        let singular = |tag: &str| (0..=21).filter(|count| Language::parse(tag).unwrap().is_singular(*count)).collect::<Vec<u32>>();
+        assert_eq!(singular("en"), vec!(1));
+        assert_eq!(singular("fr-CA"), vec!(0, 1));
+        assert_eq!(singular("pt-PT"), vec!(1));
+        assert_eq!(singular("ru"), vec!(1, 21));
+        assert_eq!(singular("ja"), Vec::<u32>::new());
+        assert_eq!(singular("lv"), vec!(1, 21));
+        assert_eq!(singular("lt"), vec!(1, 21));
+        assert_eq!(singular("sl"), vec!(1));
+        assert!(Language::parse("sl").unwrap().is_singular(101));
+        assert_eq!(singular("fil"), vec!(0, 1, 2, 3, 5, 7, 8, 10, 11, 12, 13, 15, 17, 18, 20, 21));
+        assert_eq!(singular("gd"), vec!(1, 11));
+        assert_eq!(singular("yue-HK"), Vec::<u32>::new());
+    }
+}
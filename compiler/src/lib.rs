@@ -10,24 +10,66 @@ mod symbol;
 mod r#template;
 mod string_table;
 mod text;
+mod runtime;
+mod playthrough;
+mod index;
+mod format;
+mod i18n;
+mod language;
+mod bytecode;
+#[cfg(test)]
+mod testing;
 
 use std::path::PathBuf;
 pub use attribution::Attribution;
 pub use yaml::Mark;
-use source::{gather_sources, Source};
+use source::gather_sources;
+pub use source::Source;
 use element::ElementTree;
-pub use problem::{Context, Problem};
+pub use problem::{Context, Level, Problem};
 pub use error::SourceError;
 pub use model::*;
 pub use template::*;
 pub use text::*;
 pub use expression::*;
+pub use runtime::*;
+pub use playthrough::*;
+pub use index::*;
+pub use format::*;
+pub use i18n::{Catalog, CatalogEntry, Message};
+pub use language::Language;
+pub use bytecode::{decode_model, disassemble, encode_model, encode_obfuscated_model, BytecodeError};
+pub use symbol::normalize;
 
 pub fn compile(paths: &Vec<PathBuf>) -> Result<ModelParsingResult, SourceError> {
     let sources = gather_sources(paths)?;
+    Ok(compile_sources(&sources))
+}
+
+pub fn compile_sources(sources: &Vec<Source>) -> ModelParsingResult {
+    compile_with(sources, ModelParser::new())
+}
+
+pub fn compile_localized(paths: &Vec<PathBuf>, catalog: &Catalog) -> Result<ModelParsingResult, SourceError> {
+    let sources = gather_sources(paths)?;
+    Ok(compile_with(&sources, ModelParser::new().with_catalog(catalog)))
+}
+
+fn compile_with(sources: &Vec<Source>, parser: ModelParser) -> ModelParsingResult {
     let mut problems = Vec::new();
-    let tree = ElementTree::from_sources(&sources, &mut problems);
-    let model = ModelParser::new().parse(&tree);
+    let tree = ElementTree::from_sources(sources, &mut problems);
+    let model = parser.parse(&tree);
     problems.extend(model.problems);
-    Ok(ModelParsingResult { model: model.model, problems })
+    ModelParsingResult { model: model.model, problems, uris: model.uris, messages: model.messages }
+}
+
+pub fn index_sources(sources: &Vec<Source>) -> SymbolIndex {
+    let mut problems = Vec::new();
+    let tree = ElementTree::from_sources(sources, &mut problems);
+    SymbolIndex::extract(&tree)
+}
+
+pub fn compile_playthroughs(paths: &Vec<PathBuf>) -> Result<PlaythroughParsingResult, SourceError> {
+    let sources = gather_sources(paths)?;
+    Ok(Playthrough::from_sources(&sources))
 }
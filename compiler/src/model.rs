@@ -1,13 +1,25 @@
+mod analyze;
+mod obfuscate;
+
+use std::collections::HashSet;
+use std::time::Instant;
+use log::debug;
 use serde::{Serialize, Serializer};
 use serde::ser::SerializeMap;
-use crate::{ElementTree, Problem};
+use crate::{Attribution, ElementTree, Language, Problem};
+use crate::model::analyze::analyze;
+use crate::problem::Level;
 use crate::element::{AssignElement, ConditionalElement, ExpressionElement, ListElement, NameElement, StoryletElement, TextElement, TextTemplateElement, UriElement};
 use crate::expression::{ExpressionAtom, ExpressionOperator, ExpressionParse, ExpressionParser};
-use crate::symbol::{normalize, SymbolList};
+use crate::symbol::SymbolList;
 use crate::template::{TemplateParse, TemplateParseNode, TemplateParser};
 use crate::text::{Text, TextParser};
+use crate::i18n::{Catalog, Localizer, Message};
+
+pub use crate::model::obfuscate::{obfuscate_names, obfuscate_names_as};
+pub(crate) use crate::model::obfuscate::fnv1a;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Model {
     pub meta: Meta,
     pub qualities: Vec<Quality>,
@@ -15,16 +27,35 @@ pub struct Model {
     pub storylets: Vec<Storylet>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Meta {
     #[serde(skip_serializing_if="Option::is_none")]
     pub title: Option<Text>,
     #[serde(skip_serializing_if="Option::is_none")]
     pub description: Option<Text>,
     pub credits: Vec<Text>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub lang: Option<Language>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub author: Option<Text>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+impl Meta {
+    // The language to choose plural forms and compare names in. Typography doesn't depend on it: text is shown as written
+    pub fn language(&self) -> Language {
+        self.lang.clone().unwrap_or_default()
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Location {
     pub name: String,
     pub label: TemplateParse,
@@ -34,7 +65,7 @@ pub struct Location {
     pub body: Option<TemplateParse>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Quality {
     pub name: String,
@@ -58,7 +89,7 @@ pub struct Quality {
     pub exclusive: bool,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
 pub struct QualityStyle {
     #[serde(skip_serializing_if="std::ops::Not::not")]
     pub currency: bool,
@@ -72,7 +103,7 @@ pub struct QualityStyle {
     pub uncounted: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct QualityValue {
     pub name: String,
     #[serde(skip_serializing_if="Option::is_none")]
@@ -83,7 +114,7 @@ pub struct QualityValue {
     pub icon: Option<Conditional<String>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Conditional<T> {
     Always(T),
     Conditionally(ExpressionParse, T, Box<Conditional<T>>),
@@ -104,7 +135,7 @@ impl<T> Serialize for Conditional<T> where T: Serialize {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Storylet {
     pub name: String,
     #[serde(skip_serializing_if="Option::is_none")]
@@ -120,19 +151,23 @@ pub struct Storylet {
     #[serde(skip_serializing_if="Option::is_none")]
     pub navigation: Option<Conditional<String>>,
     #[serde(skip_serializing_if="Option::is_none")]
+    pub push: Option<Conditional<Vec<String>>>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub shift: Option<Conditional<Vec<String>>>,
+    #[serde(skip_serializing_if="Option::is_none")]
     pub assignments: Option<Vec<AssignmentGroup>>,
     #[serde(skip_serializing_if="Option::is_none")]
     pub choices: Option<Choices>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct AssignmentGroup {
     pub assignments: Vec<Assignment>,
     #[serde(skip_serializing_if="Option::is_none")]
     pub description: Option<TemplateParse>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Assignment {
     #[serde(skip_serializing_if="Option::is_none")]
     pub condition: Option<ExpressionParse>,
@@ -141,7 +176,7 @@ pub struct Assignment {
     pub operand: ExpressionParse,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum AssignmentOperation {
     Set,
@@ -150,14 +185,14 @@ pub enum AssignmentOperation {
     Decrement,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Choices {
     #[serde(skip_serializing_if="Option::is_none")]
     pub prompt: Option<TemplateParse>,
     pub groups: Vec<ChoiceGroup>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ChoiceGroup {
     #[serde(skip_serializing_if="Option::is_none")]
     pub limit: Option<ExpressionParse>,
@@ -166,7 +201,7 @@ pub struct ChoiceGroup {
     pub choices: Vec<Choice>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Choice {
     #[serde(skip_serializing_if="Option::is_none")]
     pub condition: Option<ExpressionParse>,
@@ -180,19 +215,39 @@ pub struct Choice {
     #[serde(skip_serializing_if="Option::is_none")]
     pub navigation: Option<Conditional<String>>,
     #[serde(skip_serializing_if="Option::is_none")]
+    pub push: Option<Conditional<Vec<String>>>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub shift: Option<Conditional<Vec<String>>>,
+    #[serde(skip_serializing_if="Option::is_none")]
     pub assignments: Option<Vec<AssignmentGroup>>,
 }
 
 pub struct ModelParsingResult {
     pub model: Model,
     pub problems: Vec<Problem>,
+    pub uris: Vec<UriReference>,
+    pub messages: Vec<Message>,
+}
+
+// Where a URI in the model was written, for problems found while packaging it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UriReference {
+    pub uri: String,
+    pub attribution: Attribution,
 }
 
-pub struct ModelParser {}
+pub struct ModelParser<'c> {
+    catalog: Option<&'c Catalog>,
+}
 
-impl ModelParser {
+impl<'c> ModelParser<'c> {
     pub fn new() -> Self {
-        Self {}
+        Self { catalog: None }
+    }
+
+    // Replaces text with its translation from the catalog, reporting text that isn't translated
+    pub fn with_catalog(self, catalog: &'c Catalog) -> Self {
+        Self { catalog: Some(catalog) }
     }
 
     pub fn parse(&self, element_tree: &ElementTree) -> ModelParsingResult {
@@ -201,29 +256,53 @@ impl ModelParser {
         let expression_parser = ExpressionParser::new(&symbols);
         let template_parser = TemplateParser::new(&symbols);
         let text_parser = TextParser::new();
+        let storylets = element_tree.storylets.iter()
+            .chain(element_tree.locations.iter().flat_map(|location| location.storylets.iter().flat_map(|storylets| storylets.elements.iter())))
+            .filter_map(|storylet| storylet.name.as_ref().map(|name| symbols.normalize(&name.name)))
+            .collect();
         let parse = Parse {
             expression_parser,
             template_parser,
             text_parser,
             symbols: &symbols,
+            storylets,
             problems,
+            uris: Vec::new(),
+            localizer: Localizer::new(element_tree, self.catalog),
         };
 
-        parse.parse_model(element_tree)
+        let mut result = parse.parse_model(element_tree);
+        let model = &result.model;
+        let choices: usize = model.storylets.iter().filter_map(|storylet| storylet.choices.as_ref()).flat_map(|choices| &choices.groups).map(|group| group.choices.len()).sum();
+        debug!("Parsed model with {} qualities, {} storylets, {} choices and {} locations", model.qualities.len(), model.storylets.len(), choices, model.locations.len());
+
+        if !result.problems.iter().any(|problem| problem.level == Level::Fatal) {
+            let start = Instant::now();
+            analyze(&result.model, element_tree, &mut result.problems);
+            debug!("Analyzed model in {:.2?}", start.elapsed());
+        }
+        result
     }
 }
 
-struct Parse<'a> {
+struct Parse<'a, 'c> {
     template_parser: TemplateParser<'a>,
     expression_parser: ExpressionParser<'a>,
     text_parser: TextParser,
     symbols: &'a SymbolList,
+    storylets: HashSet<String>,
     problems: Vec<Problem>,
+    uris: Vec<UriReference>,
+    localizer: Localizer<'c>,
 }
 
-impl<'a> Parse<'a> {
+impl<'a, 'c> Parse<'a, 'c> {
     fn parse_conditional_uri(&mut self, element: &Option<ListElement<ConditionalElement<UriElement>>>) -> Option<Conditional<String>> {
         if let Some(list) = element {
+            self.uris.extend(list.elements.iter().map(|list_element| UriReference {
+                uri: list_element.then.uri.clone(),
+                attribution: list_element.then.attribution.clone(),
+            }));
             let mut conditional: Option<Conditional<String>> = None;
             for list_element in list.elements.iter().rev() {
                 let mut conditions = Vec::new();
@@ -300,12 +379,69 @@ impl<'a> Parse<'a> {
         }
     }
 
+    fn parse_conditional_storylets(&mut self, element: &Option<ListElement<ConditionalElement<ListElement<NameElement>>>>) -> Option<Conditional<Vec<String>>> {
+        if let Some(list) = element {
+            // A plain sequence of names is a single sequence of storylets rather than a list of alternatives
+            if list.elements.iter().all(|list_element| list_element.when.is_none() && list_element.r#if.is_none() && list_element.unless.is_none()) {
+                let storylets = list.elements.iter().flat_map(|list_element| self.require_storylets(&list_element.then)).collect();
+                return Some(Conditional::Always(storylets));
+            }
+
+            let mut conditional: Option<Conditional<Vec<String>>> = None;
+            for list_element in list.elements.iter().rev() {
+                let mut conditions = Vec::new();
+
+                if let Some(when) = self.parse_expression(&list_element.when) {
+                    conditions.push(when);
+                }
+
+                if let Some(r#if) = self.parse_expression(&list_element.r#if) {
+                    conditions.push(r#if);
+                }
+
+                if let Some(unless) = self.parse_expression(&list_element.unless) {
+                    conditions.push(ExpressionParse::Operation(ExpressionOperator::Not, vec!(unless)));
+                }
+
+                let condition = if conditions.is_empty() { None } else if conditions.len() == 1 { conditions.pop() } else {
+                    Some(ExpressionParse::Operation(ExpressionOperator::And, conditions))
+                };
+
+                let storylets = self.require_storylets(&list_element.then);
+
+                if let Some(condition) = condition {
+                    if let Some(next) = conditional {
+                        conditional = Some(Conditional::Conditionally(condition, storylets, next.into()));
+                    } else {
+                        self.problems.push(Problem::fatal("Last option must be unconditional, but one or more conditions were provided. Did you mean to include another element?", &list.attribution));
+                        conditional = Some(Conditional::Always(storylets));
+                    }
+                } else {
+                    conditional = Some(Conditional::Always(storylets));
+                }
+            }
+            conditional
+        } else {
+            None
+        }
+    }
+
+    fn require_storylets(&mut self, names: &ListElement<NameElement>) -> Vec<String> {
+        names.elements.iter().map(|name| {
+            let symbol = self.symbols.require(name, &mut self.problems);
+            if self.symbols.contains(&symbol) && !self.storylets.contains(&symbol) {
+                self.problems.push(Problem::fatal("Only storylets can be pushed or shifted, but this is the name of a quality or location", &name.attribution));
+            }
+            symbol
+        }).collect()
+    }
+
     fn parse_template(&mut self, template: &Option<TextTemplateElement>) -> Option<TemplateParse> {
         if let Some(template) = template {
             let result = self.template_parser.parse(&template.source, &template.attribution);
             self.problems.extend(result.problems);
             if !result.parse.is_empty() {
-                Some(result.parse)
+                Some(self.localizer.template(result.parse, &template.attribution, &self.template_parser, &mut self.problems))
             } else {
                 None
             }
@@ -333,7 +469,7 @@ impl<'a> Parse<'a> {
             let result = self.text_parser.parse(&text.source);
             self.problems.extend(result.problems);
             if !result.text.is_empty() {
-                Some(result.text)
+                Some(self.localizer.text(result.text, &text.source, &text.attribution, &self.text_parser, &mut self.problems))
             } else {
                 None
             }
@@ -342,6 +478,25 @@ impl<'a> Parse<'a> {
         }
     }
 
+    fn parse_language(&mut self, lang: &Option<TextElement>) -> Option<Language> {
+        let lang = lang.as_ref()?;
+        match Language::parse(&lang.source) {
+            Ok(language) => Some(language),
+            Err(reason) => {
+                let help = match Language::parse(&lang.source.replace('_', "-")) {
+                    Ok(language) => format!("did you mean `{}`?", language),
+                    Err(_) => reason.to_string(),
+                };
+                self.problems.push(Problem::warning("Expected a BCP 47 language tag, such as `en-US`", &lang.attribution).with_help(help));
+                None
+            },
+        }
+    }
+
+    fn parse_plain(&self, text: &Option<TextElement>) -> Option<String> {
+        text.as_ref().map(|text| text.source.trim().to_string()).filter(|text| !text.is_empty())
+    }
+
     fn parse_condition(&mut self, when: &Option<ExpressionElement>, r#if: &Option<ExpressionElement>, unless: &Option<ExpressionElement>, contextual_condition: Option<ExpressionParse>) -> Option<ExpressionParse> {
         let mut conditions = Vec::new();
         conditions.extend(contextual_condition);
@@ -421,14 +576,14 @@ impl<'a> Parse<'a> {
                     } else if let Some(increment) = &assignment.then.increment {
                         Some(Assignment {
                             condition,
-                            subject: normalize(&increment.name),
+                            subject: self.symbols.normalize(&increment.name),
                             operation: AssignmentOperation::Increment,
                             operand: self.parse_expression(&assignment.then.by).unwrap_or(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(1))),
                         })
                     } else if let Some(decrement) = &assignment.then.decrement {
                         Some(Assignment {
                             condition,
-                            subject: normalize(&decrement.name),
+                            subject: self.symbols.normalize(&decrement.name),
                             operation: AssignmentOperation::Decrement,
                             operand: self.parse_expression(&assignment.then.by).unwrap_or(ExpressionParse::Atom(ExpressionAtom::NumericLiteral(1))),
                         })
@@ -493,6 +648,8 @@ impl<'a> Parse<'a> {
             let icon = self.parse_conditional_uri(&storylet.icon);
             let body = self.parse_template(&storylet.body);
             let navigation = self.parse_conditional_name(&storylet.go);
+            let push = self.parse_conditional_storylets(&storylet.push);
+            let shift = self.parse_conditional_storylets(&storylet.shift);
 
             let assignments = self.parse_assignments(&storylet.assign);
 
@@ -515,6 +672,8 @@ impl<'a> Parse<'a> {
                         let icon = self.parse_conditional_uri(&choice.then.icon);
                         let body = self.parse_template(&choice.then.body);
                         let navigation = self.parse_conditional_name(&choice.then.go);
+                        let push = self.parse_conditional_storylets(&choice.then.push);
+                        let shift = self.parse_conditional_storylets(&choice.then.shift);
                         let assignments = self.parse_assignments(&choice.then.assign);
 
                         Choice {
@@ -524,6 +683,8 @@ impl<'a> Parse<'a> {
                             icon,
                             body,
                             navigation,
+                            push,
+                            shift,
                             assignments,
                         }
                     }).collect();
@@ -551,6 +712,8 @@ impl<'a> Parse<'a> {
                 icon,
                 body,
                 navigation,
+                push,
+                shift,
                 assignments,
                 choices,
             })
@@ -565,15 +728,15 @@ impl<'a> Parse<'a> {
             let title = self.parse_text(&meta.title);
             let description = self.parse_text(&meta.description);
             let credits = if let Some(credits) = &meta.credits {
-                credits.elements.iter().map(|credit| {
-                    let credit = self.text_parser.parse(&credit.source);
-                    self.problems.extend(credit.problems);
-                    if !credit.text.is_empty() {
-                        Some(credit.text)
-                    } else {
-                        None
-                    }
-                }).flatten().collect()
+                credits.elements.iter().filter_map(|credit| self.parse_text(&Some(credit.clone()))).collect()
+            } else {
+                Vec::new()
+            };
+
+            let lang = self.parse_language(&meta.lang);
+            let author = self.parse_text(&meta.author);
+            let keywords = if let Some(keywords) = &meta.keywords {
+                keywords.elements.iter().map(|keyword| keyword.source.trim().to_string()).filter(|keyword| !keyword.is_empty()).collect()
             } else {
                 Vec::new()
             };
@@ -582,12 +745,24 @@ impl<'a> Parse<'a> {
                 title,
                 description,
                 credits,
+                lang,
+                author,
+                image: self.parse_plain(&meta.image),
+                favicon: self.parse_plain(&meta.favicon),
+                url: self.parse_plain(&meta.url),
+                keywords,
             }
         } else {
             Meta {
                 title: None,
                 description: None,
                 credits: Vec::new(),
+                lang: None,
+                author: None,
+                image: None,
+                favicon: None,
+                url: None,
+                keywords: Vec::new(),
             }
         };
 
@@ -740,12 +915,13 @@ impl<'a> Parse<'a> {
 
         let locations: Vec<Location> = element_tree.locations.iter().map(|location| {
             if let Some(name) = &location.name {
-                let name = name.name.clone();
                 let label = if let Some(label) = self.parse_template(&location.label) {
                     label
                 } else {
-                    vec!(TemplateParseNode::Text(name.clone()))
+                    vec!(TemplateParseNode::Text(name.name.clone()))
                 };
+                // Locations are only ever referred to, by navigation and `in`, so they're named as references are
+                let name = self.symbols.normalize(&name.name);
                 let description = self.parse_template(&location.description);
                 let body = self.parse_template(&location.body);
 
@@ -775,7 +951,9 @@ impl<'a> Parse<'a> {
                 storylets,
                 locations,
             },
+            messages: self.localizer.finish(&mut self.problems),
             problems: self.problems,
+            uris: self.uris,
         }
     }
 }
@@ -0,0 +1,560 @@
+use std::collections::{HashMap, HashSet};
+use crate::{Assignment, AssignmentGroup, AssignmentOperation, Attribution, Choice, Conditional, Language, Model, Problem, Storylet};
+use crate::element::{ElementTree, StoryletElement};
+use crate::expression::{ExpressionAtom, ExpressionOperator, ExpressionParse};
+use crate::symbol::normalize;
+use crate::template::{TemplateParse, TemplateParseNode};
+
+pub fn analyze(model: &Model, element_tree: &ElementTree, problems: &mut Vec<Problem>) {
+    let analysis = Analysis::new(model, element_tree);
+    analysis.check_conditions(problems);
+    analysis.check_locations(problems);
+    analysis.check_qualities(problems);
+    analysis.check_reachability(problems);
+}
+
+struct Analysis<'a> {
+    model: &'a Model,
+    storylet_elements: HashMap<String, &'a StoryletElement>,
+    location_attributions: HashMap<String, &'a Attribution>,
+    quality_attributions: HashMap<String, &'a Attribution>,
+    quality_values: HashMap<String, String>,
+    qualities: HashSet<String>,
+    storylets: HashSet<String>,
+    locations: HashSet<String>,
+    assigned: HashSet<String>,
+    read: HashSet<String>,
+    destinations: HashSet<String>,
+    language: Language,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(model: &'a Model, element_tree: &'a ElementTree) -> Self {
+        let language = model.meta.language();
+        let mut storylet_elements = HashMap::new();
+        let mut location_attributions = HashMap::new();
+        let mut quality_attributions = HashMap::new();
+
+        for storylet in element_tree.storylets.iter().chain(element_tree.locations.iter().flat_map(|location| location.storylets.iter().flat_map(|storylets| storylets.elements.iter()))) {
+            if let Some(name) = &storylet.name {
+                storylet_elements.insert(normalize(&name.name, &language), storylet);
+            }
+        }
+        for location in &element_tree.locations {
+            if let Some(name) = &location.name {
+                location_attributions.insert(normalize(&name.name, &language), &name.attribution);
+            }
+        }
+        for quality in &element_tree.qualities {
+            if let Some(name) = &quality.name {
+                quality_attributions.insert(normalize(&name.name, &language), &name.attribution);
+            }
+        }
+
+        let mut quality_values = HashMap::new();
+        for quality in &model.qualities {
+            for value in quality.values.iter().flatten() {
+                quality_values.insert(normalize(&value.name, &language), normalize(&quality.name, &language));
+            }
+        }
+
+        let mut analysis = Self {
+            model,
+            storylet_elements,
+            location_attributions,
+            quality_attributions,
+            quality_values,
+            qualities: model.qualities.iter().map(|quality| normalize(&quality.name, &language)).collect(),
+            storylets: model.storylets.iter().map(|storylet| normalize(&storylet.name, &language)).collect(),
+            locations: model.locations.iter().map(|location| normalize(&location.name, &language)).collect(),
+            assigned: HashSet::new(),
+            read: HashSet::new(),
+            destinations: HashSet::new(),
+            language,
+        };
+        analysis.gather();
+        analysis
+    }
+
+    fn gather(&mut self) {
+        let model = self.model;
+        let mut read = Vec::new();
+        let mut assigned = Vec::new();
+        let mut destinations = Vec::new();
+
+        for quality in &model.qualities {
+            for template in [&quality.label, &quality.singular_label, &quality.plural_label, &quality.description].into_iter().flatten() {
+                template_references(template, &mut read);
+            }
+            conditional_references(&quality.icon, &mut read);
+            for value in quality.values.iter().flatten() {
+                for template in [&value.label, &value.description].into_iter().flatten() {
+                    template_references(template, &mut read);
+                }
+                conditional_references(&value.icon, &mut read);
+            }
+        }
+
+        for location in &model.locations {
+            template_references(&location.label, &mut read);
+            for template in [&location.description, &location.body].into_iter().flatten() {
+                template_references(template, &mut read);
+            }
+        }
+
+        for storylet in &model.storylets {
+            if let Some(condition) = &storylet.condition {
+                references(condition, &mut read);
+            }
+            for template in [&storylet.label, &storylet.description, &storylet.body].into_iter().flatten() {
+                template_references(template, &mut read);
+            }
+            conditional_references(&storylet.icon, &mut read);
+            conditional_references(&storylet.navigation, &mut read);
+            conditional_references(&storylet.push, &mut read);
+            conditional_references(&storylet.shift, &mut read);
+            conditional_values(&storylet.navigation, &mut destinations);
+            assignment_references(&storylet.assignments, &mut read, &mut assigned);
+
+            if let Some(choices) = &storylet.choices {
+                if let Some(prompt) = &choices.prompt {
+                    template_references(prompt, &mut read);
+                }
+                for group in &choices.groups {
+                    for expression in [&group.limit, &group.shuffle].into_iter().flatten() {
+                        references(expression, &mut read);
+                    }
+                    for choice in &group.choices {
+                        if let Some(condition) = &choice.condition {
+                            references(condition, &mut read);
+                        }
+                        template_references(&choice.label, &mut read);
+                        for template in [&choice.description, &choice.body].into_iter().flatten() {
+                            template_references(template, &mut read);
+                        }
+                        conditional_references(&choice.icon, &mut read);
+                        conditional_references(&choice.navigation, &mut read);
+                        conditional_references(&choice.push, &mut read);
+                        conditional_references(&choice.shift, &mut read);
+                        conditional_values(&choice.navigation, &mut destinations);
+                        assignment_references(&choice.assignments, &mut read, &mut assigned);
+                    }
+                }
+            }
+        }
+
+        self.read = read.iter().map(|name| self.subject(name)).collect();
+        self.assigned = assigned.iter().map(|name| self.subject(name)).collect();
+        self.destinations = destinations.iter().map(|name| normalize(name, &self.language)).collect();
+    }
+
+    fn subject(&self, name: &str) -> String {
+        let name = normalize(name, &self.language);
+        self.quality_values.get(&name).cloned().unwrap_or(name)
+    }
+
+    fn check_conditions(&self, problems: &mut Vec<Problem>) {
+        for storylet in &self.model.storylets {
+            if let Some(condition) = &storylet.condition {
+                let known = |name: &str| {
+                    let name = self.subject(name);
+                    !self.qualities.contains(&name) || self.assigned.contains(&name)
+                };
+                if !satisfiable(condition, &known) {
+                    let mut names = Vec::new();
+                    references(condition, &mut names);
+                    let mut seen = HashSet::new();
+                    names.retain(|name| !known(name) && seen.insert(name.clone()));
+                    let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                    problems.push(Problem::warning("This storylet's condition depends on a quality that is never assigned, so the storylet will never be available", &self.condition_attribution(storylet))
+                        .with_help(format!("the condition is `{}`, and nothing assigns {}", condition, names.join(" or "))));
+                }
+            }
+        }
+    }
+
+    fn check_locations(&self, problems: &mut Vec<Problem>) {
+        for location in &self.model.locations {
+            let name = normalize(&location.name, &self.language);
+            if !self.destinations.contains(&name) {
+                if let Some(attribution) = self.location_attributions.get(&name) {
+                    problems.push(Problem::warning("This location is never the destination of a `go`, so it can never be visited", attribution));
+                }
+            }
+        }
+    }
+
+    fn check_qualities(&self, problems: &mut Vec<Problem>) {
+        for quality in &self.model.qualities {
+            let name = normalize(&quality.name, &self.language);
+            if self.assigned.contains(&name) && !self.read.contains(&name) {
+                if let Some(attribution) = self.quality_attributions.get(&name) {
+                    problems.push(Problem::warning("This quality is assigned but never read by any condition, expression or template", attribution));
+                }
+            }
+        }
+    }
+
+    fn check_reachability(&self, problems: &mut Vec<Problem>) {
+        let mut reachable_storylets: HashSet<String> = HashSet::new();
+        let mut reachable_locations: HashSet<String> = HashSet::new();
+        let mut assignable: HashSet<String> = HashSet::new();
+
+        loop {
+            let mut changed = false;
+            for storylet in &self.model.storylets {
+                let name = normalize(&storylet.name, &self.language);
+                if reachable_storylets.contains(&name) {
+                    continue;
+                }
+
+                let known = |reference: &str| {
+                    let reference = self.subject(reference);
+                    if self.storylets.contains(&reference) {
+                        reachable_storylets.contains(&reference)
+                    } else if self.locations.contains(&reference) {
+                        reachable_locations.contains(&reference)
+                    } else if self.qualities.contains(&reference) {
+                        assignable.contains(&reference)
+                    } else {
+                        true
+                    }
+                };
+                if !storylet.condition.as_ref().is_none_or(|condition| satisfiable(condition, &known)) {
+                    continue;
+                }
+
+                let mut effects = Effects::default();
+                effects.storylet(storylet, &known);
+                for name in effects.assigned {
+                    assignable.insert(self.subject(&name));
+                }
+                for name in effects.destinations {
+                    reachable_locations.insert(normalize(&name, &self.language));
+                }
+                reachable_storylets.insert(name);
+                changed = true;
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for storylet in &self.model.storylets {
+            let name = normalize(&storylet.name, &self.language);
+            if reachable_storylets.contains(&name) {
+                continue;
+            }
+
+            if let Some(element) = self.storylet_elements.get(&name) {
+                let repeatable = element.repeatable.as_ref().is_some_and(|repeatable| repeatable.value);
+                let never_assigned = storylet.condition.as_ref().is_some_and(|condition| !satisfiable(condition, &|reference: &str| {
+                    let reference = self.subject(reference);
+                    !self.qualities.contains(&reference) || self.assigned.contains(&reference)
+                }));
+                if !repeatable && !never_assigned {
+                    let problem = Problem::warning("This storylet is not repeatable, and no sequence of storylets or choices can make it available", &self.condition_attribution(storylet));
+                    problems.push(match &storylet.condition {
+                        Some(condition) => problem.with_help(format!("the condition is `{}`", condition)),
+                        None => problem,
+                    });
+                }
+            }
+        }
+    }
+
+    fn condition_attribution(&self, storylet: &Storylet) -> Attribution {
+        let element = self.storylet_elements[&normalize(&storylet.name, &self.language)];
+        if let Some(expression) = element.when.as_ref().or(element.r#if.as_ref()).or(element.unless.as_ref()) {
+            expression.attribution.clone()
+        } else if let Some(name) = &element.name {
+            name.attribution.clone()
+        } else {
+            element.attribution.clone()
+        }
+    }
+}
+
+#[derive(Default)]
+struct Effects {
+    assigned: Vec<String>,
+    destinations: Vec<String>,
+}
+
+impl Effects {
+    fn storylet(&mut self, storylet: &Storylet, known: &dyn Fn(&str) -> bool) {
+        self.effects(&storylet.assignments, &storylet.navigation, known);
+        if let Some(choices) = &storylet.choices {
+            for group in &choices.groups {
+                for choice in &group.choices {
+                    self.choice(choice, known);
+                }
+            }
+        }
+    }
+
+    fn choice(&mut self, choice: &Choice, known: &dyn Fn(&str) -> bool) {
+        if choice.condition.as_ref().is_none_or(|condition| satisfiable(condition, known)) {
+            self.effects(&choice.assignments, &choice.navigation, known);
+        }
+    }
+
+    fn effects(&mut self, assignments: &Option<Vec<AssignmentGroup>>, navigation: &Option<Conditional<String>>, known: &dyn Fn(&str) -> bool) {
+        for Assignment { condition, subject, operation, .. } in assignments.iter().flatten().flat_map(|group| group.assignments.iter()) {
+            if matches!(operation, AssignmentOperation::Set | AssignmentOperation::Increment) && condition.as_ref().is_none_or(|condition| satisfiable(condition, known)) {
+                self.assigned.push(subject.clone());
+            }
+        }
+        conditional_values(navigation, &mut self.destinations);
+    }
+}
+
+fn satisfiable(expression: &ExpressionParse, known: &dyn Fn(&str) -> bool) -> bool {
+    match expression {
+        ExpressionParse::Atom(ExpressionAtom::LogicalLiteral(b)) => *b,
+        ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n)) => *n > 0,
+        ExpressionParse::Atom(ExpressionAtom::Reference(name)) => known(name),
+        ExpressionParse::Operation(operator, operands) => {
+            match operator {
+                ExpressionOperator::And => operands.iter().all(|operand| satisfiable(operand, known)),
+                ExpressionOperator::Or | ExpressionOperator::Either => operands.iter().any(|operand| satisfiable(operand, known)),
+                ExpressionOperator::Then => operands.iter().skip(1).any(|operand| satisfiable(operand, known)),
+                ExpressionOperator::In => operands.iter().all(|operand| satisfiable(operand, known)),
+                ExpressionOperator::GreaterThan | ExpressionOperator::GreaterThanOrEqual | ExpressionOperator::Equal | ExpressionOperator::Is => {
+                    match operands.as_slice() {
+                        [_, ExpressionParse::Atom(ExpressionAtom::NumericLiteral(0))] if *operator != ExpressionOperator::GreaterThan => true,
+                        [left, _] => positive(left, known),
+                        _ => true,
+                    }
+                },
+                ExpressionOperator::Plus | ExpressionOperator::Multiply | ExpressionOperator::Minus | ExpressionOperator::Divide
+                    | ExpressionOperator::Maximum | ExpressionOperator::Minimum | ExpressionOperator::Between | ExpressionOperator::Random => positive(expression, known),
+                _ => true,
+            }
+        },
+    }
+}
+
+fn positive(expression: &ExpressionParse, known: &dyn Fn(&str) -> bool) -> bool {
+    match expression {
+        ExpressionParse::Atom(ExpressionAtom::LogicalLiteral(b)) => *b,
+        ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n)) => *n > 0,
+        ExpressionParse::Atom(ExpressionAtom::Reference(name)) => known(name),
+        ExpressionParse::Operation(operator, operands) => {
+            match operator {
+                ExpressionOperator::Plus | ExpressionOperator::Maximum | ExpressionOperator::Either | ExpressionOperator::Between | ExpressionOperator::Random =>
+                    operands.iter().any(|operand| positive(operand, known)),
+                ExpressionOperator::Multiply | ExpressionOperator::Minimum => operands.iter().all(|operand| positive(operand, known)),
+                ExpressionOperator::Minus | ExpressionOperator::Divide => operands.first().is_none_or(|operand| positive(operand, known)),
+                ExpressionOperator::Then => operands.iter().skip(1).any(|operand| positive(operand, known)),
+                _ => satisfiable(expression, known),
+            }
+        },
+    }
+}
+
+fn references(expression: &ExpressionParse, names: &mut Vec<String>) {
+    match expression {
+        ExpressionParse::Atom(ExpressionAtom::Reference(name)) => names.push(name.clone()),
+        ExpressionParse::Atom(_) => {},
+        ExpressionParse::Operation(_, operands) => {
+            for operand in operands {
+                references(operand, names);
+            }
+        },
+    }
+}
+
+fn template_references(template: &TemplateParse, names: &mut Vec<String>) {
+    for node in template {
+        match node {
+            TemplateParseNode::Text(_) | TemplateParseNode::Paragraph => {},
+            TemplateParseNode::Italic(nodes) | TemplateParseNode::Bold(nodes) | TemplateParseNode::Anchor(_, nodes) => template_references(nodes, names),
+            TemplateParseNode::Branch(condition, consequent, alternative) => {
+                references(condition, names);
+                template_references(consequent, names);
+                if let Some(alternative) = alternative {
+                    template_references(alternative, names);
+                }
+            },
+        }
+    }
+}
+
+fn conditional_references<T>(conditional: &Option<Conditional<T>>, names: &mut Vec<String>) {
+    let mut conditional = conditional.as_ref();
+    while let Some(Conditional::Conditionally(condition, _, next)) = conditional {
+        references(condition, names);
+        conditional = Some(next);
+    }
+}
+
+fn conditional_values(conditional: &Option<Conditional<String>>, values: &mut Vec<String>) {
+    let mut conditional = conditional.as_ref();
+    while let Some(current) = conditional {
+        match current {
+            Conditional::Always(value) => {
+                values.push(value.clone());
+                conditional = None;
+            },
+            Conditional::Conditionally(_, value, next) => {
+                values.push(value.clone());
+                conditional = Some(next);
+            },
+        }
+    }
+}
+
+fn assignment_references(assignments: &Option<Vec<AssignmentGroup>>, read: &mut Vec<String>, assigned: &mut Vec<String>) {
+    for group in assignments.iter().flatten() {
+        if let Some(description) = &group.description {
+            template_references(description, read);
+        }
+        for assignment in &group.assignments {
+            if let Some(condition) = &assignment.condition {
+                references(condition, read);
+            }
+            references(&assignment.operand, read);
+            assigned.push(assignment.subject.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Problem;
+    use crate::problem::Level;
+    use crate::testing::compile_world;
+
+    fn warnings(source: &str) -> Vec<Problem> {
+        let problems = compile_world(source).problems;
+        assert!(problems.iter().all(|problem| problem.level == Level::Warning));
+        problems
+    }
+
+    #[test]
+    fn test_reachable_content_has_no_warnings() {
+        let problems = warnings(r#"
+version: 0.1
+qualities:
+  - name: coins
+locations:
+  - name: hall
+    storylets:
+      - name: spend
+        when: coins
+        repeatable: true
+        label: Spend a coin
+        assign:
+          - decrement: coins
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+        to: 2
+    go: hall
+"#);
+        assert_eq!(problems, Vec::new());
+    }
+
+    #[test]
+    fn test_unreachable_content() {
+        let problems = warnings(r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: gems
+  - name: score
+locations:
+  - name: hall
+  - name: cellar
+    storylets:
+      - name: explore
+        label: Explore the cellar
+storylets:
+  - name: initialize
+    assign:
+      - increment: score
+    go: hall
+  - name: shine
+    when: gems > 2
+    repeatable: true
+    label: Admire your gems
+  - name: spend
+    when: coins
+    label: Spend a coin
+"#);
+        let messages: Vec<(&str, &str)> = problems.iter().map(|problem| (problem.message, problem.attribution.path.as_str())).collect();
+        assert_eq!(problems[0].help.as_deref(), Some("the condition is `gems > 2`, and nothing assigns `gems`"));
+        assert_eq!(messages, vec!(
+            ("This storylet's condition depends on a quality that is never assigned, so the storylet will never be available", ".storylets[1].when"),
+            ("This storylet's condition depends on a quality that is never assigned, so the storylet will never be available", ".storylets[2].when"),
+            ("This location is never the destination of a `go`, so it can never be visited", ".locations[1].name"),
+            ("This quality is assigned but never read by any condition, expression or template", ".qualities[2].name"),
+            ("This storylet is not repeatable, and no sequence of storylets or choices can make it available", ".locations[1].storylets[0].name"),
+        ));
+    }
+
+    #[test]
+    fn test_quality_values_assigned_but_never_read() {
+        let world = |body: &str| format!(r#"
+version: 0.1
+qualities:
+  - name: mood
+    exclusive: true
+    values:
+      - name: calm
+      - name: angry
+storylets:
+  - name: initialize
+    assign:
+      - set: calm
+  - name: shout
+    label: Shout
+    body: {}
+    assign:
+      - set: angry
+"#, body);
+        let problems = warnings(&world("You shout."));
+        let messages: Vec<(&str, &str)> = problems.iter().map(|problem| (problem.message, problem.attribution.path.as_str())).collect();
+        assert_eq!(messages, vec!(
+            ("This quality is assigned but never read by any condition, expression or template", ".qualities[0].name"),
+        ));
+        assert_eq!(warnings(&world("You shout{ when angry } again{ end }.")), Vec::new());
+    }
+
+    #[test]
+    fn test_pushed_storylets_are_reachable() {
+        let problems = warnings(r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: ready
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+  - name: intro
+    label: Intro
+    push:
+      - if: coins
+        then: [first, second]
+      - third
+  - name: first
+    when: intro
+    body: One.
+    assign:
+      - set: ready
+  - name: second
+    when: intro and ready
+    body: Two.
+  - name: third
+    when: intro
+    choose:
+      - label: Leave
+        shift: first
+"#);
+        assert_eq!(problems, Vec::new());
+    }
+}
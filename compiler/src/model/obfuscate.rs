@@ -1,9 +1,348 @@
-// pub fn obfuscate(string: &str) -> String {
-//     string.chars().map(|c| {
-//         match c {
-//             'a'..='m' | 'A'..='M' => ((c as u8) + 13) as char,
-//             'n'..='z' | 'N'..='Z' => ((c as u8) - 13) as char,
-//             _ => c
-//         }
-//     }).collect()
-// }
+use std::collections::BTreeMap;
+use crate::{normalize, AssignmentGroup, Choice, Conditional, Language, Model, Storylet};
+use crate::expression::{ExpressionAtom, ExpressionParse};
+use crate::template::{TemplateParse, TemplateParseNode};
+
+// Replaces the names of qualities, quality values, locations and storylets, and every reference to them, with short
+// IDs, so that reading the model doesn't give the story away. Returns the original names by ID.
+//
+// All names share one namespace, as storylets are also referred to as qualities. IDs are derived from a hash of the name
+// rather than its position, so saved state usually still applies when a world is rebuilt with more content. It doesn't
+// when a new name's hash starts like an existing one's and the new name sorts first, as the existing name then gets a
+// longer ID.
+pub fn obfuscate_names(model: &mut Model) -> BTreeMap<String, String> {
+    // Definitions keep the name as written, while references are normalized, so names are matched and hashed normalized.
+    // The name map shows a name as it's defined
+    let language = model.meta.language();
+    let mut names = BTreeMap::new();
+    for name in definitions(model) {
+        names.entry(normalize(&name, &language)).or_insert(name);
+    }
+    visit_names(model, &mut |name| {
+        names.entry(normalize(name, &language)).or_insert_with(|| name.clone());
+    });
+
+    let mut names_by_id = BTreeMap::new();
+    for (normalized, name) in names {
+        let id = short_id(&normalized, &names_by_id);
+        names_by_id.insert(id, name);
+    }
+
+    obfuscate_names_as(model, &names_by_id, &language);
+    names_by_id
+}
+
+// Gives a model the IDs from another build of the same world, such as a translation, so that one name map applies to
+// both. Names are matched in the language the IDs were made in, which a translation's own language may lowercase
+// differently
+pub fn obfuscate_names_as(model: &mut Model, names_by_id: &BTreeMap<String, String>, language: &Language) {
+    // Qualities without labels are shown by name, so their names are kept as labels
+    for quality in &mut model.qualities {
+        let name = vec!(TemplateParseNode::Text(quality.name.clone()));
+        for value in quality.values.iter_mut().flatten() {
+            value.label.get_or_insert_with(|| name.clone());
+        }
+        quality.label.get_or_insert(name);
+    }
+
+    let ids: BTreeMap<String, &String> = names_by_id.iter().map(|(id, name)| (normalize(name, language), id)).collect();
+    visit_names(model, &mut |name| {
+        if let Some(id) = ids.get(&normalize(name, language)) {
+            *name = id.to_string();
+        }
+    });
+}
+
+fn definitions(model: &Model) -> Vec<String> {
+    model.qualities.iter().flat_map(|quality| std::iter::once(&quality.name).chain(quality.values.iter().flatten().map(|value| &value.name)))
+        .chain(model.locations.iter().map(|location| &location.name))
+        .chain(model.storylets.iter().map(|storylet| &storylet.name))
+        .cloned()
+        .collect()
+}
+
+// The shortest prefix of the name's hash that isn't already taken, of at least four characters
+fn short_id(name: &str, taken: &BTreeMap<String, String>) -> String {
+    let digits = base36(fnv1a(name.as_bytes()));
+    (4..=digits.len())
+        .map(|len| digits[..len].to_string())
+        .chain((1..).map(|n| format!("{}{}", digits, n)))
+        .find(|id| !taken.contains_key(id))
+        .unwrap()
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn base36(mut value: u64) -> String {
+    let mut digits = String::new();
+    loop {
+        digits.push(std::char::from_digit((value % 36) as u32, 36).unwrap());
+        value /= 36;
+        if value == 0 {
+            return digits;
+        }
+    }
+}
+
+fn visit_names(model: &mut Model, visit: &mut impl FnMut(&mut String)) {
+    for quality in &mut model.qualities {
+        visit(&mut quality.name);
+        for template in [&mut quality.label, &mut quality.singular_label, &mut quality.plural_label, &mut quality.description].into_iter().flatten() {
+            visit_template(template, visit);
+        }
+        if let Some(icon) = &mut quality.icon {
+            visit_conditional(icon, visit, &mut |_, _| {});
+        }
+        for value in quality.values.iter_mut().flatten() {
+            visit(&mut value.name);
+            for template in [&mut value.label, &mut value.description].into_iter().flatten() {
+                visit_template(template, visit);
+            }
+            if let Some(icon) = &mut value.icon {
+                visit_conditional(icon, visit, &mut |_, _| {});
+            }
+        }
+    }
+
+    for location in &mut model.locations {
+        visit(&mut location.name);
+        visit_template(&mut location.label, visit);
+        for template in [&mut location.description, &mut location.body].into_iter().flatten() {
+            visit_template(template, visit);
+        }
+    }
+
+    for storylet in &mut model.storylets {
+        visit_storylet(storylet, visit);
+    }
+}
+
+fn visit_storylet(storylet: &mut Storylet, visit: &mut impl FnMut(&mut String)) {
+    visit(&mut storylet.name);
+    if let Some(condition) = &mut storylet.condition {
+        visit_expression(condition, visit);
+    }
+    for template in [&mut storylet.label, &mut storylet.description].into_iter().flatten() {
+        visit_template(template, visit);
+    }
+    visit_outcome(&mut storylet.icon, &mut storylet.body, &mut storylet.navigation, &mut storylet.push, &mut storylet.shift, &mut storylet.assignments, visit);
+    if let Some(choices) = &mut storylet.choices {
+        if let Some(prompt) = &mut choices.prompt {
+            visit_template(prompt, visit);
+        }
+        for group in &mut choices.groups {
+            for expression in [&mut group.limit, &mut group.shuffle].into_iter().flatten() {
+                visit_expression(expression, visit);
+            }
+            for choice in &mut group.choices {
+                visit_choice(choice, visit);
+            }
+        }
+    }
+}
+
+fn visit_choice(choice: &mut Choice, visit: &mut impl FnMut(&mut String)) {
+    if let Some(condition) = &mut choice.condition {
+        visit_expression(condition, visit);
+    }
+    visit_template(&mut choice.label, visit);
+    if let Some(description) = &mut choice.description {
+        visit_template(description, visit);
+    }
+    visit_outcome(&mut choice.icon, &mut choice.body, &mut choice.navigation, &mut choice.push, &mut choice.shift, &mut choice.assignments, visit);
+}
+
+// The fields that storylets and choices share. Navigation refers to a location and push and shift to storylets, while
+// icons are left alone
+fn visit_outcome(
+    icon: &mut Option<Conditional<String>>,
+    body: &mut Option<TemplateParse>,
+    navigation: &mut Option<Conditional<String>>,
+    push: &mut Option<Conditional<Vec<String>>>,
+    shift: &mut Option<Conditional<Vec<String>>>,
+    assignments: &mut Option<Vec<AssignmentGroup>>,
+    visit: &mut impl FnMut(&mut String),
+) {
+    if let Some(icon) = icon {
+        visit_conditional(icon, visit, &mut |_, _| {});
+    }
+    if let Some(body) = body {
+        visit_template(body, visit);
+    }
+    if let Some(navigation) = navigation {
+        visit_conditional(navigation, visit, &mut |name, visit| visit(name));
+    }
+    for names in [push, shift].into_iter().flatten() {
+        visit_conditional(names, visit, &mut |names, visit| names.iter_mut().for_each(&mut *visit));
+    }
+    for group in assignments.iter_mut().flatten() {
+        for assignment in &mut group.assignments {
+            if let Some(condition) = &mut assignment.condition {
+                visit_expression(condition, visit);
+            }
+            visit(&mut assignment.subject);
+            visit_expression(&mut assignment.operand, visit);
+        }
+        if let Some(description) = &mut group.description {
+            visit_template(description, visit);
+        }
+    }
+}
+
+fn visit_conditional<T, V: FnMut(&mut String)>(conditional: &mut Conditional<T>, visit: &mut V, value: &mut impl FnMut(&mut T, &mut V)) {
+    match conditional {
+        Conditional::Always(always) => value(always, visit),
+        Conditional::Conditionally(condition, then, next) => {
+            visit_expression(condition, visit);
+            value(then, visit);
+            visit_conditional(next, visit, value);
+        },
+    }
+}
+
+fn visit_expression(expression: &mut ExpressionParse, visit: &mut impl FnMut(&mut String)) {
+    match expression {
+        ExpressionParse::Atom(ExpressionAtom::Reference(name)) => visit(name),
+        ExpressionParse::Atom(_) => {},
+        ExpressionParse::Operation(_, operands) => {
+            for operand in operands {
+                visit_expression(operand, visit);
+            }
+        },
+    }
+}
+
+fn visit_template(template: &mut TemplateParse, visit: &mut impl FnMut(&mut String)) {
+    for node in template {
+        match node {
+            TemplateParseNode::Italic(t) | TemplateParseNode::Bold(t) | TemplateParseNode::Anchor(_, t) => visit_template(t, visit),
+            TemplateParseNode::Branch(condition, then, otherwise) => {
+                visit_expression(condition, visit);
+                visit_template(then, visit);
+                if let Some(otherwise) = otherwise {
+                    visit_template(otherwise, visit);
+                }
+            },
+            TemplateParseNode::Text(_) | TemplateParseNode::Paragraph => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{obfuscate_names, obfuscate_names_as, Language, TemplateParse, TemplateParseNode};
+    use crate::testing::compile_world;
+
+    const WORLD: &str = r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: mood
+    label: Mood
+    values:
+      - name: calm
+      - name: angry
+locations:
+  - name: harbor
+    body: "{ if coins > 2 }Rich.{ end }"
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+        to: 2
+    go: harbor
+  - name: argue
+    when: calm and in harbor
+    label: Argue
+    choose:
+      - label: Shout
+        assign:
+          - set: mood
+            to: angry
+        push: initialize
+"#;
+
+    #[test]
+    fn test_obfuscates_names_consistently() {
+        let mut model = compile_world(WORLD).model;
+        let names = obfuscate_names(&mut model);
+        let id = |name: &str| names.iter().find(|(_, original)| *original == name).map(|(id, _)| id.clone()).unwrap();
+        assert_eq!(names.len(), 7);
+        assert!(names.keys().all(|id| id.len() >= 4 && id.chars().all(|c| c.is_ascii_alphanumeric())));
+
+        assert_eq!(model.qualities[0].name, id("coins"));
+        assert_eq!(model.qualities[1].values.as_ref().unwrap()[1].name, id("angry"));
+        assert_eq!(model.locations[0].name, id("harbor"));
+        assert!(!format!("{:?}", model.locations[0].body).contains("\"coins\""));
+
+        let initialize = &model.storylets[0];
+        assert_eq!(initialize.name, id("initialize"));
+        assert_eq!(initialize.assignments.as_ref().unwrap()[0].assignments[0].subject, id("coins"));
+        assert_eq!(format!("{:?}", initialize.navigation), format!("Some(Always({:?}))", id("harbor")));
+
+        let argue = &model.storylets[1];
+        assert_eq!(argue.name, id("argue"));
+        assert_eq!(format!("{}", argue.condition.as_ref().unwrap()), format!("({} and in {}) and not {}", id("calm"), id("harbor"), id("argue")));
+        let choice = &argue.choices.as_ref().unwrap().groups[0].choices[0];
+        assert_eq!(format!("{:?}", choice.push), format!("Some(Always([{:?}]))", id("initialize")));
+        assert_eq!(choice.assignments.as_ref().unwrap()[0].assignments[0].subject, id("mood"));
+        assert_eq!(format!("{}", choice.assignments.as_ref().unwrap()[0].assignments[0].operand), id("angry"));
+    }
+
+    #[test]
+    fn test_keeps_names_as_labels() {
+        let mut model = compile_world(WORLD).model;
+        obfuscate_names(&mut model);
+        let label = |label: &Option<TemplateParse>| format!("{:?}", label);
+        assert_eq!(label(&model.qualities[0].label), label(&Some(vec!(TemplateParseNode::Text("coins".to_string())))));
+        assert_eq!(label(&model.qualities[1].label), label(&Some(vec!(TemplateParseNode::Text("Mood".to_string())))));
+        assert_eq!(label(&model.qualities[1].values.as_ref().unwrap()[0].label), label(&Some(vec!(TemplateParseNode::Text("mood".to_string())))));
+    }
+
+    #[test]
+    fn test_matches_names_as_references_do() {
+        let mut model = compile_world(r#"
+version: 0.1
+qualities:
+  - name: Lucky  Coins
+storylets:
+  - name: initialize
+    when: lucky coins < 3
+    assign:
+      - increase: LUCKY coins
+"#).model;
+        let names = obfuscate_names(&mut model);
+        assert_eq!(names.values().collect::<Vec<_>>(), vec!("Lucky  Coins", "initialize"));
+        let id = |name: &str| names.iter().find(|(_, original)| *original == name).map(|(id, _)| id.clone()).unwrap();
+        let initialize = &model.storylets[0];
+        assert_eq!(model.qualities[0].name, id("Lucky  Coins"));
+        assert_eq!(format!("{}", initialize.condition.as_ref().unwrap()), format!("{} < 3 and not {}", id("Lucky  Coins"), id("initialize")));
+        assert_eq!(initialize.assignments.as_ref().unwrap()[0].assignments[0].subject, id("Lucky  Coins"));
+    }
+
+    #[test]
+    fn test_translations_share_ids() {
+        let world = "version: 0.1\nqualities:\n  - name: Ink\nstorylets:\n  - name: initialize\n    when: ink\n";
+        let mut model = compile_world(world).model;
+        let mut translation = model.clone();
+        let language = model.meta.language();
+        let names = obfuscate_names(&mut model);
+
+        // Turkish lowercases `Ink` to `ınk`, which the references to `ink` wouldn't match
+        translation.meta.lang = Some(Language::parse("tr").unwrap());
+        obfuscate_names_as(&mut translation, &names, &language);
+        assert_eq!(translation.qualities[0].name, model.qualities[0].name);
+        assert_eq!(translation.storylets[0].condition, model.storylets[0].condition);
+    }
+
+    #[test]
+    fn test_ids_are_stable() {
+        let mut first = compile_world(WORLD).model;
+        let mut second = compile_world(&format!("{}  - name: later\n    label: Later\n", WORLD)).model;
+        let first = obfuscate_names(&mut first);
+        let second = obfuscate_names(&mut second);
+        assert!(first.iter().all(|(id, name)| second.get(id) == Some(name)));
+    }
+}
@@ -0,0 +1,362 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::{plain, Attribution, ChoiceState, Evaluator, Model, Problem, Session, Source, State};
+use crate::element::{check_keys, Element, ListElement, PlaythroughElement};
+use crate::expression::{ExpressionAtom, ExpressionParse};
+use crate::symbol::normalize;
+
+#[derive(Debug, Clone)]
+pub struct Playthrough {
+    pub attribution: Attribution,
+    pub name: String,
+    pub seed: u64,
+    pub steps: Vec<PlaythroughStep>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaythroughStep {
+    pub attribution: Attribution,
+    pub action: Option<PlaythroughAction>,
+    pub expectations: Vec<Expectation>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlaythroughAction {
+    Continue,
+    Choose(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    pub attribution: Attribution,
+    pub kind: ExpectationKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpectationKind {
+    Location(String),
+    Storylet(String),
+    Quality(String, u32),
+    Body(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaythroughFailure {
+    pub message: String,
+    pub attribution: Attribution,
+}
+
+pub struct PlaythroughParsingResult {
+    pub playthroughs: Vec<Playthrough>,
+    pub problems: Vec<Problem>,
+}
+
+impl Playthrough {
+    pub fn from_sources(sources: &Vec<Source>) -> PlaythroughParsingResult {
+        let mut playthroughs = Vec::new();
+        let mut problems = Vec::new();
+        for source in sources {
+            for (index, document) in source.documents.iter().enumerate() {
+                if let Some(node) = &document.root {
+                    let attribution = if source.documents.len() == 1 {
+                        Attribution::new(&source.path, node.start_mark, node.end_mark)
+                    } else {
+                        Attribution::new_at_index(&source.path, index, node.start_mark, node.end_mark)
+                    };
+                    let list: ListElement<PlaythroughElement> = ListElement::from_node(node, attribution.clone(), &mut problems);
+                    check_keys(node, &attribution, &mut problems);
+                    for element in list.elements {
+                        playthroughs.push(Self::from_element(element, playthroughs.len()));
+                    }
+                }
+            }
+        }
+
+        PlaythroughParsingResult { playthroughs, problems }
+    }
+
+    fn from_element(element: PlaythroughElement, index: usize) -> Self {
+        let mut steps = Vec::new();
+        for step in element.steps.map(|steps| steps.elements).unwrap_or_default() {
+            let action = if let Some(choose) = step.choose {
+                Some(PlaythroughAction::Choose(choose.source))
+            } else if step.r#continue.is_some_and(|c| c.value) {
+                Some(PlaythroughAction::Continue)
+            } else {
+                None
+            };
+
+            let mut expectations = Vec::new();
+            if let Some(location) = step.location {
+                expectations.push(Expectation { attribution: location.attribution, kind: ExpectationKind::Location(location.name) });
+            }
+            if let Some(storylet) = step.storylet {
+                expectations.push(Expectation { attribution: storylet.attribution, kind: ExpectationKind::Storylet(storylet.name) });
+            }
+            if let Some(qualities) = step.qualities {
+                for (name, value) in qualities.expectations {
+                    expectations.push(Expectation { attribution: value.attribution, kind: ExpectationKind::Quality(name.name, value.value) });
+                }
+            }
+            if let Some(body) = step.body {
+                for text in body.elements {
+                    expectations.push(Expectation { attribution: text.attribution, kind: ExpectationKind::Body(text.source) });
+                }
+            }
+
+            steps.push(PlaythroughStep { attribution: step.attribution, action, expectations });
+        }
+
+        Self {
+            name: element.name.map(|name| name.name).unwrap_or_else(|| format!("playthrough {}", index + 1)),
+            seed: element.seed.map(|seed| seed.value as u64).unwrap_or(0),
+            attribution: element.attribution,
+            steps,
+        }
+    }
+
+    pub fn run(&self, model: &Model) -> Vec<PlaythroughFailure> {
+        let mut session = Session::new(model, State::new()).with_seed(self.seed);
+        let evaluator = Evaluator::new(model);
+        let language = model.meta.language();
+        let mut state = session.resume();
+        let mut failures = Vec::new();
+
+        for step in &self.steps {
+            match &step.action {
+                Some(PlaythroughAction::Continue) => {
+                    if !state.choices.is_empty() && state.storylet.is_some() {
+                        failures.push(PlaythroughFailure {
+                            message: format!("Expected to be able to continue, but the storylet offers choices: {}", labels(&state.choices)),
+                            attribution: step.attribution.clone(),
+                        });
+                        break;
+                    }
+                    state = session.resume();
+                },
+                Some(PlaythroughAction::Choose(label)) => {
+                    let wanted = normalize(label, &language);
+                    if let Some(choice) = state.choices.iter().find(|choice| normalize(&plain(&choice.label), &language) == wanted) {
+                        state = session.choose(choice.id);
+                    } else {
+                        let message = if state.choices.is_empty() {
+                            format!("Expected a choice labelled \"{}\", but there are no choices", label.trim())
+                        } else {
+                            format!("Expected a choice labelled \"{}\", but the choices are: {}", label.trim(), labels(&state.choices))
+                        };
+                        failures.push(PlaythroughFailure { message, attribution: step.attribution.clone() });
+                        break;
+                    }
+                },
+                None => {},
+            }
+
+            let failure_count = failures.len();
+            for expectation in &step.expectations {
+                let message = match &expectation.kind {
+                    ExpectationKind::Location(name) => {
+                        let name = &normalize(name, &language);
+                        if !model.locations.iter().any(|location| &normalize(&location.name, &language) == name) {
+                            Some(format!("There is no location named `{}`", name))
+                        } else {
+                            match session.state().location() {
+                                Some(location) if &normalize(location, &language) == name => None,
+                                Some(location) => Some(format!("Expected to be in `{}`, but was in `{}`", name, location)),
+                                None => Some(format!("Expected to be in `{}`, but was not in any location", name)),
+                            }
+                        }
+                    },
+                    ExpectationKind::Storylet(name) => {
+                        let name = &normalize(name, &language);
+                        match &state.storylet {
+                            Some(storylet) if &normalize(&storylet.name, &language) == name => None,
+                            Some(storylet) => Some(format!("Expected storylet `{}`, but `{}` is active", name, storylet.name)),
+                            None => Some(format!("Expected storylet `{}`, but no storylet is active", name)),
+                        }
+                    },
+                    ExpectationKind::Quality(name, value) => {
+                        let name = &normalize(name, &language);
+                        match quality(model, &evaluator, session.state(), name) {
+                            None => Some(format!("There is no quality named `{}`", name)),
+                            Some(actual) if actual != *value => Some(format!("Expected `{}` to be {}, but it was {}", name, value, actual)),
+                            Some(_) => None,
+                        }
+                    },
+                    ExpectationKind::Body(text) => {
+                        let body = plain(&state.body);
+                        if normalize(&body, &language).contains(&normalize(text, &language)) {
+                            None
+                        } else {
+                            Some(format!("Expected the body to contain \"{}\", but it was \"{}\"", text.trim(), body))
+                        }
+                    },
+                };
+
+                if let Some(message) = message {
+                    failures.push(PlaythroughFailure { message, attribution: expectation.attribution.clone() });
+                }
+            }
+
+            if failures.len() > failure_count {
+                break;
+            }
+        }
+
+        failures
+    }
+}
+
+// Reads a quality, a storylet's visit count or whether a quality has a value, as a reference to the name would in a
+// condition, so a value counts as 1 while it's held
+fn quality(model: &Model, evaluator: &Evaluator, state: &State, name: &str) -> Option<u32> {
+    let language = model.meta.language();
+    if model.qualities.iter().any(|quality| normalize(&quality.name, &language) == name) || model.storylets.iter().any(|storylet| normalize(&storylet.name, &language) == name) {
+        Some(state.get(name))
+    } else if evaluator.quality_value(name).is_some() {
+        let reference = ExpressionParse::Atom(ExpressionAtom::Reference(name.to_string()));
+        Some(evaluator.evaluate_logical(&reference, state, &mut StdRng::seed_from_u64(0)) as u32)
+    } else {
+        None
+    }
+}
+
+fn labels(choices: &[ChoiceState]) -> String {
+    choices.iter().map(|choice| format!("\"{}\"", plain(&choice.label))).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Model, Playthrough};
+    use crate::source::Source;
+    use crate::testing::compile_model;
+
+    const WORLD: &str = r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: mood
+    exclusive: true
+    values:
+      - name: calm
+      - name: angry
+locations:
+  - name: hall
+    label: The Hall
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+        to: 2
+      - set: calm
+    go: hall
+  - name: spend
+    when: coins
+    repeatable: true
+    label: Spend a coin
+    body: You spend a coin{ when calm } calmly{ end }.
+    assign:
+      - decrement: coins
+"#;
+
+    fn model() -> Model {
+        compile_model(WORLD)
+    }
+
+    fn playthroughs(source: &str) -> Vec<Playthrough> {
+        let sources = vec!(Source::from_string("world.test.yaml", source).unwrap());
+        let result = Playthrough::from_sources(&sources);
+        assert!(result.problems.is_empty());
+        result.playthroughs
+    }
+
+    #[test]
+    fn test_passing_playthrough() {
+        let model = model();
+        let playthroughs = playthroughs(r#"
+- name: spending
+  steps:
+    - location: hall
+      qualities:
+        coins: 2
+    - choose: spend a coin
+      body: you spend
+      qualities:
+        coins: 1
+"#);
+        assert_eq!(playthroughs.len(), 1);
+        assert!(playthroughs[0].run(&model).is_empty());
+    }
+
+    #[test]
+    fn test_names_are_matched_normalized() {
+        let model = compile_model(r#"
+version: 0.1
+locations:
+  - name: Red  Room
+    label: The Red Room
+storylets:
+  - name: initialize
+    go: red room
+  - name: Look Around
+    label: Look around
+    choose:
+      - label: Leave
+"#);
+        let playthroughs = playthroughs(r#"
+- name: red room
+  steps:
+    - location: Red Room
+    - choose: look around
+      location: red  ROOM
+      storylet: look around
+"#);
+        let failures = playthroughs[0].run(&model);
+        assert!(failures.is_empty(), "{:?}", failures.iter().map(|failure| &failure.message).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_failing_playthrough() {
+        let model = model();
+        let playthroughs = playthroughs(r#"
+name: overspending
+steps:
+  - choose: Spend a coin
+  - choose: Spend a coin
+    qualities:
+      coins: 1
+  - choose: Spend a coin
+"#);
+        let failures = playthroughs[0].run(&model);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].message, "Expected `coins` to be 1, but it was 0");
+        assert_eq!(failures[0].attribution.path, ".steps[1].qualities.coins");
+        assert_eq!(failures[0].attribution.start_mark.line, 6);
+    }
+
+    #[test]
+    fn test_quality_value_and_storylet_expectations() {
+        let model = model();
+        let playthroughs = playthroughs(r#"
+- name: values
+  steps:
+    - qualities:
+        calm: 1
+        angry: 0
+        initialize: 1
+    - choose: spend a coin
+      qualities:
+        spend: 1
+- name: wrong values
+  steps:
+    - qualities:
+        angry: 1
+        calmness: 1
+"#);
+        assert!(playthroughs[0].run(&model).is_empty());
+        let failures = playthroughs[1].run(&model);
+        assert_eq!(failures.iter().map(|failure| failure.message.as_str()).collect::<Vec<_>>(), vec!(
+            "Expected `angry` to be 1, but it was 0",
+            "There is no quality named `calmness`",
+        ));
+    }
+}
@@ -12,6 +12,7 @@ pub struct Problem {
     pub message: &'static str,
     pub attribution: Attribution,
     pub context: Option<Context>,
+    pub help: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -27,6 +28,7 @@ impl Problem {
             message,
             attribution: attribution.clone(),
             context: None,
+            help: None,
         }
     }
 
@@ -36,6 +38,7 @@ impl Problem {
             message,
             attribution: attribution.clone(),
             context: None,
+            help: None,
         }
     }
 
@@ -48,6 +51,14 @@ impl Problem {
                 message,
                 attribution: attribution.clone(),
             }),
+            help: self.help,
+        }
+    }
+
+    pub fn with_help(self, help: String) -> Problem {
+        Problem {
+            help: Some(help),
+            ..self
         }
     }
 }
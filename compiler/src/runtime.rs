@@ -0,0 +1,9 @@
+mod state;
+mod evaluate;
+mod session;
+mod simulate;
+
+pub use crate::runtime::state::*;
+pub use crate::runtime::evaluate::*;
+pub use crate::runtime::session::*;
+pub use crate::runtime::simulate::*;
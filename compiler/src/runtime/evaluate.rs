@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use rand::Rng;
+use crate::{Conditional, Model, Quality};
+use crate::expression::{ExpressionAtom, ExpressionOperator, ExpressionParse};
+use crate::runtime::state::State;
+use crate::template::{TemplateParse, TemplateParseNode};
+use crate::text::{Text, TextNode};
+
+pub struct Evaluator<'m> {
+    qualities: HashMap<&'m str, &'m Quality>,
+    quality_values: HashMap<&'m str, (&'m Quality, usize)>,
+}
+
+impl<'m> Evaluator<'m> {
+    pub fn new(model: &'m Model) -> Self {
+        let mut qualities = HashMap::new();
+        let mut quality_values = HashMap::new();
+        for quality in &model.qualities {
+            qualities.insert(quality.name.as_str(), quality);
+            if let Some(values) = &quality.values {
+                for (index, value) in values.iter().enumerate() {
+                    quality_values.insert(value.name.as_str(), (quality, index));
+                }
+            }
+        }
+        Self { qualities, quality_values }
+    }
+
+    pub fn quality(&self, name: &str) -> Option<&'m Quality> {
+        self.qualities.get(name).copied()
+    }
+
+    pub fn quality_value(&self, name: &str) -> Option<(&'m Quality, usize)> {
+        if self.qualities.contains_key(name) {
+            None
+        } else {
+            self.quality_values.get(name).copied()
+        }
+    }
+
+    pub fn evaluate_numeric<R: Rng>(&self, expression: &ExpressionParse, state: &State, rng: &mut R) -> u32 {
+        match expression {
+            ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n)) => *n,
+            ExpressionParse::Atom(ExpressionAtom::LogicalLiteral(b)) => if *b { 1 } else { 0 },
+            ExpressionParse::Atom(ExpressionAtom::Reference(name)) => {
+                if let Some((_, index)) = self.quality_value(name) {
+                    index as u32 + 1
+                } else {
+                    state.get(name)
+                }
+            },
+            ExpressionParse::Operation(operator, operands) => {
+                match operator {
+                    ExpressionOperator::Plus => {
+                        operands.iter().fold(0, |sum, operand| sum.saturating_add(self.evaluate_numeric(operand, state, rng)))
+                    },
+                    ExpressionOperator::Multiply => {
+                        operands.iter().fold(1, |product, operand| product.saturating_mul(self.evaluate_numeric(operand, state, rng)))
+                    },
+                    ExpressionOperator::Minus => {
+                        let mut operands = operands.iter();
+                        let first = operands.next().map(|operand| self.evaluate_numeric(operand, state, rng)).unwrap_or(0);
+                        operands.fold(first, |difference, operand| difference.saturating_sub(self.evaluate_numeric(operand, state, rng)))
+                    },
+                    ExpressionOperator::Divide => {
+                        let mut operands = operands.iter();
+                        let mut quotient = operands.next().map(|operand| self.evaluate_numeric(operand, state, rng)).unwrap_or(0);
+                        for operand in operands {
+                            let divisor = self.evaluate_numeric(operand, state, rng);
+                            if divisor == 0 {
+                                return 0;
+                            }
+                            quotient /= divisor;
+                        }
+                        quotient
+                    },
+                    ExpressionOperator::Maximum => {
+                        operands.iter().map(|operand| self.evaluate_numeric(operand, state, rng)).max().unwrap_or(0)
+                    },
+                    ExpressionOperator::Minimum => {
+                        operands.iter().map(|operand| self.evaluate_numeric(operand, state, rng)).min().unwrap_or(0)
+                    },
+                    ExpressionOperator::Between | ExpressionOperator::Random => {
+                        let values: Vec<u32> = operands.iter().map(|operand| self.evaluate_numeric(operand, state, rng)).collect();
+                        let minimum = values.iter().copied().min().unwrap_or(0);
+                        let maximum = values.iter().copied().max().unwrap_or(0);
+                        rng.gen_range(minimum..=maximum)
+                    },
+                    ExpressionOperator::Either => {
+                        if operands.is_empty() {
+                            0
+                        } else {
+                            self.evaluate_numeric(&operands[rng.gen_range(0..operands.len())], state, rng)
+                        }
+                    },
+                    ExpressionOperator::Then => {
+                        if let [condition, consequent, alternative] = operands.as_slice() {
+                            if self.evaluate_logical(condition, state, rng) {
+                                self.evaluate_numeric(consequent, state, rng)
+                            } else {
+                                self.evaluate_numeric(alternative, state, rng)
+                            }
+                        } else {
+                            0
+                        }
+                    },
+                    _ => if self.evaluate_logical(expression, state, rng) { 1 } else { 0 },
+                }
+            },
+        }
+    }
+
+    pub fn evaluate_logical<R: Rng>(&self, expression: &ExpressionParse, state: &State, rng: &mut R) -> bool {
+        match expression {
+            ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n)) => *n > 0,
+            ExpressionParse::Atom(ExpressionAtom::LogicalLiteral(b)) => *b,
+            ExpressionParse::Atom(ExpressionAtom::Reference(name)) => {
+                if let Some((quality, index)) = self.quality_value(name) {
+                    if quality.exclusive {
+                        state.get(&quality.name) == index as u32 + 1
+                    } else {
+                        state.get(&quality.name) > index as u32
+                    }
+                } else {
+                    state.get(name) > 0
+                }
+            },
+            ExpressionParse::Operation(operator, operands) => {
+                match operator {
+                    ExpressionOperator::And => operands.iter().all(|operand| self.evaluate_logical(operand, state, rng)),
+                    ExpressionOperator::Or => operands.iter().any(|operand| self.evaluate_logical(operand, state, rng)),
+                    ExpressionOperator::Not => !operands.iter().all(|operand| self.evaluate_logical(operand, state, rng)),
+                    ExpressionOperator::Is | ExpressionOperator::Equal => self.compare(operands, state, rng, |left, right| left == right),
+                    ExpressionOperator::NotEqual => self.compare(operands, state, rng, |left, right| left != right),
+                    ExpressionOperator::GreaterThan => self.compare(operands, state, rng, |left, right| left > right),
+                    ExpressionOperator::GreaterThanOrEqual => self.compare(operands, state, rng, |left, right| left >= right),
+                    ExpressionOperator::LessThan => self.compare(operands, state, rng, |left, right| left < right),
+                    ExpressionOperator::LessThanOrEqual => self.compare(operands, state, rng, |left, right| left <= right),
+                    ExpressionOperator::In => {
+                        if let Some(ExpressionParse::Atom(ExpressionAtom::Reference(name))) = operands.first() {
+                            state.location() == Some(name.as_str())
+                        } else {
+                            false
+                        }
+                    },
+                    ExpressionOperator::Either => {
+                        if operands.is_empty() {
+                            false
+                        } else {
+                            self.evaluate_logical(&operands[rng.gen_range(0..operands.len())], state, rng)
+                        }
+                    },
+                    ExpressionOperator::Then => {
+                        if let [condition, consequent, alternative] = operands.as_slice() {
+                            if self.evaluate_logical(condition, state, rng) {
+                                self.evaluate_logical(consequent, state, rng)
+                            } else {
+                                self.evaluate_logical(alternative, state, rng)
+                            }
+                        } else {
+                            false
+                        }
+                    },
+                    _ => self.evaluate_numeric(expression, state, rng) > 0,
+                }
+            },
+        }
+    }
+
+    pub fn evaluate_template<R: Rng>(&self, template: &TemplateParse, state: &State, rng: &mut R) -> Text {
+        let mut paragraphs = Vec::new();
+        let mut paragraph = Vec::new();
+        self.evaluate_template_nodes(template, state, rng, &mut paragraphs, &mut paragraph);
+        if !paragraph.is_empty() || paragraphs.is_empty() {
+            paragraphs.push(TextNode::Paragraph(paragraph));
+        }
+        paragraphs
+    }
+
+    pub fn evaluate_conditional<'c, T, R: Rng>(&self, conditional: &'c Conditional<T>, state: &State, rng: &mut R) -> &'c T {
+        match conditional {
+            Conditional::Always(value) => value,
+            Conditional::Conditionally(condition, value, next) => {
+                if self.evaluate_logical(condition, state, rng) {
+                    value
+                } else {
+                    self.evaluate_conditional(next, state, rng)
+                }
+            },
+        }
+    }
+
+    fn compare<R: Rng>(&self, operands: &[ExpressionParse], state: &State, rng: &mut R, predicate: fn(u32, u32) -> bool) -> bool {
+        if let [left, right] = operands {
+            let left = self.evaluate_numeric(left, state, rng);
+            let right = self.evaluate_numeric(right, state, rng);
+            predicate(left, right)
+        } else {
+            false
+        }
+    }
+
+    fn evaluate_template_nodes<R: Rng>(&self, nodes: &[TemplateParseNode], state: &State, rng: &mut R, paragraphs: &mut Text, paragraph: &mut Text) {
+        for node in nodes {
+            match node {
+                TemplateParseNode::Text(text) => paragraph.push(TextNode::Plain(text.clone())),
+                TemplateParseNode::Paragraph => {
+                    if !paragraph.is_empty() {
+                        paragraphs.push(TextNode::Paragraph(std::mem::take(paragraph)));
+                    }
+                },
+                TemplateParseNode::Italic(nodes) => paragraph.push(TextNode::Italic(self.evaluate_inline(nodes, state, rng))),
+                TemplateParseNode::Bold(nodes) => paragraph.push(TextNode::Bold(self.evaluate_inline(nodes, state, rng))),
+                TemplateParseNode::Anchor(href, nodes) => paragraph.push(TextNode::Anchor(href.clone(), self.evaluate_inline(nodes, state, rng))),
+                TemplateParseNode::Branch(condition, consequent, alternative) => {
+                    if self.evaluate_logical(condition, state, rng) {
+                        self.evaluate_template_nodes(consequent, state, rng, paragraphs, paragraph);
+                    } else if let Some(alternative) = alternative {
+                        self.evaluate_template_nodes(alternative, state, rng, paragraphs, paragraph);
+                    }
+                },
+            }
+        }
+    }
+
+    fn evaluate_inline<R: Rng>(&self, nodes: &[TemplateParseNode], state: &State, rng: &mut R) -> Text {
+        let mut paragraphs = Vec::new();
+        let mut paragraph = Vec::new();
+        self.evaluate_template_nodes(nodes, state, rng, &mut paragraphs, &mut paragraph);
+        let mut result = Vec::new();
+        for node in paragraphs {
+            if let TextNode::Paragraph(text) = node {
+                result.extend(text);
+            }
+        }
+        result.extend(paragraph);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::{Attribution, Mark, Model, Meta};
+    use crate::expression::{ExpressionAtom, ExpressionOperator, ExpressionParse, ExpressionParser};
+    use crate::symbol::SymbolList;
+    use crate::runtime::evaluate::Evaluator;
+    use crate::runtime::state::State;
+
+    fn number(n: u32) -> ExpressionParse {
+        ExpressionParse::Atom(ExpressionAtom::NumericLiteral(n))
+    }
+
+    fn reference(name: &str) -> ExpressionParse {
+        ExpressionParse::Atom(ExpressionAtom::Reference(name.to_string()))
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let model = Model { meta: Meta { title: None, description: None, credits: Vec::new(), lang: None, author: None, image: None, favicon: None, url: None, keywords: Vec::new() }, qualities: Vec::new(), locations: Vec::new(), storylets: Vec::new() };
+        let evaluator = Evaluator::new(&model);
+        let mut state = State::new();
+        state.set("coins", 6);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let product = ExpressionParse::Operation(ExpressionOperator::Multiply, vec!(reference("coins"), number(2)));
+        assert_eq!(evaluator.evaluate_numeric(&product, &state, &mut rng), 12);
+
+        let difference = ExpressionParse::Operation(ExpressionOperator::Minus, vec!(number(2), reference("coins")));
+        assert_eq!(evaluator.evaluate_numeric(&difference, &state, &mut rng), 0);
+
+        let quotient = ExpressionParse::Operation(ExpressionOperator::Divide, vec!(reference("coins"), number(4)));
+        assert_eq!(evaluator.evaluate_numeric(&quotient, &state, &mut rng), 1);
+
+        let branch = ExpressionParse::Operation(ExpressionOperator::Then, vec!(reference("gems"), number(1), number(2)));
+        assert_eq!(evaluator.evaluate_numeric(&branch, &state, &mut rng), 2);
+
+        let between = ExpressionParse::Operation(ExpressionOperator::Between, vec!(number(1), number(6)));
+        for _ in 0..20 {
+            assert!((1..=6).contains(&evaluator.evaluate_numeric(&between, &state, &mut rng)));
+        }
+    }
+
+    #[test]
+    fn test_logical_literals() {
+        let model = Model { meta: Meta { title: None, description: None, credits: Vec::new(), lang: None, author: None, image: None, favicon: None, url: None, keywords: Vec::new() }, qualities: Vec::new(), locations: Vec::new(), storylets: Vec::new() };
+        let evaluator = Evaluator::new(&model);
+        let state = State::new();
+        let mut rng = StdRng::seed_from_u64(0);
+        let symbols = SymbolList::new();
+        let parser = ExpressionParser::new(&symbols);
+        let attribution = Attribution::new("test", Mark::default(), Mark::default());
+        let evaluate = |source: &str, rng: &mut StdRng| evaluator.evaluate_logical(&parser.parse(source, &attribution).parse.unwrap(), &state, rng);
+
+        for source in ["yes", "true", "always", "not no"] {
+            assert!(evaluate(source, &mut rng), "{}", source);
+        }
+        for source in ["no", "false", "never", "not yes"] {
+            assert!(!evaluate(source, &mut rng), "{}", source);
+        }
+    }
+}
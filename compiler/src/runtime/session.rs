@@ -0,0 +1,762 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::{AssignmentGroup, AssignmentOperation, Choice, Choices, Conditional, Language, Location, Model, Quality, QualityStyle, Storylet};
+use crate::expression::ExpressionParse;
+use crate::runtime::evaluate::Evaluator;
+use crate::runtime::state::{Effect, State};
+use crate::symbol::normalize;
+use crate::template::TemplateParse;
+use crate::text::{Text, TextNode};
+
+const MAX_CONSECUTIVE_STORYLETS: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct SessionState {
+    pub location: Option<LocationState>,
+    pub storylet: Option<StoryletState>,
+    pub body: Text,
+    pub assignments: Vec<AssignmentGroupResult>,
+    pub prompt: Option<Text>,
+    pub choices: Vec<ChoiceState>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocationState {
+    pub name: String,
+    pub label: Text,
+    pub description: Option<Text>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoryletState {
+    pub name: String,
+    pub label: Option<Text>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceState {
+    pub id: usize,
+    pub label: Text,
+    pub description: Option<Text>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssignmentGroupResult {
+    pub results: Vec<AssignmentResult>,
+    pub description: Option<Text>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssignmentResult {
+    pub quality: String,
+    pub label: Text,
+    pub value: AssignmentValue,
+    pub operation: AssignmentOperation,
+    pub description: Option<Text>,
+    pub style: Option<QualityStyle>,
+    pub effect: Effect,
+}
+
+#[derive(Debug, Clone)]
+pub enum AssignmentValue {
+    Numeric(u32),
+    Text(Text),
+}
+
+pub struct Session<'m> {
+    model: &'m Model,
+    evaluator: Evaluator<'m>,
+    state: State,
+    rng: StdRng,
+    location: Option<&'m Location>,
+    storylet: Option<usize>,
+    choices: Vec<&'m Choice>,
+    body: Text,
+    assignments: Vec<AssignmentGroupResult>,
+    pending_assignments: Vec<&'m AssignmentGroup>,
+    pending_navigation: Option<&'m String>,
+    pending_stack: Vec<(&'m Vec<String>, bool)>,
+    recent_storylets: Vec<usize>,
+    language: Language,
+}
+
+impl<'m> Session<'m> {
+    pub fn new(model: &'m Model, state: State) -> Self {
+        let location = state.location().and_then(|name| model.locations.iter().find(|location| location.name == name));
+        let storylet = state.storylet().and_then(|name| model.storylets.iter().position(|storylet| storylet.name == name));
+        Self {
+            model,
+            evaluator: Evaluator::new(model),
+            state,
+            rng: StdRng::from_entropy(),
+            location,
+            storylet,
+            choices: Vec::new(),
+            body: Vec::new(),
+            assignments: Vec::new(),
+            pending_assignments: Vec::new(),
+            pending_navigation: None,
+            pending_stack: Vec::new(),
+            recent_storylets: Vec::new(),
+            language: model.meta.language(),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn model(&self) -> &'m Model {
+        self.model
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn location(&self) -> Option<&'m Location> {
+        self.location
+    }
+
+    pub fn storylet(&self) -> Option<&'m Storylet> {
+        self.storylet.map(|index| &self.model.storylets[index])
+    }
+
+    pub fn choice(&self, id: usize) -> Option<&'m Choice> {
+        if self.storylet.is_some() {
+            self.choices.get(id).copied()
+        } else {
+            None
+        }
+    }
+
+    // As in the engine, a choice is shown whether or not its condition holds, and choosing one that doesn't hold does
+    // nothing
+    pub fn can_choose(&mut self, id: usize) -> bool {
+        match self.choice(id) {
+            Some(choice) => self.is_eligible(&choice.condition),
+            None => true,
+        }
+    }
+
+    pub fn resume(&mut self) -> SessionState {
+        if let Some(index) = self.storylet {
+            if !self.choices.is_empty() {
+                return self.present_choices(index);
+            }
+        }
+
+        self.begin_step();
+        self.execute()
+    }
+
+    pub fn choose(&mut self, id: usize) -> SessionState {
+        if let Some(index) = self.storylet {
+            if let Some(choice) = self.choices.get(id).copied() {
+                if self.is_eligible(&choice.condition) {
+                    self.begin_step();
+                    return self.execute_choice(choice);
+                }
+            }
+            return self.present_choices(index);
+        }
+
+        self.begin_step();
+        if let Some(storylet) = self.model.storylets.get(id) {
+            if storylet.label.is_some() && self.is_eligible(&storylet.condition) {
+                self.storylet = Some(id);
+                self.state.set_storylet(&storylet.name);
+            }
+        }
+        self.execute()
+    }
+
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.location = None;
+        self.storylet = None;
+        self.choices.clear();
+        self.body.clear();
+        self.assignments.clear();
+        self.pending_assignments.clear();
+        self.pending_navigation = None;
+        self.pending_stack.clear();
+        self.recent_storylets.clear();
+    }
+
+    fn begin_step(&mut self) {
+        self.body.clear();
+        self.assignments.clear();
+    }
+
+    fn execute(&mut self) -> SessionState {
+        if self.storylet.is_none() {
+            self.evaluate_next_storylet();
+        }
+
+        let mut steps = 0;
+        while let Some(index) = self.storylet {
+            let storylet = &self.model.storylets[index];
+            if let Some(body) = &storylet.body {
+                let body = self.template(body);
+                self.body.extend(body);
+            }
+
+            if let Some(assignments) = &storylet.assignments {
+                self.pending_assignments.extend(assignments.iter());
+            }
+
+            if let Some(navigation) = &storylet.navigation {
+                self.pending_navigation = Some(self.evaluator.evaluate_conditional(navigation, &self.state, &mut self.rng));
+            }
+
+            self.stack(&storylet.push, &storylet.shift);
+
+            if let Some(choices) = &storylet.choices {
+                self.choices = self.gather_choices(choices);
+                if !self.choices.is_empty() {
+                    return self.present_choices(index);
+                }
+            }
+
+            self.commit();
+
+            steps += 1;
+            if steps >= MAX_CONSECUTIVE_STORYLETS {
+                self.state.commit_storylet();
+                self.storylet = None;
+                break;
+            }
+        }
+
+        let choices = self.evaluate_available_storylets();
+        if self.body.is_empty() {
+            if let Some(body) = self.location.and_then(|location| location.body.as_ref()) {
+                self.body = self.template(body);
+            }
+        }
+        self.snapshot(choices, None)
+    }
+
+    fn execute_choice(&mut self, choice: &'m Choice) -> SessionState {
+        if let Some(body) = &choice.body {
+            self.body = self.template(body);
+        }
+
+        if let Some(assignments) = &choice.assignments {
+            self.pending_assignments.extend(assignments.iter());
+        }
+
+        if let Some(navigation) = &choice.navigation {
+            self.pending_navigation = Some(self.evaluator.evaluate_conditional(navigation, &self.state, &mut self.rng));
+        }
+
+        self.stack(&choice.push, &choice.shift);
+
+        self.commit();
+        self.execute()
+    }
+
+    fn present_choices(&mut self, index: usize) -> SessionState {
+        let mut choices = Vec::new();
+        for (id, choice) in self.choices.clone().into_iter().enumerate() {
+            choices.push(ChoiceState {
+                id,
+                label: self.template(&choice.label),
+                description: choice.description.as_ref().map(|description| self.template(description)),
+                icon: choice.icon.as_ref().map(|icon| self.evaluator.evaluate_conditional(icon, &self.state, &mut self.rng).clone()),
+            });
+        }
+        let prompt = self.model.storylets[index].choices.as_ref().and_then(|choices| choices.prompt.as_ref());
+        self.snapshot(choices, prompt)
+    }
+
+    fn snapshot(&mut self, choices: Vec<ChoiceState>, prompt: Option<&'m TemplateParse>) -> SessionState {
+        let location = self.location.map(|location| LocationState {
+            name: location.name.clone(),
+            label: self.template(&location.label),
+            description: location.description.as_ref().map(|description| self.template(description)),
+        });
+        let storylet = self.storylet.map(|index| {
+            let storylet = &self.model.storylets[index];
+            StoryletState {
+                name: storylet.name.clone(),
+                label: storylet.label.as_ref().map(|label| self.template(label)),
+            }
+        });
+        SessionState {
+            location,
+            storylet,
+            body: self.body.clone(),
+            assignments: self.assignments.clone(),
+            prompt: prompt.map(|prompt| self.template(prompt)),
+            choices,
+        }
+    }
+
+    fn gather_choices(&mut self, choices: &'m Choices) -> Vec<&'m Choice> {
+        let mut result = Vec::new();
+        for group in &choices.groups {
+            let shuffle = group.shuffle.as_ref().is_some_and(|shuffle| self.logical(shuffle));
+            let limit = group.limit.as_ref().map_or(0, |limit| self.numeric(limit)) as usize;
+            // As in the engine, conditions aren't checked until a choice is made, so they don't count towards the limit
+            let mut choices: Vec<_> = group.choices.iter().collect();
+            if shuffle {
+                choices.shuffle(&mut self.rng);
+            }
+            if limit > 0 {
+                choices.truncate(limit);
+            }
+            result.extend(choices);
+        }
+        result
+    }
+
+    fn stack(&mut self, push: &'m Option<Conditional<Vec<String>>>, shift: &'m Option<Conditional<Vec<String>>>) {
+        if let Some(push) = push {
+            let names = self.evaluator.evaluate_conditional(push, &self.state, &mut self.rng);
+            self.pending_stack.push((names, false));
+        }
+
+        if let Some(shift) = shift {
+            let names = self.evaluator.evaluate_conditional(shift, &self.state, &mut self.rng);
+            self.pending_stack.push((names, true));
+        }
+    }
+
+    fn evaluate_next_storylet(&mut self) {
+        while let Some(name) = self.state.pop_storylet() {
+            let index = self.model.storylets.iter().position(|storylet| normalize(&storylet.name, &self.language) == name);
+            if let Some(index) = index {
+                if self.is_eligible(&self.model.storylets[index].condition) {
+                    self.storylet = Some(index);
+                    self.state.set_storylet(&self.model.storylets[index].name);
+                    return;
+                }
+            }
+        }
+
+        self.evaluate_eligible_storylets();
+    }
+
+    fn evaluate_eligible_storylets(&mut self) {
+        let mut results = Vec::new();
+        for (index, storylet) in self.model.storylets.iter().enumerate() {
+            if storylet.label.is_none() && self.is_eligible(&storylet.condition) {
+                results.push(index);
+            }
+        }
+
+        self.recent_storylets.retain(|index| results.contains(index));
+        results.retain(|index| !self.recent_storylets.contains(index));
+
+        if !results.is_empty() {
+            let index = results[self.rng.gen_range(0..results.len())];
+            self.recent_storylets.push(index);
+            self.storylet = Some(index);
+            self.state.set_storylet(&self.model.storylets[index].name);
+        }
+    }
+
+    fn evaluate_available_storylets(&mut self) -> Vec<ChoiceState> {
+        let mut choices = Vec::new();
+        for (id, storylet) in self.model.storylets.iter().enumerate() {
+            if let Some(label) = &storylet.label {
+                if self.is_eligible(&storylet.condition) {
+                    choices.push(ChoiceState {
+                        id,
+                        label: self.template(label),
+                        description: storylet.description.as_ref().map(|description| self.template(description)),
+                        icon: storylet.icon.as_ref().map(|icon| self.evaluator.evaluate_conditional(icon, &self.state, &mut self.rng).clone()),
+                    });
+                }
+            }
+        }
+        choices
+    }
+
+    fn commit(&mut self) {
+        for group in std::mem::take(&mut self.pending_assignments) {
+            let mut results = Vec::new();
+            for assignment in &group.assignments {
+                if !self.is_eligible(&assignment.condition) {
+                    continue;
+                }
+
+                let value = self.numeric(&assignment.operand);
+                if let Some(quality) = self.evaluator.quality(&assignment.subject) {
+                    let effect = self.apply(&quality.name, assignment.operation, value);
+                    if let Some(effect) = effect {
+                        if let Some(result) = self.render_assignment_result(quality, effect) {
+                            results.push(result);
+                        }
+                    }
+                } else if let Some((quality, index)) = self.evaluator.quality_value(&assignment.subject) {
+                    let position = index as u32 + 1;
+                    let effect = match assignment.operation {
+                        AssignmentOperation::Set if value == 1 => {
+                            if quality.exclusive {
+                                self.state.set(&quality.name, position).or_else(|| self.state.unset(&quality.name, position))
+                            } else {
+                                self.state.set(&quality.name, position)
+                            }
+                        },
+                        AssignmentOperation::Unset if value == 0 => {
+                            if quality.exclusive {
+                                self.state.unset(&quality.name, 0)
+                            } else {
+                                self.state.unset(&quality.name, index as u32)
+                            }
+                        },
+                        operation => self.apply(&quality.name, operation, value),
+                    };
+                    if let Some(effect) = effect {
+                        if let Some(result) = self.render_assignment_result(quality, effect) {
+                            results.push(result);
+                        }
+                    }
+                } else {
+                    self.apply(&assignment.subject, assignment.operation, value);
+                }
+            }
+
+            if !results.is_empty() || group.description.is_some() {
+                let description = group.description.as_ref().map(|description| self.template(description));
+                self.assignments.push(AssignmentGroupResult { results, description });
+            }
+        }
+
+        if let Some(destination) = self.pending_navigation.take() {
+            if let Some(location) = self.model.locations.iter().find(|location| &location.name == destination) {
+                self.location = Some(location);
+                self.state.set_location(&location.name);
+            }
+        }
+
+        // The running storylet has already left the stack, so shifted storylets take its place by running straight away,
+        // ahead of any that it pushes as follow-ups
+        let mut pending_stack = std::mem::take(&mut self.pending_stack);
+        pending_stack.sort_by_key(|(_, shift)| *shift);
+        for (names, _) in pending_stack {
+            self.state.push_storylets(names);
+        }
+
+        self.state.commit_storylet();
+        self.storylet = None;
+        self.choices.clear();
+
+        self.evaluate_next_storylet();
+    }
+
+    fn apply(&mut self, subject: &str, operation: AssignmentOperation, value: u32) -> Option<Effect> {
+        match operation {
+            AssignmentOperation::Set => self.state.set(subject, value),
+            AssignmentOperation::Unset => self.state.unset(subject, value),
+            AssignmentOperation::Increment => self.state.increment(subject, value),
+            AssignmentOperation::Decrement => self.state.decrement(subject, value),
+        }
+    }
+
+    fn render_assignment_result(&mut self, quality: &'m Quality, effect: Effect) -> Option<AssignmentResult> {
+        if quality.hidden {
+            return None;
+        }
+
+        let style = quality.style.unwrap_or_default();
+        let name = || vec!(TextNode::Paragraph(vec!(TextNode::Plain(quality.name.clone()))));
+
+        if let Some(values) = &quality.values {
+            let position = if effect.after > 0 { effect.after } else { effect.before };
+            let value = values.get((position as usize).checked_sub(1)?)?;
+            let value_label = value.label.as_ref().map_or_else(name, |label| self.template(label));
+            let description = value.description.as_ref().or(quality.description.as_ref()).map(|description| self.template(description));
+            let operation = if effect.after > 0 { AssignmentOperation::Set } else { AssignmentOperation::Unset };
+
+            if style.uncounted {
+                Some(AssignmentResult {
+                    quality: quality.name.clone(),
+                    label: value_label,
+                    value: AssignmentValue::Numeric(if effect.after > 0 { 1 } else { 0 }),
+                    operation,
+                    description,
+                    style: quality.style,
+                    effect,
+                })
+            } else {
+                let label = quality.plural_label.as_ref().filter(|_| style.plural).or(quality.label.as_ref());
+                let label = label.map_or_else(name, |label| self.template(label));
+                Some(AssignmentResult {
+                    quality: quality.name.clone(),
+                    label,
+                    value: AssignmentValue::Text(value_label),
+                    operation,
+                    description,
+                    style: quality.style,
+                    effect,
+                })
+            }
+        } else {
+            let description = quality.description.as_ref().map(|description| self.template(description));
+
+            if style.uncounted {
+                if effect.before > 0 && effect.after > 0 {
+                    return None;
+                }
+
+                let label = self.quality_label(quality, style.plural).unwrap_or_else(name);
+                Some(AssignmentResult {
+                    quality: quality.name.clone(),
+                    label,
+                    value: AssignmentValue::Numeric(if effect.after > 0 { 1 } else { 0 }),
+                    operation: if effect.after > 0 { AssignmentOperation::Set } else { AssignmentOperation::Unset },
+                    description,
+                    style: quality.style,
+                    effect,
+                })
+            } else {
+                let plural = if style.currency { !self.language.is_singular(effect.after) } else { style.plural };
+                let label = self.quality_label(quality, plural).unwrap_or_else(name);
+                Some(AssignmentResult {
+                    quality: quality.name.clone(),
+                    label,
+                    value: AssignmentValue::Numeric(effect.after),
+                    operation: if effect.after > effect.before { AssignmentOperation::Increment } else { AssignmentOperation::Decrement },
+                    description,
+                    style: quality.style,
+                    effect,
+                })
+            }
+        }
+    }
+
+    fn quality_label(&mut self, quality: &'m Quality, plural: bool) -> Option<Text> {
+        let label = if plural {
+            quality.plural_label.as_ref()
+        } else {
+            quality.singular_label.as_ref()
+        };
+        label.or(quality.label.as_ref()).map(|label| self.template(label))
+    }
+
+    fn is_eligible(&mut self, condition: &Option<ExpressionParse>) -> bool {
+        condition.as_ref().is_none_or(|condition| self.logical(condition))
+    }
+
+    fn logical(&mut self, expression: &ExpressionParse) -> bool {
+        self.evaluator.evaluate_logical(expression, &self.state, &mut self.rng)
+    }
+
+    fn numeric(&mut self, expression: &ExpressionParse) -> u32 {
+        self.evaluator.evaluate_numeric(expression, &self.state, &mut self.rng)
+    }
+
+    fn template(&mut self, template: &TemplateParse) -> Text {
+        self.evaluator.evaluate_template(template, &self.state, &mut self.rng)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::TextNode;
+    use crate::runtime::session::{AssignmentValue, Session};
+    use crate::runtime::state::State;
+    use crate::testing::{compile_model, compile_world};
+
+    const WORLD: &str = r#"
+version: 0.1
+qualities:
+  - name: coins
+  - name: mood
+    exclusive: true
+    values:
+      - name: calm
+      - name: angry
+locations:
+  - name: hall
+    label: The Hall
+    body: An empty hall.
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+        to: 2
+      - set: calm
+    go: hall
+  - name: spend
+    when: coins
+    repeatable: true
+    label: Spend a coin
+    assign:
+      - decrement: coins
+  - name: shout
+    label: Shout
+    body: You shout.
+    choose:
+      - label: Loudly
+        assign:
+          - set: angry
+      - label: Never
+        when: coins > 100
+"#;
+
+    #[test]
+    fn test_automatic_storylets_run_on_resume() {
+        let model = compile_world(WORLD).model;
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+
+        assert_eq!(session.state().get("coins"), 2);
+        assert_eq!(session.state().get("mood"), 1);
+        assert_eq!(session.state().location(), Some("hall"));
+        assert_eq!(session.state().get("initialize"), 1);
+        assert_eq!(state.location.map(|location| location.name), Some("hall".to_string()));
+        assert_eq!(state.choices.len(), 2);
+        assert_eq!(state.assignments.iter().map(|group| group.results.len()).sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_enters_locations_named_with_capitals() {
+        let model = compile_model(r#"
+version: 0.1
+locations:
+  - name: Red  Room
+storylets:
+  - name: initialize
+    go: red room
+"#);
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+        let location = state.location.unwrap();
+        assert_eq!(location.name, "red room");
+        assert_eq!(location.label, vec!(TextNode::Paragraph(vec!(TextNode::Plain("Red  Room".to_string())))));
+        assert_eq!(Session::new(&model, session.state().clone()).location().map(|location| location.name.as_str()), Some("red room"));
+    }
+
+    #[test]
+    fn test_choosing_storylets_applies_assignments() {
+        let model = compile_world(WORLD).model;
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+        let spend = state.choices.iter().find(|choice| model.storylets[choice.id].name == "spend").unwrap().id;
+
+        session.choose(spend);
+        let state = session.choose(spend);
+        assert_eq!(session.state().get("coins"), 0);
+        assert!(matches!(state.assignments[0].results[0].value, AssignmentValue::Numeric(0)));
+        assert_eq!(state.choices.len(), 1);
+    }
+
+    #[test]
+    fn test_choices_check_conditions_and_apply_exclusive_values() {
+        let model = compile_world(WORLD).model;
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+        let shout = state.choices.iter().find(|choice| model.storylets[choice.id].name == "shout").unwrap().id;
+
+        let state = session.choose(shout);
+        assert_eq!(state.choices.len(), 2);
+        assert!(state.storylet.is_some());
+        assert!(!session.can_choose(1));
+        let state = session.choose(1);
+        assert_eq!(state.choices.len(), 2);
+        assert_eq!(session.state().get("mood"), 1);
+
+        let state = session.choose(0);
+        assert!(state.storylet.is_none());
+        assert_eq!(session.state().get("mood"), 2);
+        assert_eq!(session.state().get("shout"), 1);
+    }
+
+    #[test]
+    fn test_choice_limits_apply_before_conditions() {
+        let model = compile_model(r#"
+version: 0.1
+qualities:
+  - name: coins
+storylets:
+  - name: initialize
+    push: pick
+  - name: pick
+    choose:
+      limit: 1
+      choices:
+        - label: Buy
+          when: coins
+        - label: Beg
+        - label: Leave
+"#);
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+        assert_eq!(state.choices.len(), 1);
+        assert!(format!("{:?}", state.choices[0].label).contains("\"Buy\""), "{:?}", state.choices[0].label);
+        assert!(!session.can_choose(0));
+        let state = session.choose(0);
+        assert_eq!(state.choices.len(), 1);
+        assert_eq!(session.storylet().map(|storylet| storylet.name.as_str()), Some("pick"));
+    }
+
+    #[test]
+    fn test_push_and_shift_sequence_storylets() {
+        let model = compile_model(r#"
+version: 0.1
+storylets:
+  - name: initialize
+    push: [first, second]
+  - name: first
+    label: First
+    body: One.
+    choose:
+      - label: Skip ahead
+        shift: third
+  - name: second
+    label: Second
+    body: Two.
+  - name: third
+    label: Third
+    body: Three.
+"#);
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        let state = session.resume();
+        assert_eq!(state.storylet.map(|storylet| storylet.name), Some("first".to_string()));
+        assert_eq!(session.state().stack(), &["second".to_string()]);
+
+        let state = session.choose(0);
+        assert!(state.storylet.is_none());
+        assert_eq!(session.state().get("first"), 1);
+        assert_eq!(session.state().get("third"), 1);
+        assert_eq!(session.state().get("second"), 1);
+        assert!(session.state().stack().is_empty());
+        assert!(state.choices.is_empty());
+    }
+
+    #[test]
+    fn test_shifted_storylets_run_before_pushed_ones() {
+        let model = compile_model(r#"
+version: 0.1
+storylets:
+  - name: initialize
+    push: last
+    choose:
+      - label: Go on
+        push: first
+        shift: second
+  - name: first
+    label: First
+    body: One.
+  - name: second
+    label: Second
+    body: Two.
+  - name: last
+    label: Last
+    body: Three.
+"#);
+        let mut session = Session::new(&model, State::new()).with_seed(0);
+        session.resume();
+        let body = format!("{:?}", session.choose(0).body);
+        let position = |text: &str| body.find(text).unwrap();
+        assert!(position("Two.") < position("One.") && position("One.") < position("Three."), "{}", body);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::{Choice, Model};
+use crate::runtime::session::Session;
+use crate::runtime::state::State;
+
+pub struct Simulation<'m> {
+    model: &'m Model,
+    runs: usize,
+    steps: usize,
+    seed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub runs: usize,
+    pub steps: usize,
+    pub storylets: Vec<StoryletVisits>,
+    pub choices: Vec<ChoiceVisits>,
+    pub qualities: Vec<QualityDistribution>,
+    pub dead_ends: Vec<DeadEnd>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoryletVisits {
+    pub storylet: usize,
+    pub visits: u64,
+    pub runs: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceVisits {
+    pub storylet: usize,
+    pub group: usize,
+    pub choice: usize,
+    pub visits: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityDistribution {
+    pub quality: usize,
+    pub samples: Vec<QualitySample>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QualitySample {
+    pub step: usize,
+    pub minimum: u32,
+    pub median: u32,
+    pub maximum: u32,
+    pub mean: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadEnd {
+    pub location: Option<String>,
+    pub runs: usize,
+    pub earliest_step: usize,
+    pub example: BTreeMap<String, u32>,
+}
+
+impl<'m> Simulation<'m> {
+    pub fn new(model: &'m Model) -> Self {
+        Self { model, runs: 1000, steps: 200, seed: 0 }
+    }
+
+    pub fn with_runs(self, runs: usize) -> Self {
+        Self { runs, ..self }
+    }
+
+    pub fn with_steps(self, steps: usize) -> Self {
+        Self { steps, ..self }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    pub fn run(&self) -> SimulationReport {
+        let checkpoints = self.checkpoints();
+        let mut storylets: Vec<StoryletVisits> = (0..self.model.storylets.len()).map(|storylet| StoryletVisits { storylet, visits: 0, runs: 0 }).collect();
+        let mut choices = Vec::new();
+        for (storylet_index, storylet) in self.model.storylets.iter().enumerate() {
+            if let Some(storylet_choices) = &storylet.choices {
+                for (group_index, group) in storylet_choices.groups.iter().enumerate() {
+                    for choice_index in 0..group.choices.len() {
+                        choices.push(ChoiceVisits { storylet: storylet_index, group: group_index, choice: choice_index, visits: 0 });
+                    }
+                }
+            }
+        }
+        let mut samples: Vec<Vec<Vec<u32>>> = vec!(vec!(Vec::with_capacity(self.runs); checkpoints.len()); self.model.qualities.len());
+        let mut dead_ends: Vec<DeadEnd> = Vec::new();
+
+        for run in 0..self.runs {
+            let seed = self.seed.wrapping_add(run as u64);
+            let mut session = Session::new(self.model, State::new()).with_seed(seed);
+            let mut rng = StdRng::seed_from_u64(!seed);
+            let mut state = session.resume();
+            let mut checkpoint = 0;
+
+            for step in 1..=self.steps {
+                if state.choices.is_empty() {
+                    state = session.resume();
+                }
+                // Choices whose conditions don't hold are shown, but a player can't get any further with them
+                let available: Vec<usize> = state.choices.iter().map(|choice| choice.id).filter(|id| session.can_choose(*id)).collect();
+                if available.is_empty() {
+                    let location = session.state().location().map(|location| location.to_string());
+                    if let Some(dead_end) = dead_ends.iter_mut().find(|dead_end| dead_end.location == location) {
+                        dead_end.runs += 1;
+                        if step < dead_end.earliest_step {
+                            dead_end.earliest_step = step;
+                            dead_end.example = session.state().qualities().clone();
+                        }
+                    } else {
+                        dead_ends.push(DeadEnd { location, runs: 1, earliest_step: step, example: session.state().qualities().clone() });
+                    }
+                    break;
+                }
+
+                let id = available[rng.gen_range(0..available.len())];
+                if let Some(choice) = session.choice(id) {
+                    if let Some(visits) = choices.iter_mut().find(|visits| self.is_choice(visits, choice)) {
+                        visits.visits += 1;
+                    }
+                }
+                state = session.choose(id);
+
+                while checkpoint < checkpoints.len() && checkpoints[checkpoint] == step {
+                    self.sample(&session, checkpoint, &mut samples);
+                    checkpoint += 1;
+                }
+            }
+
+            while checkpoint < checkpoints.len() {
+                self.sample(&session, checkpoint, &mut samples);
+                checkpoint += 1;
+            }
+
+            for visits in storylets.iter_mut() {
+                let count = session.state().get(&self.model.storylets[visits.storylet].name);
+                if count > 0 {
+                    visits.visits += count as u64;
+                    visits.runs += 1;
+                }
+            }
+        }
+
+        let qualities = samples.into_iter().enumerate().map(|(quality, samples)| QualityDistribution {
+            quality,
+            samples: samples.into_iter().zip(checkpoints.iter()).map(|(values, step)| distribution(*step, values)).collect(),
+        }).collect();
+
+        dead_ends.sort_by(|a, b| b.runs.cmp(&a.runs).then(a.earliest_step.cmp(&b.earliest_step)));
+
+        SimulationReport {
+            runs: self.runs,
+            steps: self.steps,
+            storylets,
+            choices,
+            qualities,
+            dead_ends,
+        }
+    }
+
+    fn checkpoints(&self) -> Vec<usize> {
+        let mut checkpoints: Vec<usize> = (1..=4).map(|quarter| self.steps * quarter / 4).filter(|step| *step > 0).collect();
+        checkpoints.dedup();
+        checkpoints
+    }
+
+    fn is_choice(&self, visits: &ChoiceVisits, choice: &Choice) -> bool {
+        self.model.storylets[visits.storylet].choices.as_ref()
+            .map(|choices| std::ptr::eq(&choices.groups[visits.group].choices[visits.choice], choice))
+            .unwrap_or(false)
+    }
+
+    fn sample(&self, session: &Session, checkpoint: usize, samples: &mut [Vec<Vec<u32>>]) {
+        for (quality, quality_samples) in self.model.qualities.iter().zip(samples.iter_mut()) {
+            quality_samples[checkpoint].push(session.state().get(&quality.name));
+        }
+    }
+}
+
+fn distribution(step: usize, mut values: Vec<u32>) -> QualitySample {
+    values.sort_unstable();
+    if values.is_empty() {
+        return QualitySample { step, minimum: 0, median: 0, maximum: 0, mean: 0.0 };
+    }
+
+    QualitySample {
+        step,
+        minimum: values[0],
+        median: values[values.len() / 2],
+        maximum: values[values.len() - 1],
+        mean: values.iter().map(|value| *value as f64).sum::<f64>() / values.len() as f64,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Model;
+    use crate::runtime::simulate::Simulation;
+    use crate::testing::compile_world;
+
+    const WORLD: &str = r#"
+version: 0.1
+qualities:
+  - name: coins
+locations:
+  - name: hall
+    label: The Hall
+storylets:
+  - name: initialize
+    assign:
+      - set: coins
+        to: 3
+    go: hall
+  - name: spend
+    when: coins
+    repeatable: true
+    label: Spend a coin
+    choose:
+      - label: Spend it
+        assign:
+          - decrement: coins
+      - label: Hoard it
+        when: coins > 10
+  - name: unreachable
+    when: coins > 5
+    label: Count your riches
+"#;
+
+    fn model() -> Model {
+        compile_world(WORLD).model
+    }
+
+    #[test]
+    fn test_simulation_report() {
+        let model = model();
+        let report = Simulation::new(&model).with_runs(20).with_steps(20).run();
+
+        let visits = |name: &str| report.storylets.iter().find(|visits| model.storylets[visits.storylet].name == name).unwrap().clone();
+        assert_eq!(visits("initialize").runs, 20);
+        assert_eq!(visits("spend").visits, 60);
+        assert_eq!(visits("unreachable").runs, 0);
+
+        assert_eq!(report.choices.len(), 2);
+        assert_eq!(report.choices[0].visits, 60);
+        assert_eq!(report.choices[1].visits, 0);
+
+        assert_eq!(report.dead_ends.len(), 1);
+        assert_eq!(report.dead_ends[0].location.as_deref(), Some("hall"));
+        assert_eq!(report.dead_ends[0].runs, 20);
+
+        let coins = &report.qualities[0];
+        assert_eq!(coins.samples.last().unwrap().maximum, 0);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Effect {
+    pub before: u32,
+    pub after: u32,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct State {
+    qualities: BTreeMap<String, u32>,
+    location: Option<String>,
+    storylet: Option<String>,
+    stack: Vec<String>,
+}
+
+impl State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn qualities(&self) -> &BTreeMap<String, u32> {
+        &self.qualities
+    }
+
+    pub fn get(&self, name: &str) -> u32 {
+        self.qualities.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    pub fn storylet(&self) -> Option<&str> {
+        self.storylet.as_deref()
+    }
+
+    pub fn stack(&self) -> &[String] {
+        &self.stack
+    }
+
+    pub fn set(&mut self, name: &str, value: u32) -> Option<Effect> {
+        let before = self.get(name);
+        if before < value {
+            self.update(name, value);
+            Some(Effect { before, after: value })
+        } else {
+            None
+        }
+    }
+
+    pub fn unset(&mut self, name: &str, value: u32) -> Option<Effect> {
+        let before = self.get(name);
+        if value > 0 && before > value {
+            self.update(name, value);
+            Some(Effect { before, after: value })
+        } else {
+            None
+        }
+    }
+
+    pub fn increment(&mut self, name: &str, step: u32) -> Option<Effect> {
+        let before = self.get(name);
+        let after = before.saturating_add(step);
+        if after > before {
+            self.update(name, after);
+            Some(Effect { before, after })
+        } else {
+            None
+        }
+    }
+
+    pub fn decrement(&mut self, name: &str, step: u32) -> Option<Effect> {
+        let before = self.get(name);
+        let after = before.saturating_sub(step);
+        if after < before {
+            self.update(name, after);
+            Some(Effect { before, after })
+        } else {
+            None
+        }
+    }
+
+    pub fn set_location(&mut self, name: &str) {
+        self.increment(name, 1);
+        self.location = Some(name.to_string());
+    }
+
+    pub fn set_storylet(&mut self, name: &str) {
+        self.commit_storylet();
+        self.storylet = Some(name.to_string());
+    }
+
+    pub fn commit_storylet(&mut self) {
+        if let Some(storylet) = self.storylet.take() {
+            self.increment(&storylet, 1);
+        }
+    }
+
+    pub fn push_storylets(&mut self, names: &[String]) {
+        self.stack.extend(names.iter().rev().cloned());
+    }
+
+    pub fn pop_storylet(&mut self) -> Option<String> {
+        self.stack.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.qualities.clear();
+        self.location = None;
+        self.storylet = None;
+        self.stack.clear();
+    }
+
+    fn update(&mut self, name: &str, value: u32) {
+        if value == 0 {
+            self.qualities.remove(name);
+        } else {
+            self.qualities.insert(name.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::runtime::state::{Effect, State};
+
+    #[test]
+    fn test_set_only_raises() {
+        let mut state = State::new();
+        assert_eq!(state.set("coins", 3), Some(Effect { before: 0, after: 3 }));
+        assert_eq!(state.set("coins", 2), None);
+        assert_eq!(state.get("coins"), 3);
+    }
+
+    #[test]
+    fn test_unset_only_lowers() {
+        let mut state = State::new();
+        state.set("coins", 3);
+        assert_eq!(state.unset("coins", 5), None);
+        assert_eq!(state.unset("coins", 0), None);
+        assert_eq!(state.unset("coins", 1), Some(Effect { before: 3, after: 1 }));
+    }
+
+    #[test]
+    fn test_decrement_floors_at_zero() {
+        let mut state = State::new();
+        state.increment("coins", 2);
+        assert_eq!(state.decrement("coins", 5), Some(Effect { before: 2, after: 0 }));
+        assert_eq!(state.decrement("coins", 1), None);
+        assert!(state.qualities().is_empty());
+    }
+
+    #[test]
+    fn test_storylets_and_locations_are_counted() {
+        let mut state = State::new();
+        state.set_location("red-room");
+        state.set_storylet("intro");
+        state.set_storylet("outro");
+        state.commit_storylet();
+        assert_eq!(state.get("red-room"), 1);
+        assert_eq!(state.get("intro"), 1);
+        assert_eq!(state.get("outro"), 1);
+        assert_eq!(state.location(), Some("red-room"));
+        assert_eq!(state.storylet(), None);
+    }
+}
@@ -1,5 +1,7 @@
 use std::fs::{File};
 use std::path::PathBuf;
+use std::time::Instant;
+use log::debug;
 use crate::error::SourceError;
 use crate::yaml::{Document, FailsafeSchema};
 
@@ -21,8 +23,11 @@ impl Source {
     }
 
     pub fn from_path(path: &PathBuf) -> Result<Source, SourceError> {
+        let start = Instant::now();
         let file = File::open(path).map_err(|e| SourceError::from(e))?;
-        FailsafeSchema::parse(file).map_err(|e| SourceError::from(e)).and_then(|documents| Ok(Self::new(&path.to_string_lossy(), documents)))
+        let source = FailsafeSchema::parse(file).map_err(|e| SourceError::from(e)).and_then(|documents| Ok(Self::new(&path.to_string_lossy(), documents)))?;
+        debug!("Parsed {} ({} document{}) in {:.2?}", path.display(), source.documents.len(), if source.documents.len() == 1 { "" } else { "s" }, start.elapsed());
+        Ok(source)
     }
 }
 
@@ -11,7 +11,6 @@ pub struct StringTableAddress {
     pub end: usize,
 }
 
-#[allow(dead_code)]
 impl StringTable {
     pub fn new() -> Self {
         StringTable {
@@ -1,18 +1,22 @@
 use std::collections::HashMap;
+use std::str::Chars;
 use lazy_static::lazy_static;
+use log::debug;
 use regex::Regex;
-use crate::{ElementTree, Problem};
+use crate::{ElementTree, Language, Problem};
 use crate::element::NameElement;
 
 pub struct SymbolList {
     symbols: Vec<String>,
+    language: Language,
 }
 
 #[allow(dead_code)]
 impl SymbolList {
     pub fn new() -> Self {
         SymbolList {
-            symbols: Vec::new()
+            symbols: Vec::new(),
+            language: Language::default(),
         }
     }
 
@@ -21,7 +25,7 @@ impl SymbolList {
     }
 
     pub fn extract(element_tree: &ElementTree, problems: &mut Vec<Problem>) -> Self {
-
+        let language = element_tree.language();
         let mut name_elements = Vec::new();
 
         for quality in &element_tree.qualities {
@@ -62,7 +66,7 @@ impl SymbolList {
         let mut symbols = Vec::new();
 
         for name in name_elements {
-            let normalized_name = normalize(&name.name);
+            let normalized_name = normalize(&name.name, &language);
             if let Some(existing_attribution) = map.get(&normalized_name) {
                 problems.push(Problem::fatal("All names must be unique, but this one isn't", &name.attribution)
                     .with_context("Already defined here", *existing_attribution));
@@ -74,12 +78,17 @@ impl SymbolList {
 
         symbols.sort();
         symbols.reverse();
+        debug!("Extracted {} symbols", symbols.len());
+
+        Self { symbols, language }
+    }
 
-        Self { symbols }
+    pub fn normalize(&self, name: &str) -> String {
+        normalize(name, &self.language)
     }
 
     pub fn push(&mut self, symbol: &str) {
-        let normalized_symbol = normalize(symbol);
+        let normalized_symbol = self.normalize(symbol);
 
         if !self.symbols.contains(&normalized_symbol) {
             self.symbols.push(normalized_symbol);
@@ -89,12 +98,12 @@ impl SymbolList {
     }
 
     pub fn contains(&self, symbol: &str) -> bool {
-        let normalized_symbol = normalize(symbol);
+        let normalized_symbol = self.normalize(symbol);
         self.symbols.contains(&normalized_symbol)
     }
 
     pub fn require(&self, name: &NameElement, problems: &mut Vec<Problem>) -> String {
-        let normalized_symbol = normalize(&name.name);
+        let normalized_symbol = self.normalize(&name.name);
         if !self.symbols.contains(&normalized_symbol) {
             problems.push(Problem::fatal("Expected the name of an existing quality, location, or storylet", &name.attribution));
         }
@@ -115,13 +124,12 @@ impl SymbolList {
                     continue;
                 }
 
-                let lowercase_source_char = source_char.to_ascii_lowercase();
                 if let Some(symbol_char) = symbol_chars.next() {
                     if whitespace {
                         whitespace = false;
                         if symbol_char.is_whitespace() {
                             if let Some(symbol_char) = symbol_chars.next() {
-                                if lowercase_source_char == symbol_char {
+                                if self.matches(source_char, symbol_char, &mut symbol_chars) {
                                     source_length += source_char.len_utf8();
                                 } else {
                                     break;
@@ -133,7 +141,7 @@ impl SymbolList {
                             matched = false;
                             break;
                         }
-                    } else if lowercase_source_char == symbol_char {
+                    } else if self.matches(source_char, symbol_char, &mut symbol_chars) {
                         source_length += source_char.len_utf8();
                     } else {
                         matched = false;
@@ -151,6 +159,13 @@ impl SymbolList {
         }
         None
     }
+
+    // Whether a character of the source, lowercased, comes next in a symbol. Some characters lowercase to more than one
+    fn matches(&self, source_char: char, symbol_char: char, symbol_chars: &mut Chars) -> bool {
+        let lowercase = self.language.to_lowercase(source_char.encode_utf8(&mut [0; 4]));
+        let mut lowercase_chars = lowercase.chars();
+        lowercase_chars.next() == Some(symbol_char) && lowercase_chars.all(|c| symbol_chars.next() == Some(c))
+    }
 }
 
 #[cfg(test)]
@@ -178,6 +193,23 @@ mod symbol_list_tests {
         assert_eq!(symbols.starts_with("a\triver    in space"), Some((19, String::from("a river in space"))));
     }
 
+    #[test]
+    fn it_lowercases_in_the_language() {
+        let symbols = SymbolList::builder().push("\u{00c4}rger").push("Stra\u{00df}e").build();
+        assert_eq!(symbols.starts_with("\u{00c4}RGER"), Some((6, String::from("\u{00e4}rger"))));
+        assert_eq!(symbols.starts_with("STRA\u{00df}E"), Some((7, String::from("stra\u{00df}e"))));
+
+        let symbols = SymbolList::builder().language(Language::parse("tr").unwrap()).push("I\u{015e}IK").push("\u{0130}p").build();
+        assert_eq!(symbols.starts_with("\u{0131}\u{015f}\u{0131}k"), Some((7, String::from("\u{0131}\u{015f}\u{0131}k"))));
+        assert_eq!(symbols.starts_with("ip"), Some((2, String::from("ip"))));
+        assert_eq!(symbols.starts_with("\u{0130}P"), Some((3, String::from("ip"))));
+        assert_eq!(symbols.starts_with("isik"), None);
+
+        let symbols = SymbolList::builder().push("\u{0130}p").build();
+        assert_eq!(symbols.starts_with("\u{0130}p"), Some((3, String::from("i\u{0307}p"))));
+        assert_eq!(symbols.starts_with("ip"), None);
+    }
+
     #[test]
     fn it_counts_utf8_bytes() {
         let symbols = SymbolList::builder().push("r\u{00e9}sum\u{00e9}").build();
@@ -187,16 +219,23 @@ mod symbol_list_tests {
 
 pub struct SymbolListBuilder {
     symbols: Vec<String>,
+    language: Language,
 }
 
 #[allow(dead_code)]
 impl SymbolListBuilder {
     pub fn new() -> Self {
-        SymbolListBuilder { symbols: Vec::new() }
+        SymbolListBuilder { symbols: Vec::new(), language: Language::default() }
+    }
+
+    // Symbols pushed afterward are lowercased in this language
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
     }
 
     pub fn push(mut self, symbol: &str) -> Self {
-        let normalized_symbol = normalize(symbol);
+        let normalized_symbol = normalize(symbol, &self.language);
         if !self.symbols.contains(&normalized_symbol) {
             self.symbols.push(normalized_symbol);
         }
@@ -209,6 +248,7 @@ impl SymbolListBuilder {
 
         SymbolList {
             symbols: self.symbols,
+            language: self.language,
         }
     }
 }
@@ -217,8 +257,9 @@ lazy_static! {
     static ref WHITESPACE_REGEX: Regex = Regex::new("\\s+").unwrap();
 }
 
-pub fn normalize(source: &str) -> String {
-    WHITESPACE_REGEX.replace_all(source.trim(), " ").to_lowercase()
+// Names are compared ignoring case, in the world's language, and runs of whitespace
+pub fn normalize(source: &str, language: &Language) -> String {
+    language.to_lowercase(&WHITESPACE_REGEX.replace_all(source.trim(), " "))
 }
 
 #[cfg(test)]
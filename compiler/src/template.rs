@@ -4,3 +4,5 @@ mod compile;
 mod lexer;
 
 pub use crate::template::parse::*;
+pub(crate) use crate::template::compile::*;
+pub(crate) use crate::template::lexer::{TemplateLexer, TemplateToken};
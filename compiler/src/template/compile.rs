@@ -1,7 +1,8 @@
-use crate::{Attribution, Problem};
-use crate::expression::{BRANCH, ExpressionCompiler, JUMP};
+use crate::bytecode::BytecodeError;
+use crate::expression::{ExpressionCompiler, JUMP};
 use crate::string_table::StringTable;
 use crate::template::TemplateParseNode;
+use crate::text::{Text, TextNode};
 
 pub const STRING_PUSH: u32 = 100;
 pub const ITALIC_PUSH: u32 = 101;
@@ -11,9 +12,10 @@ pub const BOLD_POP: u32 = 104;
 pub const ANCHOR_PUSH: u32 = 105;
 pub const ANCHOR_POP: u32 = 106;
 pub const PARAGRAPH_PUSH: u32 = 107;
+pub const PARAGRAPH_POP: u32 = 108;
+pub const TEMPLATE_BRANCH: u32 = 109;
 
-#[allow(dead_code)]
-pub fn compile_template(parse: Vec<TemplateParseNode>, attribution: &Attribution, string_table: &mut StringTable, problems: &mut Vec<Problem>) -> Vec<u32> {
+pub fn compile_template(parse: &[TemplateParseNode], string_table: &mut StringTable) -> Result<Vec<u32>, BytecodeError> {
     let mut result = Vec::new();
     let expression_compiler = ExpressionCompiler::new();
 
@@ -21,49 +23,87 @@ pub fn compile_template(parse: Vec<TemplateParseNode>, attribution: &Attribution
         match parse {
             TemplateParseNode::Text(s) => {
                 result.push(STRING_PUSH);
-                let address = string_table.put(&s);
+                let address = string_table.put(s);
                 result.push(address.start as u32);
                 result.push(address.end as u32);
             },
             TemplateParseNode::Italic(t) => {
                 result.push(ITALIC_PUSH);
-                result.extend(compile_template(t, attribution, string_table, problems));
+                result.extend(compile_template(t, string_table)?);
                 result.push(ITALIC_POP);
             },
             TemplateParseNode::Bold(t) => {
                 result.push(BOLD_PUSH);
-                result.extend(compile_template(t, attribution, string_table, problems));
+                result.extend(compile_template(t, string_table)?);
                 result.push(BOLD_POP);
             },
             TemplateParseNode::Anchor(href, t) => {
                 result.push(ANCHOR_PUSH);
-                result.extend(compile_template(t, attribution, string_table, problems));
+                result.extend(compile_template(t, string_table)?);
                 result.push(ANCHOR_POP);
-                let address = string_table.put(&href);
+                let address = string_table.put(href);
                 result.push(address.start as u32);
                 result.push(address.end as u32);
             },
+            // The jump past the else branch is always written, so that the end of the then branch is unambiguous
             TemplateParseNode::Branch(condition, then_branch, else_branch) => {
-                let expression_result = expression_compiler.compile_logical(&condition, attribution, string_table);
-                problems.extend(expression_result.problems);
-                result.extend(expression_result.bytecode);
-                result.push(BRANCH);
-                let mut then_result = compile_template(then_branch, attribution, string_table, problems);
-                if let Some(else_branch) = else_branch {
-                    then_result.push(JUMP);
-                    let else_result = compile_template(else_branch, attribution, string_table, problems);
-                    then_result.push(else_result.len() as u32);
-                    result.push(then_result.len() as u32);
-                    result.extend(then_result);
-                    result.extend(else_result);
-                } else {
-                    result.push(then_result.len() as u32);
-                    result.extend(then_result);
-                }
+                result.extend(expression_compiler.compile(condition, string_table)?);
+                let then_result = compile_template(then_branch, string_table)?;
+                let else_result = match else_branch {
+                    Some(else_branch) => compile_template(else_branch, string_table)?,
+                    None => Vec::new(),
+                };
+                result.push(TEMPLATE_BRANCH);
+                result.push((then_result.len() + 2) as u32);
+                result.extend(then_result);
+                result.push(JUMP);
+                result.push(else_result.len() as u32);
+                result.extend(else_result);
             },
             TemplateParseNode::Paragraph => result.push(PARAGRAPH_PUSH),
         }
     }
 
+    Ok(result)
+}
+
+// Text is compiled like a template without branches, except that paragraphs enclose their text
+pub fn compile_text(text: &Text, string_table: &mut StringTable) -> Vec<u32> {
+    let mut result = Vec::new();
+
+    for node in text {
+        match node {
+            TextNode::Plain(s) => {
+                result.push(STRING_PUSH);
+                let address = string_table.put(s);
+                result.push(address.start as u32);
+                result.push(address.end as u32);
+            },
+            TextNode::Paragraph(t) => {
+                result.push(PARAGRAPH_PUSH);
+                result.extend(compile_text(t, string_table));
+                result.push(PARAGRAPH_POP);
+            },
+            TextNode::Italic(t) => {
+                result.push(ITALIC_PUSH);
+                result.extend(compile_text(t, string_table));
+                result.push(ITALIC_POP);
+            },
+            TextNode::Bold(t) => {
+                result.push(BOLD_PUSH);
+                result.extend(compile_text(t, string_table));
+                result.push(BOLD_POP);
+            },
+            TextNode::Anchor(href, t) => {
+                result.push(ANCHOR_PUSH);
+                result.extend(compile_text(t, string_table));
+                result.push(ANCHOR_POP);
+                let address = string_table.put(href);
+                result.push(address.start as u32);
+                result.push(address.end as u32);
+            },
+        }
+    }
+
     result
 }
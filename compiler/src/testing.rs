@@ -0,0 +1,31 @@
+// Fixtures shared by tests that compile a world from a single YAML file
+
+use crate::{Model, ModelParser, ModelParsingResult, Problem, Source};
+use crate::element::ElementTree;
+
+pub fn element_tree(source: &str) -> (ElementTree, Vec<Problem>) {
+    let sources = vec!(Source::from_string("world.yaml", source).unwrap());
+    let mut problems = Vec::new();
+    let tree = ElementTree::from_sources(&sources, &mut problems);
+    (tree, problems)
+}
+
+// Compiles a world with the given parser, collecting the problems found while reading it before those from parsing it
+pub fn compile_world_with(source: &str, parser: ModelParser) -> ModelParsingResult {
+    let (tree, mut problems) = element_tree(source);
+    let mut result = parser.parse(&tree);
+    problems.append(&mut result.problems);
+    result.problems = problems;
+    result
+}
+
+pub fn compile_world(source: &str) -> ModelParsingResult {
+    compile_world_with(source, ModelParser::new())
+}
+
+// Compiles a world that's expected to have no problems at all
+pub fn compile_model(source: &str) -> Model {
+    let result = compile_world(source);
+    assert!(result.problems.is_empty(), "{:?}", result.problems.iter().map(|problem| problem.message).collect::<Vec<_>>());
+    result.model
+}
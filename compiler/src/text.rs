@@ -3,7 +3,7 @@ mod parse;
 
 pub type Text = Vec<TextNode>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum TextNode {
     Plain(String),
     Paragraph(Text),
@@ -44,3 +44,21 @@ impl Serialize for TextNode {
 use serde::{Serialize, Serializer};
 use serde::ser::SerializeMap;
 pub use crate::text::parse::*;
+
+// The text without formatting, with paragraphs separated by spaces
+pub fn plain(text: &Text) -> String {
+    let mut result = String::new();
+    for node in text {
+        match node {
+            TextNode::Plain(s) => result.push_str(s),
+            TextNode::Paragraph(t) => {
+                if !result.is_empty() {
+                    result.push(' ');
+                }
+                result.push_str(&plain(t));
+            },
+            TextNode::Italic(t) | TextNode::Bold(t) | TextNode::Anchor(_, t) => result.push_str(&plain(t)),
+        }
+    }
+    result.trim().to_string()
+}
@@ -7,10 +7,13 @@ mod document;
 mod context;
 mod path;
 mod mark;
+mod mapping;
 
 pub use schema::{*};
 pub use value::{Value};
+pub use mapping::{Mapping};
 pub use node::{Node};
 pub use document::{Document};
 pub use path::{Path};
 pub use mark::{Mark};
+pub use error::{Error};
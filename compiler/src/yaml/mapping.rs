@@ -0,0 +1,85 @@
+use std::cell::{Ref, RefCell};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use crate::yaml::node::Node;
+
+// A mapping that remembers which keys have been looked up by name, in the order they were first asked for, whether or
+// not they were present. Lookups don't take part in comparisons
+#[derive(Clone, Default)]
+pub struct Mapping {
+    entries: BTreeMap<Node, Node>,
+    lookups: RefCell<Vec<String>>,
+}
+
+impl Mapping {
+    pub fn new(entries: BTreeMap<Node, Node>) -> Self {
+        Self {
+            entries,
+            lookups: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn lookup(&self, key: &str) -> Option<&Node> {
+        let mut lookups = self.lookups.borrow_mut();
+        if !lookups.iter().any(|lookup| lookup == key) {
+            lookups.push(key.to_string());
+        }
+        self.entries.get(&Node::string(key))
+    }
+
+    pub fn lookups(&self) -> Ref<'_, Vec<String>> {
+        self.lookups.borrow()
+    }
+}
+
+impl Deref for Mapping {
+    type Target = BTreeMap<Node, Node>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<'a> IntoIterator for &'a Mapping {
+    type Item = (&'a Node, &'a Node);
+    type IntoIter = std::collections::btree_map::Iter<'a, Node, Node>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl Debug for Mapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.entries, f)
+    }
+}
+
+impl PartialEq for Mapping {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries.eq(&other.entries)
+    }
+}
+
+impl Eq for Mapping {}
+
+impl Hash for Mapping {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries.hash(state);
+    }
+}
+
+impl PartialOrd for Mapping {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Mapping {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entries.cmp(&other.entries)
+    }
+}
@@ -5,6 +5,7 @@ use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use crate::yaml::document::DocumentParsingContext;
 use crate::yaml::error::Error;
+use crate::yaml::mapping::Mapping;
 use crate::yaml::mark::Mark;
 use crate::yaml::path::{Path, PathElement};
 use crate::yaml::schema::Schema;
@@ -150,7 +151,7 @@ impl<'p, 'd, 'n, S: Schema> NodeParsingContext<'p, 'd, 'n, S> {
             let key_event = self.next()?;
 
             if let EventData::MappingEnd { .. } = key_event.data {
-                return Ok(self.resolve(tag, Value::Mapping(map), start_mark, key_event.end_mark.into()));
+                return Ok(self.resolve(tag, Value::Mapping(Mapping::new(map)), start_mark, key_event.end_mark.into()));
             }
 
             let key = self.parse(key_event)?;
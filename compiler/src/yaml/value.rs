@@ -1,14 +1,14 @@
-use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use crate::yaml::mapping::Mapping;
 use crate::yaml::node::Node;
 
 #[derive(Clone, Debug, Eq)]
 pub enum Value {
     Scalar(String),
     Sequence(Vec<Node>),
-    Mapping(BTreeMap<Node, Node>),
+    Mapping(Mapping),
 }
 
 impl Value {
@@ -28,7 +28,7 @@ impl Value {
         }
     }
 
-    pub fn as_mapping(&self) -> Option<&BTreeMap<Node, Node>> {
+    pub fn as_mapping(&self) -> Option<&Mapping> {
         if let Self::Mapping(btree) = self {
             Some(btree)
         } else {
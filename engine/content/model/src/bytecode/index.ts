@@ -0,0 +1,579 @@
+import { Model } from '../index';
+import { Conditional } from '../conditional';
+import { Expression } from '../expression';
+import { Location } from '../location';
+import { Quality, QualityValue } from '../quality';
+import { Assignment, AssignmentGroup, Choice, ChoiceGroup, Choices, Storylet } from '../storylet';
+import { Template, TemplateNode } from '../template';
+import { Text, TextNode } from '../text';
+
+// Decodes the bytecode model format written by `worldtree build --model-format bytecode`. The format is described in
+// the compiler's bytecode module.
+
+const MAGIC = [0x57, 0x54, 0x42, 0x43];
+const VERSION = 1;
+
+const PUSH = 0;
+const BRANCH = 8;
+const JUMP = 9;
+const PUSH_LOGICAL = 10;
+const PUSH_VALUE_OF = 11;
+const IN_LOCATION = 12;
+
+const STRING_PUSH = 100;
+const ITALIC_PUSH = 101;
+const ITALIC_POP = 102;
+const BOLD_PUSH = 103;
+const BOLD_POP = 104;
+const ANCHOR_PUSH = 105;
+const ANCHOR_POP = 106;
+const PARAGRAPH_PUSH = 107;
+const PARAGRAPH_POP = 108;
+const TEMPLATE_BRANCH = 109;
+
+const OPERATORS: Record<number, string> = {
+    1: 'not',
+    2: 'and',
+    3: 'or',
+    4: 'plus',
+    5: 'minus',
+    6: 'multiply',
+    7: 'divide',
+    13: 'either',
+    14: 'equal',
+    15: 'notEqual',
+    16: 'greaterThan',
+    17: 'greaterThanOrEqual',
+    18: 'lessThan',
+    19: 'lessThanOrEqual',
+    20: 'maximum',
+    21: 'minimum',
+    22: 'random',
+    23: 'between',
+    24: 'is',
+};
+
+const OPERATIONS: Assignment['operation'][] = ['set', 'unset', 'increment', 'decrement'];
+
+export function decodeModel(bytes: Uint8Array): Model {
+    return new Decoder(bytes).model();
+}
+
+export function isBytecodeModel(bytes: Uint8Array): boolean {
+    return MAGIC.every((byte, index) => bytes[index] === byte);
+}
+
+class Decoder {
+    private readonly strings: Uint8Array;
+    private readonly words: number[] = [];
+    private position = 0;
+
+    constructor(bytes: Uint8Array) {
+        if (!isBytecodeModel(bytes)) {
+            throw new Error('Not a Worldtree bytecode model');
+        }
+
+        const cursor = { position: MAGIC.length };
+        const version = readVarint(bytes, cursor);
+        if (version !== VERSION) {
+            throw new Error(`Unsupported bytecode version ${version}, expected ${VERSION}`);
+        }
+
+        const key = readVarint(bytes, cursor);
+        const stringsLength = readVarint(bytes, cursor);
+        if (cursor.position + stringsLength > bytes.length) {
+            throw new Error('Unexpected end of bytecode');
+        }
+        this.strings = bytes.slice(cursor.position, cursor.position + stringsLength);
+        scramble(this.strings, key);
+        cursor.position += stringsLength;
+
+        const wordsLength = readVarint(bytes, cursor);
+        for (let i = 0; i < wordsLength; i++) {
+            this.words.push(readVarint(bytes, cursor));
+        }
+    }
+
+    model(): Model {
+        const flags = this.word();
+        const meta: Model['meta'] = {};
+        if (isSet(flags, 0)) {
+            meta.title = this.text();
+        }
+        if (isSet(flags, 1)) {
+            meta.description = this.text();
+        }
+        meta.credits = this.list(() => this.text());
+        if (isSet(flags, 2)) {
+            meta.lang = this.string();
+        }
+        if (isSet(flags, 3)) {
+            meta.author = this.text();
+        }
+        if (isSet(flags, 4)) {
+            meta.image = this.string();
+        }
+        if (isSet(flags, 5)) {
+            meta.favicon = this.string();
+        }
+        if (isSet(flags, 6)) {
+            meta.url = this.string();
+        }
+        const keywords = this.list(() => this.string());
+        if (keywords.length > 0) {
+            meta.keywords = keywords;
+        }
+
+        const qualities = this.list(() => this.quality());
+        const locations = this.list(() => this.location());
+        const storylets = this.list(() => this.storylet());
+        if (this.position !== this.words.length) {
+            throw new Error(`Invalid code at word ${this.position}: unexpected words after the model`);
+        }
+        return { meta, qualities, locations, storylets };
+    }
+
+    private quality(): Quality {
+        const quality: Quality = { name: this.string() };
+        const flags = this.word();
+        if (isSet(flags, 0)) {
+            quality.label = this.template();
+        }
+        if (isSet(flags, 1)) {
+            quality.singularLabel = this.template();
+        }
+        if (isSet(flags, 2)) {
+            quality.pluralLabel = this.template();
+        }
+        if (isSet(flags, 3)) {
+            quality.description = this.template();
+        }
+        if (isSet(flags, 4)) {
+            quality.icon = this.conditional(() => this.string());
+        }
+        if (isSet(flags, 5)) {
+            quality.hidden = true;
+        }
+        if (isSet(flags, 6)) {
+            const style = this.word();
+            quality.style = {
+                currency: isSet(style, 0),
+                personal: isSet(style, 1),
+                plural: isSet(style, 2),
+                possessive: isSet(style, 3),
+                uncounted: isSet(style, 4),
+            };
+        }
+        if (isSet(flags, 7)) {
+            quality.values = this.list(() => {
+                const value: QualityValue = { name: this.string() };
+                const flags = this.word();
+                if (isSet(flags, 0)) {
+                    value.label = this.template();
+                }
+                if (isSet(flags, 1)) {
+                    value.description = this.template();
+                }
+                if (isSet(flags, 2)) {
+                    value.icon = this.conditional(() => this.string());
+                }
+                return value;
+            });
+        }
+        if (isSet(flags, 8)) {
+            quality.exclusive = true;
+        }
+        return quality;
+    }
+
+    private location(): Location {
+        const location: Location = { name: this.string(), label: this.template() };
+        const flags = this.word();
+        if (isSet(flags, 0)) {
+            location.description = this.template();
+        }
+        if (isSet(flags, 1)) {
+            location.body = this.template();
+        }
+        return location;
+    }
+
+    private storylet(): Storylet {
+        const storylet: Storylet = { name: this.string() };
+        const flags = this.word();
+        if (isSet(flags, 0)) {
+            storylet.condition = this.expression();
+        }
+        if (isSet(flags, 1)) {
+            storylet.label = this.template();
+        }
+        if (isSet(flags, 2)) {
+            storylet.description = this.template();
+        }
+        this.outcome(storylet, flags, 3);
+        if (isSet(flags, 9)) {
+            storylet.choices = this.choices();
+        }
+        return storylet;
+    }
+
+    // The fields that storylets and choices share, in the order both declare them, starting from the given flag
+    private outcome(outcome: Storylet | Choice, flags: number, bit: number) {
+        if (isSet(flags, bit)) {
+            outcome.icon = this.conditional(() => this.string());
+        }
+        if (isSet(flags, bit + 1)) {
+            outcome.body = this.template();
+        }
+        if (isSet(flags, bit + 2)) {
+            outcome.navigation = this.conditional(() => this.string());
+        }
+        if (isSet(flags, bit + 3)) {
+            outcome.push = this.conditional(() => this.list(() => this.string()));
+        }
+        if (isSet(flags, bit + 4)) {
+            outcome.shift = this.conditional(() => this.list(() => this.string()));
+        }
+        if (isSet(flags, bit + 5)) {
+            outcome.assignments = this.list(() => this.assignmentGroup());
+        }
+    }
+
+    private assignmentGroup(): AssignmentGroup {
+        const flags = this.word();
+        const group: AssignmentGroup = {
+            assignments: this.list(() => {
+                const flags = this.word();
+                const condition = isSet(flags, 0) ? this.expression() : undefined;
+                const subject = this.string();
+                const position = this.position;
+                const operation = OPERATIONS[this.word()];
+                if (!operation) {
+                    throw new Error(`Invalid code at word ${position}: unknown assignment operation`);
+                }
+                const assignment: Assignment = { subject, operation, operand: this.expression() };
+                if (condition !== undefined) {
+                    assignment.condition = condition;
+                }
+                return assignment;
+            }),
+        };
+        if (isSet(flags, 0)) {
+            group.description = this.template();
+        }
+        return group;
+    }
+
+    private choices(): Choices {
+        const flags = this.word();
+        const prompt = isSet(flags, 0) ? this.template() : undefined;
+        const choices: Choices = {
+            groups: this.list(() => {
+                const flags = this.word();
+                const group: ChoiceGroup = { choices: [] };
+                if (isSet(flags, 0)) {
+                    group.limit = this.expression();
+                }
+                if (isSet(flags, 1)) {
+                    group.shuffle = this.expression();
+                }
+                group.choices = this.list(() => this.choice());
+                return group;
+            }),
+        };
+        if (prompt !== undefined) {
+            choices.prompt = prompt;
+        }
+        return choices;
+    }
+
+    private choice(): Choice {
+        const flags = this.word();
+        const condition = isSet(flags, 0) ? this.expression() : undefined;
+        const choice: Choice = { label: this.template() };
+        if (condition !== undefined) {
+            choice.condition = condition;
+        }
+        if (isSet(flags, 1)) {
+            choice.description = this.template();
+        }
+        this.outcome(choice, flags, 2);
+        return choice;
+    }
+
+    private conditional<V>(value: () => V): Conditional<V> {
+        const conditions: [Expression, V][] = [];
+        const count = this.word();
+        for (let i = 0; i < count; i++) {
+            const condition = this.expression();
+            conditions.push([condition, value()]);
+        }
+        let result: Conditional<V> = value();
+        for (const [condition, then] of conditions.reverse()) {
+            result = { condition, value: then, next: result };
+        }
+        return result;
+    }
+
+    private list<T>(item: () => T): T[] {
+        const result: T[] = [];
+        const count = this.word();
+        for (let i = 0; i < count; i++) {
+            result.push(item());
+        }
+        return result;
+    }
+
+    private word(): number {
+        if (this.position >= this.words.length) {
+            throw new Error('Unexpected end of bytecode');
+        }
+        return this.words[this.position++];
+    }
+
+    private string(): string {
+        const start = this.word();
+        const end = this.word();
+        if (start > end || end > this.strings.length) {
+            throw new Error(`Invalid string table address ${start}..${end}`);
+        }
+        return decodeUtf8(this.strings.subarray(start, end));
+    }
+
+    // Code is preceded by its length, which must contain it exactly
+    private codeEnd(): number {
+        const end = this.word() + this.position;
+        if (end > this.words.length) {
+            throw new Error('Unexpected end of bytecode');
+        }
+        return end;
+    }
+
+    private expression(): Expression {
+        return this.expressionCode(this.codeEnd());
+    }
+
+    private template(): Template {
+        return this.templateNodes(this.codeEnd());
+    }
+
+    private text(): Text {
+        return this.textNodes(this.codeEnd());
+    }
+
+    private expressionCode(end: number): Expression {
+        const start = this.position;
+        const stack: Expression[] = [];
+        while (this.position < end) {
+            this.expressionInstruction(stack, end);
+        }
+        if (stack.length !== 1 || this.position !== end) {
+            throw new Error(`Invalid code at word ${start}: expected code that leaves one value`);
+        }
+        return stack[0];
+    }
+
+    private expressionInstruction(stack: Expression[], end: number) {
+        const position = this.position;
+        const opcode = this.word();
+        switch (opcode) {
+            case PUSH:
+                stack.push(this.word());
+                break;
+            case PUSH_LOGICAL:
+                stack.push(this.word() ? 1 : 0);
+                break;
+            case PUSH_VALUE_OF:
+                stack.push(this.string());
+                break;
+            case IN_LOCATION:
+                stack.push(['in', this.string()]);
+                break;
+            case BRANCH: {
+                const skip = this.word();
+                const condition = stack.pop();
+                if (condition === undefined || skip < 2 || this.position + skip > end) {
+                    throw new Error(`Invalid code at word ${position}: invalid branch`);
+                }
+                const consequent = this.expressionCode(this.position + skip - 2);
+                const alternative = this.expressionCode(this.jump(end));
+                stack.push(['then', condition, consequent, alternative]);
+                break;
+            }
+            default: {
+                const operator = OPERATORS[opcode];
+                if (!operator) {
+                    throw new Error(`Invalid opcode ${opcode} at word ${position}`);
+                }
+                const count = this.word();
+                if (count > stack.length) {
+                    throw new Error(`Invalid code at word ${position}: operation has fewer values than it takes`);
+                }
+                stack.push([operator, ...stack.splice(stack.length - count, count)]);
+            }
+        }
+    }
+
+    // Reads the jump past an alternative, returning where the alternative ends
+    private jump(end: number): number {
+        const position = this.position;
+        if (this.word() !== JUMP) {
+            throw new Error(`Invalid code at word ${position}: expected a jump after a branch`);
+        }
+        const skip = this.word();
+        if (this.position + skip > end) {
+            throw new Error(`Invalid code at word ${position}: jump out of bounds`);
+        }
+        return this.position + skip;
+    }
+
+    // Reads nodes until the end of the code or, inside formatting, the instruction that closes it
+    private templateNodes(end: number, closing?: number): Template {
+        const nodes: TemplateNode[] = [];
+        const conditions: Expression[] = [];
+        for (;;) {
+            const position = this.position;
+            if (position >= end || this.words[position] === closing) {
+                if (position >= end && closing !== undefined) {
+                    throw new Error(`Invalid code at word ${position}: formatting is never closed`);
+                }
+                if (conditions.length > 0) {
+                    throw new Error(`Invalid code at word ${position}: condition without a branch`);
+                }
+                return nodes;
+            }
+
+            const opcode = this.words[position];
+            if (opcode < STRING_PUSH) {
+                this.expressionInstruction(conditions, end);
+                continue;
+            }
+
+            this.position++;
+            switch (opcode) {
+                case STRING_PUSH:
+                    nodes.push(this.string());
+                    break;
+                case PARAGRAPH_PUSH:
+                    nodes.push('\n');
+                    break;
+                case ITALIC_PUSH:
+                    nodes.push({ i: this.templateNodes(end, ITALIC_POP) });
+                    this.position++;
+                    break;
+                case BOLD_PUSH:
+                    nodes.push({ b: this.templateNodes(end, BOLD_POP) });
+                    this.position++;
+                    break;
+                case ANCHOR_PUSH: {
+                    const a = this.templateNodes(end, ANCHOR_POP);
+                    this.position++;
+                    nodes.push({ a, href: this.string() });
+                    break;
+                }
+                case TEMPLATE_BRANCH: {
+                    const skip = this.word();
+                    const condition = conditions.pop();
+                    if (condition === undefined || skip < 2 || this.position + skip > end) {
+                        throw new Error(`Invalid code at word ${position}: invalid branch`);
+                    }
+                    const value = this.templateNodes(this.position + skip - 2);
+                    const next = this.templateNodes(this.jump(end));
+                    nodes.push(next.length > 0 ? { condition, value, next } : { condition, value });
+                    break;
+                }
+                default:
+                    throw new Error(`Invalid opcode ${opcode} at word ${position}`);
+            }
+        }
+    }
+
+    private textNodes(end: number, closing?: number): Text {
+        const nodes: TextNode[] = [];
+        for (;;) {
+            const position = this.position;
+            if (position >= end || this.words[position] === closing) {
+                if (position >= end && closing !== undefined) {
+                    throw new Error(`Invalid code at word ${position}: formatting is never closed`);
+                }
+                return nodes;
+            }
+
+            const opcode = this.word();
+            switch (opcode) {
+                case STRING_PUSH:
+                    nodes.push(this.string());
+                    break;
+                case PARAGRAPH_PUSH:
+                    nodes.push({ p: this.textNodes(end, PARAGRAPH_POP) });
+                    this.position++;
+                    break;
+                case ITALIC_PUSH:
+                    nodes.push({ i: this.textNodes(end, ITALIC_POP) });
+                    this.position++;
+                    break;
+                case BOLD_PUSH:
+                    nodes.push({ b: this.textNodes(end, BOLD_POP) });
+                    this.position++;
+                    break;
+                case ANCHOR_PUSH: {
+                    const a = this.textNodes(end, ANCHOR_POP);
+                    this.position++;
+                    nodes.push({ a, href: this.string() });
+                    break;
+                }
+                default:
+                    throw new Error(`Invalid opcode ${opcode} at word ${position}`);
+            }
+        }
+    }
+}
+
+function isSet(flags: number, bit: number): boolean {
+    return (flags & (1 << bit)) !== 0;
+}
+
+// Restores a string table obfuscated with a key, by XORing it with the same xorshift32 sequence
+function scramble(bytes: Uint8Array, key: number) {
+    let state = key;
+    for (let i = 0; i < bytes.length; i++) {
+        state ^= state << 13;
+        state ^= state >>> 17;
+        state ^= state << 5;
+        bytes[i] ^= state & 0xff;
+    }
+}
+
+function readVarint(bytes: Uint8Array, cursor: { position: number }): number {
+    let value = 0;
+    for (let shift = 0; shift < 35; shift += 7) {
+        if (cursor.position >= bytes.length) {
+            throw new Error('Unexpected end of bytecode');
+        }
+        const byte = bytes[cursor.position++];
+        value += (byte & 0x7f) * 2 ** shift;
+        if ((byte & 0x80) === 0) {
+            return value;
+        }
+    }
+    throw new Error('Not a Worldtree bytecode model');
+}
+
+// The string table is UTF-8, and is decoded here so that the model doesn't depend on the DOM's TextDecoder
+function decodeUtf8(bytes: Uint8Array): string {
+    let result = '';
+    for (let i = 0; i < bytes.length;) {
+        const byte = bytes[i++];
+        if (byte < 0x80) {
+            result += String.fromCharCode(byte);
+        } else {
+            const length = byte >= 0xf0 ? 3 : byte >= 0xe0 ? 2 : 1;
+            let codePoint = byte & (0x3f >> length);
+            for (let j = 0; j < length; j++) {
+                codePoint = (codePoint << 6) | (bytes[i++] & 0x3f);
+            }
+            result += String.fromCodePoint(codePoint);
+        }
+    }
+    return result;
+}
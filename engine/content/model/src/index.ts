@@ -8,15 +8,24 @@ export interface Model {
         title?: Text;
         description?: Text;
         credits?: Text[];
+        lang?: string;
+        author?: Text;
+        image?: string;
+        favicon?: string;
+        url?: string;
+        keywords?: string[];
     };
     qualities: Quality[];
     locations: Location[];
     storylets: Storylet[];
 }
 
+export * from './bytecode';
 export * from './conditional';
 export * from './expression';
+export * from './language';
 export * from './location';
+export * from './name';
 export * from './quality';
 export * from './storylet';
 export * from './template'
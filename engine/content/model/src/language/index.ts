@@ -0,0 +1,51 @@
+// A copy of the compiler's plural rules, so that both runtimes choose the same label for a count. These follow the CLDR
+// rules for whole numbers, reduced to whether a count takes the form for one
+
+// Languages whose nouns don't change with number
+const UNINFLECTED = [
+    'bo', 'dz', 'id', 'ig', 'ii', 'ja', 'jbo', 'jv', 'kde', 'kea', 'km', 'ko', 'lkt', 'lo', 'ms', 'my', 'nqo', 'sah',
+    'ses', 'sg', 'su', 'th', 'to', 'vi', 'wuu', 'yo', 'yue', 'zh',
+];
+
+// Languages that treat zero like one, e.g. French
+const ZERO_IS_SINGULAR = [
+    'ak', 'am', 'as', 'bho', 'bn', 'doi', 'fa', 'ff', 'fr', 'gu', 'guw', 'hi', 'hy', 'kab', 'kn', 'ln', 'mg', 'nso',
+    'pa', 'pcm', 'pt', 'shi', 'si', 'ti', 'wa', 'zu',
+];
+
+// Languages that use the singular for 1, 21, 31 and so on, but not 11, e.g. Russian
+const ENDS_IN_ONE_IS_SINGULAR = ['be', 'bs', 'hr', 'is', 'lt', 'lv', 'mk', 'ru', 'sh', 'sr', 'uk'];
+
+// Languages that use the singular for 1, 101, 201 and so on, e.g. Slovenian
+const HUNDREDS_END_IN_ONE_IS_SINGULAR = ['dsb', 'hsb', 'sl'];
+
+// Languages that use the singular for every count that doesn't end in 4, 6 or 9, e.g. Filipino
+const ENDS_IN_FOUR_SIX_OR_NINE_IS_PLURAL = ['ceb', 'fil', 'tl'];
+
+// Whether a count takes the singular form of a label in a language, given as a BCP 47 tag. Labels only have two forms,
+// so languages with more are approximated by the form used for one
+export function isSingular(lang: string | undefined, count: number): boolean {
+    const tag = (lang ?? 'en').toLowerCase();
+    const primary = tag.split('-')[0];
+    if (UNINFLECTED.includes(primary)) {
+        return false;
+    } else if (ZERO_IS_SINGULAR.includes(primary) && tag !== 'pt-pt') {
+        return count <= 1;
+    } else if (ENDS_IN_ONE_IS_SINGULAR.includes(primary)) {
+        return count % 10 === 1 && count % 100 !== 11;
+    } else if (HUNDREDS_END_IN_ONE_IS_SINGULAR.includes(primary)) {
+        return count % 100 === 1;
+    } else if (ENDS_IN_FOUR_SIX_OR_NINE_IS_PLURAL.includes(primary)) {
+        return ![4, 6, 9].includes(count % 10);
+    } else if (primary === 'br') {
+        return count % 10 === 1 && ![11, 71, 91].includes(count % 100);
+    } else if (primary === 'gd') {
+        return count === 1 || count === 11;
+    } else if (primary === 'gv') {
+        return count % 10 === 1;
+    } else if (primary === 'tzm') {
+        return count <= 1 || (count >= 11 && count <= 99);
+    } else {
+        return count === 1;
+    }
+}
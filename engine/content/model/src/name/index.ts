@@ -0,0 +1,16 @@
+// Languages that write a dotless lowercase i, so I lowercases to ı and İ to i
+const DOTLESS_I = ['az', 'crh', 'tr'];
+
+// Names are compared the way the compiler compares them, ignoring case in the world's language and runs of whitespace.
+// Characters are lowercased one at a time, as the compiler does, so a final sigma doesn't depend on its neighbours
+export function normalize(name: string, lang?: string): string {
+    const dotlessI = DOTLESS_I.includes((lang ?? 'en').toLowerCase().split('-')[0]);
+    return Array.from(name.trim().replace(/\s+/g, ' '), (c) => {
+        if (dotlessI && c === 'I') {
+            return 'ı';
+        } else if (dotlessI && c === 'İ') {
+            return 'i';
+        }
+        return c.toLowerCase();
+    }).join('');
+}
@@ -10,6 +10,8 @@ export interface Storylet {
     icon?: Conditional<string>;
     body?: Template;
     navigation?: Conditional<string>;
+    push?: Conditional<string[]>;
+    shift?: Conditional<string[]>;
     assignments?: AssignmentGroup[];
     choices?: Choices;
 }
@@ -45,5 +47,7 @@ export interface Choice {
     icon?: Conditional<string>;
     body?: Template;
     navigation?: Conditional<string>;
+    push?: Conditional<string[]>;
+    shift?: Conditional<string[]>;
     assignments?: AssignmentGroup[];
 }
@@ -1,6 +1,6 @@
 import {
     Location,
-    Model, Storylet, Template, Text, Choice as ChoiceModel, Choices, AssignmentGroup, Quality
+    Model, Storylet, Template, Text, Choice as ChoiceModel, Choices, AssignmentGroup, Quality, isSingular, normalize
 } from '@worldtreeengine/content.model';
 import {
     Runtime,
@@ -41,6 +41,7 @@ class LocalRuntimeSession implements RuntimeSession {
 
     private readonly pendingAssignments: AssignmentGroup[] = [];
     private pendingNavigationDestination?: string;
+    private readonly pendingStack: [string[], boolean][] = [];
 
     private readonly recentStorylets: number[] = [];
 
@@ -69,7 +70,7 @@ class LocalRuntimeSession implements RuntimeSession {
         }
 
         if (!this.storylet) {
-            await this.evaluateEligibleStorylets(transaction);
+            await this.evaluateNextStorylet(transaction);
         }
 
         while (this.storylet) {
@@ -93,6 +94,14 @@ class LocalRuntimeSession implements RuntimeSession {
                 this.pendingNavigationDestination = await evaluateConditional(this.storylet.navigation, this.model, transaction);
             }
 
+            if (this.storylet.push) {
+                this.pendingStack.push([await evaluateConditional(this.storylet.push, this.model, transaction), false]);
+            }
+
+            if (this.storylet.shift) {
+                this.pendingStack.push([await evaluateConditional(this.storylet.shift, this.model, transaction), true]);
+            }
+
             if (this.storylet.choices) {
                 const choices = await this.gatherChoices(this.storylet.choices, transaction);
                 if (choices.length) {
@@ -170,6 +179,14 @@ class LocalRuntimeSession implements RuntimeSession {
             this.pendingNavigationDestination = await evaluateConditional(choice.navigation, this.model, transaction);
         }
 
+        if (choice.push) {
+            this.pendingStack.push([await evaluateConditional(choice.push, this.model, transaction), false]);
+        }
+
+        if (choice.shift) {
+            this.pendingStack.push([await evaluateConditional(choice.shift, this.model, transaction), true]);
+        }
+
         const assignments = await this.commit(transaction);
         if (assignments.length) {
             if (this.staleAssignments) {
@@ -244,8 +261,9 @@ class LocalRuntimeSession implements RuntimeSession {
                     style: quality.style,
                 };
             } else if (quality.style?.currency) {
-                let label = effect.after === 1 && quality.singularLabel ? await evaluateTemplate(quality.singularLabel, this.model, transaction) :
-                    effect.after !== 1 && quality.pluralLabel ? await evaluateTemplate(quality.pluralLabel, this.model, transaction) :
+                let singular = isSingular(this.model.meta.lang, effect.after);
+                let label = singular && quality.singularLabel ? await evaluateTemplate(quality.singularLabel, this.model, transaction) :
+                    !singular && quality.pluralLabel ? await evaluateTemplate(quality.pluralLabel, this.model, transaction) :
                     quality.label ? await evaluateTemplate(quality.label, this.model, transaction) : [ quality.name ];
                 let value = effect.after;
                 let operation: 'increment' | 'decrement' = effect.after > effect.before ? 'increment' : 'decrement';
@@ -389,11 +407,23 @@ class LocalRuntimeSession implements RuntimeSession {
 
         this.pendingNavigationDestination = undefined;
 
+        // The running storylet has already left the stack, so shifted storylets take its place by running straight away,
+        // ahead of any that it pushes as follow-ups
+        for (const shifted of [false, true]) {
+            for (const [names, shift] of this.pendingStack) {
+                if (shift === shifted) {
+                    await transaction.pushStorylets(names);
+                }
+            }
+        }
+
+        this.pendingStack.splice(0);
+
         await transaction.commitStorylet();
         this.storylet = undefined;
         this.choices = undefined;
 
-        await this.evaluateEligibleStorylets(transaction);
+        await this.evaluateNextStorylet(transaction);
 
         return assignments;
     }
@@ -451,6 +481,7 @@ class LocalRuntimeSession implements RuntimeSession {
 
             this.pendingAssignments.splice(0);
             this.pendingNavigationDestination = undefined;
+            this.pendingStack.splice(0);
 
             this.staleBody = false;
             this.staleAssignments = false;
@@ -477,6 +508,20 @@ class LocalRuntimeSession implements RuntimeSession {
         }
     }
 
+    private async evaluateNextStorylet(transaction: Transaction): Promise<void> {
+        let name: string | undefined;
+        while ((name = await transaction.popStorylet()) !== undefined) {
+            const storylet = this.model.storylets.find((storylet) => normalize(storylet.name, this.model.meta.lang) === name);
+            if (storylet && (storylet.condition === undefined || await evaluateLogical(storylet.condition, this.model, transaction))) {
+                this.storylet = storylet;
+                await transaction.setStorylet(storylet.name);
+                return;
+            }
+        }
+
+        await this.evaluateEligibleStorylets(transaction);
+    }
+
     private async evaluateEligibleStorylets(transaction: Transaction): Promise<void> {
         const results: number[] = [];
 
@@ -2,11 +2,22 @@ import React from 'react';
 
 import './index.css';
 
+let bundledGameIcons: Record<string, string> | undefined;
+
+function gameIconUrl(name: string) {
+    if (!bundledGameIcons) {
+        const gameIconsJson = document.getElementById('game-icons')?.textContent;
+        bundledGameIcons = gameIconsJson ? JSON.parse(gameIconsJson) as Record<string, string> : {};
+    }
+
+    return bundledGameIcons[name] ?? `https://cdn.jsdelivr.net/gh/game-icons/icons@master/${name}.svg`;
+}
+
 export function Icon({ uri }: { uri: string}) {
     const url = React.useMemo(() => new URL(uri, document.location.toString()), [ uri ]);
 
     if (url.protocol === 'game-icons:') {
-        const maskIconUrl = `url(https://cdn.jsdelivr.net/gh/game-icons/icons@master/${url.pathname}.svg)`;
+        const maskIconUrl = `url(${gameIconUrl(url.pathname)})`;
         let maskIconColor = url.hash || undefined;
         if (maskIconColor && !maskIconColor.match(/^#[0-9a-f]{3}$|^#[0-9a-f]{6}$/)) {
             maskIconColor = maskIconColor.substring(1);
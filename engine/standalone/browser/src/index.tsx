@@ -3,7 +3,7 @@ import './index.css';
 import { createRoot } from 'react-dom/client';
 import React from 'react';
 import { Standalone } from './components/Standalone';
-import { Model } from '@worldtreeengine/content.model';
+import { decodeModel, Model } from '@worldtreeengine/content.model';
 import { LocalRuntimeDriver } from '@worldtreeengine/runtime.drivers.local-driver/src';
 import { LocalStorageStateDriver } from '@worldtreeengine/state.drivers.local-storage-driver/src';
 import { SessionProvider } from './hooks/useSession';
@@ -30,10 +30,12 @@ window.addEventListener('load', () => {
     const modelScript = document.getElementById('model');
 
     if (modelScript && modelScript.tagName.toLowerCase() === 'script') {
-        const modelJson = modelScript.textContent;
+        const modelContent = modelScript.textContent;
 
-        if (modelJson) {
-            const model = JSON.parse(modelJson) as Model;
+        if (modelContent) {
+            const model = modelScript.dataset.format === 'bytecode'
+                ? decodeModel(Uint8Array.from(atob(modelContent.trim()), (c) => c.charCodeAt(0)))
+                : JSON.parse(modelContent) as Model;
             const runtime = new LocalRuntimeDriver(model);
             const state = new LocalStorageStateDriver(stateKey || 'state');
 
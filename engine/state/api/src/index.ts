@@ -16,6 +16,8 @@ export interface Transaction {
     getStorylet(): Promise<string | undefined>;
     setStorylet(id: string): Promise<void>;
     commitStorylet(): Promise<void>;
+    pushStorylets(names: string[]): Promise<void>;
+    popStorylet(): Promise<string | undefined>;
     clear(): Promise<void>;
 }
 
@@ -83,6 +85,8 @@ export abstract class TransactionBase implements Transaction {
     abstract getStorylet(): Promise<string | undefined>;
     abstract setStorylet(name: string): Promise<void>;
     abstract commitStorylet(): Promise<void>;
+    abstract pushStorylets(names: string[]): Promise<void>;
+    abstract popStorylet(): Promise<string | undefined>;
 }
 
 export * from './serialized';
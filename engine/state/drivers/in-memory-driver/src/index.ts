@@ -3,6 +3,7 @@ import { SerializedState, TransactionBase } from '@worldtreeengine/state.api';
 export interface State {
     location?: string;
     storylet?: string;
+    stack?: string[];
     qualities: Record<string, number>;
 }
 
@@ -19,6 +20,7 @@ export class InMemoryStateDriver extends SerializedState<InMemoryTransaction> {
     getState(): State {
         return {
             ...this.state,
+            stack: this.state.stack && [...this.state.stack],
             qualities: {
                 ...this.state.qualities,
             },
@@ -28,6 +30,7 @@ export class InMemoryStateDriver extends SerializedState<InMemoryTransaction> {
     protected async begin(): Promise<InMemoryTransaction> {
         return new InMemoryTransaction({
             ...this.state,
+            stack: this.state.stack && [...this.state.stack],
             qualities: {
                 ...this.state.qualities,
             },
@@ -55,6 +58,7 @@ class InMemoryTransaction extends TransactionBase {
     getState(): State {
         return {
             ...this.state,
+            stack: this.state.stack && [...this.state.stack],
             qualities: {
                 ...this.state.qualities,
             },
@@ -107,4 +111,12 @@ class InMemoryTransaction extends TransactionBase {
         await this.commitStorylet();
         this.state.storylet = id;
     }
+
+    async pushStorylets(names: string[]): Promise<void> {
+        this.state.stack = [...(this.state.stack || []), ...[...names].reverse()];
+    }
+
+    async popStorylet(): Promise<string | undefined> {
+        return this.state.stack?.pop();
+    }
 }
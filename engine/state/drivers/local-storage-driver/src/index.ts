@@ -22,6 +22,13 @@ export class LocalStorageStateDriver extends InMemoryStateDriver {
                         initialState.storylet = parsedState.storylet;
                     }
 
+                    if ('stack' in parsedState && Array.isArray(parsedState.stack)) {
+                        const stack = parsedState.stack.filter((name: unknown) => typeof name === 'string');
+                        if (stack.length) {
+                            initialState.stack = stack;
+                        }
+                    }
+
                     if ('qualities' in parsedState && typeof parsedState.qualities === 'object') {
                         for (const key in parsedState.qualities) {
                             const value = parsedState.qualities[key];
@@ -47,6 +54,7 @@ export class LocalStorageStateDriver extends InMemoryStateDriver {
         const stateToStore: {
             location?: string;
             storylet?: string;
+            stack?: string[];
             qualities: Record<string, number>;
         } = {
             qualities: {},
@@ -60,6 +68,10 @@ export class LocalStorageStateDriver extends InMemoryStateDriver {
             stateToStore.storylet = state.storylet;
         }
 
+        if (state.stack?.length) {
+            stateToStore.stack = state.stack;
+        }
+
         for (const key in state.qualities) {
             let value = state.qualities[key];
             if (value > 0) {